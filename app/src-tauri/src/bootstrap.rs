@@ -30,17 +30,27 @@ pub fn run() {
                 }
                 let _ = window.show();
             }
+            std::thread::spawn(|| crate::fs_ops_undo_trash::enforce_undo_trash_limits(&[]));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             crate::fs_query_cmds::fs_list_dir,
             crate::fs_query_cmds::fs_list_dir_by_ref,
+            crate::fs_query_cmds::fs_list_dir_stream,
+            crate::fs_query_cmds::fs_list_dir_page,
             crate::fs_ops_transfer_copy::fs_copy,
             crate::fs_ops_transfer_copy::fs_copy_pairs,
             crate::fs_ops_transfer_move::fs_move,
             crate::fs_ops_transfer_helpers::op_cancel,
+            crate::fs_ops_sync::fs_sync_plan,
+            crate::fs_ops_sync::fs_sync,
+            crate::fs_ops_split::fs_split_file,
+            crate::fs_ops_split::fs_join_file,
             crate::fs_ops_create::fs_create,
+            crate::fs_ops_template::fs_list_templates,
+            crate::fs_ops_template::fs_create_from_template,
             crate::fs_ops_rename::fs_rename,
+            crate::fs_ops_attributes::fs_set_attributes,
             crate::fs_query_cmds::fs_read_text,
             crate::fs_query_cmds::fs_read_text_by_ref,
             crate::fs_query_cmds::fs_text_viewport_info,
@@ -56,17 +66,37 @@ pub fn run() {
             crate::fs_ops_archive::zip_create,
             crate::fs_ops_archive::zip_extract,
             crate::fs_ops_archive::zip_extract_list_conflicts,
+            crate::fs_ops_archive_cmds::archive_create,
+            crate::fs_ops_archive_cmds::archive_extract,
+            crate::fs_ops_archive_cmds::archive_extract_list_conflicts,
+            crate::fs_ops_archive_cmds::archive_list,
+            crate::fs_ops_archive_cmds::archive_test,
+            crate::fs_ops_archive_update::zip_update,
             crate::fs_query_cmds::fs_get_properties,
             crate::fs_query_cmds::fs_get_properties_by_ref,
             crate::fs_query_cmds::fs_get_capabilities,
             crate::fs_query_cmds::fs_get_capabilities_by_ref,
             crate::fs_query_cmds::fs_dir_stats,
+            crate::fs_compare_cmds::fs_compare_dirs,
+            crate::fs_ops_duplicates::fs_find_duplicates,
+            crate::fs_ops_duplicates::fs_replace_with_hardlinks,
             crate::fs_ops_delete::fs_delete_trash,
             crate::fs_ops_delete::fs_delete_with_undo,
+            crate::fs_ops_shred::fs_delete_shred,
+            crate::fs_ops_undo_trash::undo_trash_list,
+            crate::fs_ops_undo_trash::undo_trash_restore,
+            crate::fs_ops_undo_trash::undo_trash_purge,
+            crate::fs_ops_system_trash::system_trash_list,
+            crate::fs_ops_system_trash::system_trash_restore,
+            crate::fs_ops_system_trash::system_trash_purge,
             crate::config_cmds::config_get,
             crate::config_cmds::config_get_startup,
             crate::config_cmds::undo_redo_load_session,
             crate::config_cmds::undo_redo_save_session,
+            crate::undo_journal_cmds::undo_journal_get,
+            crate::undo_journal_cmds::undo_journal_clear,
+            crate::undo_journal_cmds::undo_apply,
+            crate::undo_journal_cmds::redo_apply,
             crate::config_cmds::config_get_path,
             crate::config_cmds::config_open_in_editor,
             crate::config_cmds::config_create_backup,
@@ -3,7 +3,7 @@ use crate::config_io::legacy_config_path;
 use crate::config_types::{AppConfig, Language};
 use std::path::PathBuf;
 
-pub use crate::config_io::{config_path, save_config, save_history, save_jump_list};
+pub use crate::config_io::{config_path, save_config, save_history, save_jump_list, templates_dir};
 
 struct InstallerLanguageHint {
     language: Language,
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 
 use crate::config_io::{config_path, load_history, load_jump_list};
 use crate::config_types::{
-    AppConfig, FileIconMode, KeymapProfile, Language, SortKey, SortOrder, Theme,
+    AppConfig, FileIconMode, KeymapProfile, Language, SortKey, SortOrder, Theme, ZipMethod,
 };
 
 pub fn default_app_config() -> AppConfig {
@@ -44,6 +44,17 @@ pub fn default_app_config() -> AppConfig {
         external_terminal_profile_cmd: String::new(),
         external_terminal_profile_powershell: String::new(),
         external_terminal_profile_wsl: String::new(),
+        undo_trash_max_age_days: 30,
+        undo_trash_max_size_mb: 2048,
+        archive_max_total_size_mb: 32768,
+        archive_max_entries: 100_000,
+        archive_max_ratio: 1000,
+        archive_max_path_depth: 64,
+        archive_zip_method: ZipMethod::Deflate,
+        archive_zip_level: 0,
+        archive_zip_exclude: Vec::new(),
+        archive_zip_follow_symlinks: true,
+        archive_zip_cp932_names: false,
         log_path: default_log_path().to_string_lossy().to_string(),
         log_enabled: true,
     }
@@ -133,6 +144,9 @@ pub fn normalize_config(mut config: AppConfig) -> AppConfig {
     if matches!(config.input_keymap_profile, KeymapProfile::Unknown) {
         config.input_keymap_profile = KeymapProfile::default();
     }
+    if matches!(config.archive_zip_method, ZipMethod::Unknown) {
+        config.archive_zip_method = ZipMethod::default();
+    }
 
     config.external_vscode_path = normalize_executable_path(&config.external_vscode_path);
     config.external_git_client_path = normalize_executable_path(&config.external_git_client_path);
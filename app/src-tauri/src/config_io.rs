@@ -21,6 +21,10 @@ pub fn legacy_config_path() -> PathBuf {
     appdata_base().join("ReflexFIles").join("config.json")
 }
 
+pub fn templates_dir() -> PathBuf {
+    appdata_base().join("ReflexFIles").join("templates")
+}
+
 fn history_path() -> PathBuf {
     appdata_base().join("ReflexFIles").join("history.toml")
 }
@@ -208,6 +212,56 @@ fn localize_config_comments_to_en(mut text: String) -> String {
             "# Default associations (extension -> app id).\n",
         ),
         ("# 外部アプリ定義。\n", "# External app definitions.\n"),
+        ("# --- 元に戻す用ごみ箱 ---\n", "# --- Undo Trash ---\n"),
+        (
+            "# 元に戻す用ごみ箱の保持日数。0 で無期限。\n",
+            "# Days to keep undo trash items. 0 keeps them forever.\n",
+        ),
+        (
+            "# 元に戻す用ごみ箱の合計サイズ上限 (MB)。0 で無制限。\n",
+            "# Total size limit of the undo trash in MB. 0 means unlimited.\n",
+        ),
+        (
+            "# --- アーカイブ展開 ---\n",
+            "# --- Archive Extraction ---\n",
+        ),
+        (
+            "# 展開後の合計サイズ上限 (MB)。0 で無制限。\n",
+            "# Limit on the total extracted size in MB. 0 means unlimited.\n",
+        ),
+        (
+            "# 展開するエントリ数の上限。0 で無制限。\n",
+            "# Limit on the number of extracted entries. 0 means unlimited.\n",
+        ),
+        (
+            "# エントリごとの圧縮率 (展開後/圧縮後) の上限。0 で無制限。\n",
+            "# Limit on each entry's compression ratio (extracted/packed). 0 means unlimited.\n",
+        ),
+        (
+            "# エントリパスの階層数の上限。0 で無制限。\n",
+            "# Limit on the folder depth of entry paths. 0 means unlimited.\n",
+        ),
+        ("# --- ZIP 作成 ---\n", "# --- ZIP Creation ---\n"),
+        (
+            "# 圧縮方式: deflate | store | zstd | bzip2。\n",
+            "# Compression method: deflate | store | zstd | bzip2.\n",
+        ),
+        (
+            "# 圧縮レベル (deflate/bzip2: 1-9, zstd: 1-22)。0 で方式ごとの既定値。\n",
+            "# Compression level (deflate/bzip2: 1-9, zstd: 1-22). 0 uses the method default.\n",
+        ),
+        (
+            "# 圧縮時に除外するパターン (例: \".git\", \"node_modules\", \"*.tmp\")。\n",
+            "# Patterns left out when compressing (e.g. \".git\", \"node_modules\", \"*.tmp\").\n",
+        ),
+        (
+            "# シンボリックリンクの参照先を格納するか (既定 true)。false ならリンク自体を格納。\n",
+            "# Store the targets of symlinks. false stores the links themselves.\n",
+        ),
+        (
+            "# エントリ名を CP932 (Shift_JIS) で書き込むか。古い展開ツール向け。\n",
+            "# Write entry names in CP932 (Shift_JIS) for older unzip tools.\n",
+        ),
         ("# --- ログ ---\n", "# --- Logging ---\n"),
         ("# ログファイルパス。\n", "# Log file path.\n"),
         ("# ログ出力を有効化するか。\n", "# Enable log output.\n"),
@@ -272,6 +326,14 @@ pub fn save_config(config: &AppConfig) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
+    let out = config_text(config)?;
+    let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
+    file.write_all(out.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The commented TOML `save_config` writes, in the configured UI language.
+fn config_text(config: &AppConfig) -> Result<String, String> {
     let mut out = String::new();
     out.push_str("# ReflexFIles 設定ファイル\n");
     out.push_str("# 各項目の上に説明コメントがあります。\n\n");
@@ -464,6 +526,75 @@ pub fn save_config(config: &AppConfig) -> Result<(), String> {
         Value::try_from(&config.external_apps).map_err(|e| e.to_string())?
     ));
 
+    out.push_str("# --- 元に戻す用ごみ箱 ---\n");
+    out.push_str("# 元に戻す用ごみ箱の保持日数。0 で無期限。\n");
+    out.push_str(&format!(
+        "undo_trash_max_age_days = {}\n\n",
+        config.undo_trash_max_age_days
+    ));
+    out.push_str("# 元に戻す用ごみ箱の合計サイズ上限 (MB)。0 で無制限。\n");
+    out.push_str(&format!(
+        "undo_trash_max_size_mb = {}\n\n",
+        config.undo_trash_max_size_mb
+    ));
+
+    out.push_str("# --- アーカイブ展開 ---\n");
+    out.push_str("# 展開後の合計サイズ上限 (MB)。0 で無制限。\n");
+    out.push_str(&format!(
+        "archive_max_total_size_mb = {}\n\n",
+        config.archive_max_total_size_mb
+    ));
+    out.push_str("# 展開するエントリ数の上限。0 で無制限。\n");
+    out.push_str(&format!(
+        "archive_max_entries = {}\n\n",
+        config.archive_max_entries
+    ));
+    out.push_str("# エントリごとの圧縮率 (展開後/圧縮後) の上限。0 で無制限。\n");
+    out.push_str(&format!(
+        "archive_max_ratio = {}\n\n",
+        config.archive_max_ratio
+    ));
+    out.push_str("# エントリパスの階層数の上限。0 で無制限。\n");
+    out.push_str(&format!(
+        "archive_max_path_depth = {}\n\n",
+        config.archive_max_path_depth
+    ));
+
+    out.push_str("# --- ZIP 作成 ---\n");
+    out.push_str("# 圧縮方式: deflate | store | zstd | bzip2。\n");
+    out.push_str(&format!(
+        "archive_zip_method = {}\n\n",
+        Value::String(config.archive_zip_method.as_str().to_string())
+    ));
+    out.push_str("# 圧縮レベル (deflate/bzip2: 1-9, zstd: 1-22)。0 で方式ごとの既定値。\n");
+    out.push_str(&format!(
+        "archive_zip_level = {}\n\n",
+        config.archive_zip_level
+    ));
+    out.push_str("# 圧縮時に除外するパターン (例: \".git\", \"node_modules\", \"*.tmp\")。\n");
+    out.push_str(&format!(
+        "archive_zip_exclude = {}\n\n",
+        Value::Array(
+            config
+                .archive_zip_exclude
+                .iter()
+                .map(|s| Value::String(s.to_string()))
+                .collect::<Vec<_>>()
+        )
+    ));
+    out.push_str(
+        "# シンボリックリンクの参照先を格納するか (既定 true)。false ならリンク自体を格納。\n",
+    );
+    out.push_str(&format!(
+        "archive_zip_follow_symlinks = {}\n\n",
+        config.archive_zip_follow_symlinks
+    ));
+    out.push_str("# エントリ名を CP932 (Shift_JIS) で書き込むか。古い展開ツール向け。\n");
+    out.push_str(&format!(
+        "archive_zip_cp932_names = {}\n\n",
+        config.archive_zip_cp932_names
+    ));
+
     out.push_str("# --- ログ ---\n");
     out.push_str("# ログファイルパス。\n");
     out.push_str(&format!("log_path = {}\n\n", toml_string(&config.log_path)));
@@ -488,8 +619,26 @@ pub fn save_config(config: &AppConfig) -> Result<(), String> {
     if !matches!(config.ui_language, Language::Ja) {
         out = localize_config_comments_to_en(out);
     }
+    Ok(out)
+}
 
-    let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
-    file.write_all(out.as_bytes()).map_err(|e| e.to_string())?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::{config_text, Language};
+    use crate::config_defaults::default_app_config;
+
+    #[test]
+    fn english_config_has_every_comment_translated() {
+        let mut config = default_app_config();
+        config.ui_language = Language::En;
+        let text = config_text(&config).expect("config text");
+        let untranslated: Vec<&str> = text
+            .lines()
+            .filter(|line| line.starts_with('#') && !line.is_ascii())
+            .collect();
+        assert!(untranslated.is_empty(), "{untranslated:#?}");
+        assert!(
+            text.contains("# Store the targets of symlinks. false stores the links themselves.")
+        );
+    }
 }
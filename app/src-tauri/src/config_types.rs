@@ -1,4 +1,4 @@
 pub use crate::types::{
     AppConfig, FileIconMode, HistoryFile, JumpItem, JumpListFile, KeymapProfile, Language, SortKey,
-    SortOrder, Theme,
+    SortOrder, Theme, ZipMethod,
 };
@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::error::{AppError, AppErrorKind, AppResult};
+use crate::fs_ops_transfer_helpers::io_error_code;
+use crate::storage_provider::resolve_legacy_path;
+use crate::types::{
+    CompareCounts, CompareNode, CompareOptions, CompareResult, CompareSide, CompareStatus,
+};
+use crate::utils::{matches_any_glob, mtimes_match, system_time_to_rfc3339};
+
+const COMPARE_CHUNK_SIZE: usize = 64 * 1024;
+
+fn read_children(dir: &Path) -> io::Result<BTreeMap<String, Metadata>> {
+    let mut out = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Links are not followed; they are compared by their link text (`read_link`).
+        let meta = fs::symlink_metadata(entry.path())?;
+        out.insert(entry.file_name().to_string_lossy().to_string(), meta);
+    }
+    Ok(out)
+}
+
+fn side_from(meta: &Metadata) -> CompareSide {
+    CompareSide {
+        is_dir: meta.is_dir(),
+        is_link: meta.file_type().is_symlink(),
+        size: if meta.is_dir() { 0 } else { meta.len() },
+        modified: system_time_to_rfc3339(meta.modified().ok()),
+    }
+}
+
+fn read_full(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let read = file.read(&mut buf[filled..])?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+fn contents_equal(left: &Path, right: &Path) -> io::Result<bool> {
+    let mut left_file = File::open(left)?;
+    let mut right_file = File::open(right)?;
+    let mut left_buf = vec![0u8; COMPARE_CHUNK_SIZE];
+    let mut right_buf = vec![0u8; COMPARE_CHUNK_SIZE];
+    loop {
+        let left_read = read_full(&mut left_file, &mut left_buf)?;
+        let right_read = read_full(&mut right_file, &mut right_buf)?;
+        if left_read != right_read || left_buf[..left_read] != right_buf[..right_read] {
+            return Ok(false);
+        }
+        if left_read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+fn files_equal(
+    left: &Path,
+    right: &Path,
+    left_meta: &Metadata,
+    right_meta: &Metadata,
+    options: &CompareOptions,
+) -> io::Result<bool> {
+    let left_link = left_meta.file_type().is_symlink();
+    if left_link || right_meta.file_type().is_symlink() {
+        return Ok(left_link
+            && right_meta.file_type().is_symlink()
+            && fs::read_link(left)? == fs::read_link(right)?);
+    }
+    if left_meta.len() != right_meta.len() {
+        return Ok(false);
+    }
+    if options.content {
+        contents_equal(left, right)
+    } else {
+        Ok(mtimes_match(
+            left_meta.modified().ok(),
+            right_meta.modified().ok(),
+        ))
+    }
+}
+
+/// Builds a one-sided subtree (left-only or right-only) and counts its files.
+fn one_sided_node(
+    path: &Path,
+    meta: &Metadata,
+    name: &str,
+    rel: &str,
+    status: CompareStatus,
+    options: &CompareOptions,
+    counts: &mut CompareCounts,
+) -> CompareNode {
+    let mut children = Vec::new();
+    let mut error = None;
+    if meta.is_dir() {
+        match read_children(path) {
+            Ok(entries) => {
+                for (child_name, child_meta) in entries {
+                    let child_rel = format!("{}/{}", rel, child_name);
+                    if matches_any_glob(&options.ignore, &child_rel) {
+                        continue;
+                    }
+                    children.push(one_sided_node(
+                        &path.join(&child_name),
+                        &child_meta,
+                        &child_name,
+                        &child_rel,
+                        status,
+                        options,
+                        counts,
+                    ));
+                }
+            }
+            Err(err) => error = Some(node_error(err, counts)),
+        }
+    } else if status == CompareStatus::LeftOnly {
+        counts.left_only += 1;
+    } else {
+        counts.right_only += 1;
+    }
+    let side = Some(side_from(meta));
+    let (left, right) = if status == CompareStatus::LeftOnly {
+        (side, None)
+    } else {
+        (None, side)
+    };
+    CompareNode {
+        name: name.to_string(),
+        rel_path: rel.to_string(),
+        status,
+        left,
+        right,
+        children,
+        error,
+    }
+}
+
+fn node_error(err: io::Error, counts: &mut CompareCounts) -> String {
+    counts.errors += 1;
+    format!("code={}; {}", io_error_code(&err), err)
+}
+
+fn compare_children(
+    left: &Path,
+    right: &Path,
+    prefix: &str,
+    options: &CompareOptions,
+    counts: &mut CompareCounts,
+) -> io::Result<Vec<CompareNode>> {
+    let left_children = read_children(left)?;
+    let right_children = read_children(right)?;
+    let mut names: Vec<&String> = left_children.keys().chain(right_children.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    let mut nodes = Vec::with_capacity(names.len());
+    for name in names {
+        let rel = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        if matches_any_glob(&options.ignore, &rel) {
+            continue;
+        }
+        let left_path = left.join(name);
+        let right_path = right.join(name);
+        let node = match (left_children.get(name), right_children.get(name)) {
+            (Some(meta), None) => one_sided_node(
+                &left_path,
+                meta,
+                name,
+                &rel,
+                CompareStatus::LeftOnly,
+                options,
+                counts,
+            ),
+            (None, Some(meta)) => one_sided_node(
+                &right_path,
+                meta,
+                name,
+                &rel,
+                CompareStatus::RightOnly,
+                options,
+                counts,
+            ),
+            (Some(left_meta), Some(right_meta)) => {
+                let mut error = None;
+                // Anything that could not be read counts as different.
+                let (status, children) = if left_meta.is_dir() && right_meta.is_dir() {
+                    match compare_children(&left_path, &right_path, &rel, options, counts) {
+                        Ok(children) => {
+                            let all_identical = children
+                                .iter()
+                                .all(|child| child.status == CompareStatus::Identical);
+                            let status = if all_identical {
+                                CompareStatus::Identical
+                            } else {
+                                CompareStatus::Different
+                            };
+                            (status, children)
+                        }
+                        Err(err) => {
+                            error = Some(node_error(err, counts));
+                            (CompareStatus::Different, Vec::new())
+                        }
+                    }
+                } else if left_meta.is_dir() != right_meta.is_dir() {
+                    counts.different += 1;
+                    (CompareStatus::Different, Vec::new())
+                } else {
+                    match files_equal(&left_path, &right_path, left_meta, right_meta, options) {
+                        Ok(true) => {
+                            counts.identical += 1;
+                            (CompareStatus::Identical, Vec::new())
+                        }
+                        Ok(false) => {
+                            counts.different += 1;
+                            (CompareStatus::Different, Vec::new())
+                        }
+                        Err(err) => {
+                            error = Some(node_error(err, counts));
+                            (CompareStatus::Different, Vec::new())
+                        }
+                    }
+                };
+                CompareNode {
+                    name: name.clone(),
+                    rel_path: rel,
+                    status,
+                    left: Some(side_from(left_meta)),
+                    right: Some(side_from(right_meta)),
+                    children,
+                    error,
+                }
+            }
+            (None, None) => continue,
+        };
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+fn resolve_compare_dir(path: &str) -> AppResult<std::path::PathBuf> {
+    let resolved = resolve_legacy_path(path)?;
+    if !resolved.is_dir() {
+        return Err(AppError::with_kind(
+            AppErrorKind::NotFound,
+            format!("folder not found: {}", resolved.display()),
+        ));
+    }
+    Ok(resolved)
+}
+
+pub(crate) fn fs_compare_dirs_impl(
+    left: String,
+    right: String,
+    options: CompareOptions,
+) -> AppResult<CompareResult> {
+    let left_path = resolve_compare_dir(&left)?;
+    let right_path = resolve_compare_dir(&right)?;
+    let mut counts = CompareCounts::default();
+    let children = compare_children(&left_path, &right_path, "", &options, &mut counts)?;
+    Ok(CompareResult {
+        left: left_path.to_string_lossy().to_string(),
+        right: right_path.to_string_lossy().to_string(),
+        children,
+        counts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fs_compare_dirs_impl, one_sided_node};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{CompareCounts, CompareOptions, CompareStatus};
+    use std::fs;
+
+    #[test]
+    fn compare_dirs_marks_statuses_and_honors_ignore() {
+        let root = unique_temp_dir("rf-compare");
+        let (left, right) = (root.join("left"), root.join("right"));
+        fs::create_dir_all(left.join("sub")).expect("create left");
+        fs::create_dir_all(right.join("sub")).expect("create right");
+        fs::create_dir_all(left.join("cache")).expect("create ignored");
+        fs::write(left.join("cache").join("x.bin"), b"x").expect("write ignored");
+        fs::write(left.join("only.txt"), b"l").expect("write left only");
+        fs::write(right.join("extra.txt"), b"r").expect("write right only");
+        fs::write(left.join("sub").join("same.txt"), b"same").expect("write same");
+        fs::write(right.join("sub").join("same.txt"), b"same").expect("write same");
+        fs::write(left.join("sub").join("diff.txt"), b"aaaa").expect("write diff");
+        fs::write(right.join("sub").join("diff.txt"), b"bbbb").expect("write diff");
+
+        let options = CompareOptions {
+            content: true,
+            ignore: vec!["cache".to_string()],
+        };
+        let result = fs_compare_dirs_impl(
+            left.to_string_lossy().to_string(),
+            right.to_string_lossy().to_string(),
+            options,
+        )
+        .expect("compare");
+
+        let top: Vec<(&str, CompareStatus)> = result
+            .children
+            .iter()
+            .map(|node| (node.name.as_str(), node.status))
+            .collect();
+        assert_eq!(
+            top,
+            vec![
+                ("extra.txt", CompareStatus::RightOnly),
+                ("only.txt", CompareStatus::LeftOnly),
+                ("sub", CompareStatus::Different),
+            ]
+        );
+        let sub = &result.children[2].children;
+        assert_eq!(sub[0].rel_path, "sub/diff.txt");
+        assert_eq!(sub[0].status, CompareStatus::Different);
+        assert_eq!(sub[1].status, CompareStatus::Identical);
+        assert_eq!(result.counts.left_only, 1);
+        assert_eq!(result.counts.right_only, 1);
+        assert_eq!(result.counts.identical, 1);
+        assert_eq!(result.counts.different, 1);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compare_dirs_compares_links_as_links_and_keeps_going_past_unreadable_folders() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let root = unique_temp_dir("rf-compare-links");
+        let (left, right) = (root.join("left"), root.join("right"));
+        for side in [&left, &right] {
+            fs::create_dir_all(side.join("locked")).expect("create locked");
+            fs::write(side.join("locked").join("a.txt"), b"a").expect("write locked");
+            fs::write(side.join("target.txt"), side.to_string_lossy().as_bytes())
+                .expect("write target");
+            symlink("target.txt", side.join("same-link")).expect("same link");
+            symlink("missing", side.join("dangling")).expect("dangling link");
+        }
+        symlink("target.txt", left.join("moved-link")).expect("left link");
+        symlink("missing", right.join("moved-link")).expect("right link");
+        fs::set_permissions(left.join("locked"), fs::Permissions::from_mode(0o000)).expect("lock");
+        // Root ignores permissions, so there is nothing unreadable to report.
+        let locked = fs::read_dir(left.join("locked")).is_err();
+
+        let result = fs_compare_dirs_impl(
+            left.to_string_lossy().to_string(),
+            right.to_string_lossy().to_string(),
+            CompareOptions {
+                content: true,
+                ignore: Vec::new(),
+            },
+        )
+        .expect("compare");
+        fs::set_permissions(left.join("locked"), fs::Permissions::from_mode(0o755))
+            .expect("unlock");
+
+        let top: Vec<(&str, CompareStatus)> = result
+            .children
+            .iter()
+            .map(|node| (node.name.as_str(), node.status))
+            .collect();
+        assert_eq!(
+            top,
+            vec![
+                ("dangling", CompareStatus::Identical),
+                (
+                    "locked",
+                    if locked {
+                        CompareStatus::Different
+                    } else {
+                        CompareStatus::Identical
+                    }
+                ),
+                ("moved-link", CompareStatus::Different),
+                ("same-link", CompareStatus::Identical),
+                ("target.txt", CompareStatus::Different),
+            ]
+        );
+        assert!(result.children[0]
+            .left
+            .as_ref()
+            .is_some_and(|side| side.is_link));
+        assert_eq!(result.children[1].error.is_some(), locked);
+        assert_eq!(result.counts.errors, u64::from(locked));
+
+        // A folder that vanished between listing and reading is reported on its node.
+        let mut counts = CompareCounts::default();
+        let node = one_sided_node(
+            &left.join("vanished"),
+            &fs::symlink_metadata(&left).expect("folder metadata"),
+            "vanished",
+            "vanished",
+            CompareStatus::LeftOnly,
+            &CompareOptions::default(),
+            &mut counts,
+        );
+        assert!(node
+            .error
+            .is_some_and(|err| err.starts_with("code=not_found")));
+        assert_eq!(counts.errors, 1);
+        let _ = fs::remove_dir_all(root);
+    }
+}
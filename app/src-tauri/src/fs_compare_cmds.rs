@@ -0,0 +1,12 @@
+use crate::fs_compare::fs_compare_dirs_impl;
+use crate::types::{CompareOptions, CompareResult};
+
+#[tauri::command]
+pub fn fs_compare_dirs(
+    left: String,
+    right: String,
+    options: Option<CompareOptions>,
+) -> Result<CompareResult, String> {
+    fs_compare_dirs_impl(left, right, options.unwrap_or_default())
+        .map_err(|err| format!("code={}; {}", err.code(), err))
+}
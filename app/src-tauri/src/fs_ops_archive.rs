@@ -1,17 +1,39 @@
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use tauri::AppHandle;
+use zip::extra_fields::ExtraField;
+use zip::read::ZipFile;
 use zip::result::ZipError;
-use zip::write::SimpleFileOptions;
-use zip::{AesMode, CompressionMethod, ZipArchive, ZipWriter};
+use zip::write::FullFileOptions;
+use zip::{AesMode, CompressionMethod, DateTime, HasZipMetadata, ZipArchive, ZipWriter};
 
 use crate::error::{format_error, AppErrorKind};
-use crate::fs_ops_preflight::{preflight_zip_create, preflight_zip_extract};
-use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::fs_ops_archive_cmds::{archive_create, archive_extract, archive_extract_list_conflicts};
+use crate::fs_ops_archive_limits::{ExtractLimits, LimitGuard, SizeExceeded, ARCHIVE_LIMIT_CODE};
+use crate::fs_ops_archive_names::{write_cp932_names, zip_entry_names};
+use crate::fs_ops_archive_split::{check_split_space, check_volume_size, split_zip, ZipVolumes};
+use crate::fs_ops_attributes::set_times;
+use crate::fs_ops_mutate_helpers::{plan_conflict, ConflictAction};
+use crate::fs_ops_transfer_helpers::{cancel_requested, io_error_code};
+use crate::types::{
+    ArchiveExtractOptions, ArchiveFormat, ArchiveListEntry, ArchiveOp, ArchiveProgress,
+    ArchiveSummary, ArchiveTestEntry, ArchiveTestReport, ConflictPolicy, ZipCreateOptions,
+    ZipMethod, ZipNameEncoding,
+};
+use crate::utils::{glob_match, matches_any_glob, system_time_to_rfc3339};
 
-fn zip_safe_path(base: &Path, name: &str) -> Result<PathBuf, String> {
+const ARCHIVE_COPY_CHUNK: usize = 256 * 1024;
+const ARCHIVE_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Files this large need zip64 headers written up front.
+const ZIP64_THRESHOLD: u64 = 0xFFFF_FFFF;
+/// Info-ZIP extended timestamp extra field, holding the modification time in UTC seconds.
+const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+
+pub(crate) fn zip_safe_path(base: &Path, name: &str) -> Result<PathBuf, String> {
     let mut out = PathBuf::from(base);
     for comp in Path::new(name).components() {
         match comp {
@@ -28,198 +50,971 @@ fn zip_safe_path(base: &Path, name: &str) -> Result<PathBuf, String> {
     Ok(out)
 }
 
-fn zip_add_path(
-    writer: &mut ZipWriter<fs::File>,
-    src: &Path,
-    base_name: &Path,
-    password: &Option<String>,
-) -> Result<(), String> {
-    if src.is_dir() {
-        let name = base_name.to_string_lossy().replace('\\', "/") + "/";
-        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
-        writer
-            .add_directory(name, options)
-            .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-        for entry in fs::read_dir(src).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))? {
-            let entry = entry.map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-            let path = entry.path();
-            let rel = base_name.join(entry.file_name());
-            zip_add_path(writer, &path, &rel, password)?;
-        }
+pub(crate) fn invalid_entry() -> String {
+    format_error(AppErrorKind::InvalidPath, "invalid path in archive entry")
+}
+
+/// Fails unless `path` (which must exist) really lies inside `root`, so entries cannot be
+/// written through links that point elsewhere.
+pub(crate) fn ensure_inside(root: &Path, path: &Path) -> Result<(), String> {
+    let real = path.canonicalize().map_err(io_failure)?;
+    if real.starts_with(root) {
+        Ok(())
     } else {
-        let name = base_name.to_string_lossy().replace('\\', "/");
-        let mut options =
-            SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
-        if let Some(pass) = password {
-            options = options.with_aes_encryption(AesMode::Aes256, pass);
-        }
-        writer
-            .start_file(name, options)
-            .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-        let mut file =
-            fs::File::open(src).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)
-            .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-        writer
-            .write_all(&buffer)
-            .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+        Err(invalid_entry())
+    }
+}
+
+/// Creates the parent folders of a file entry inside `root` and removes a link already
+/// sitting at `outpath`, so the entry is never written through it.
+pub(crate) fn prepare_file_target(root: &Path, outpath: &Path) -> Result<(), String> {
+    let parent = outpath.parent().ok_or_else(invalid_entry)?;
+    fs::create_dir_all(parent).map_err(io_failure)?;
+    ensure_inside(root, parent)?;
+    if let Ok(meta) = fs::symlink_metadata(outpath) {
+        if meta.file_type().is_symlink() {
+            fs::remove_file(outpath).map_err(io_failure)?;
+        }
     }
     Ok(())
 }
 
-#[tauri::command]
-pub fn zip_create(
-    items: Vec<String>,
-    destination: String,
-    password: Option<String>,
-) -> Result<(), String> {
-    let started = Instant::now();
-    let resolved_destination =
-        resolve_legacy_path_for(&destination, ProviderCapability::ArchiveCreate).map_err(
-            |err| {
-                crate::log_error(
-                    "zip_create",
-                    "batch",
-                    &destination,
-                    &format!("code={}; {}", err.code(), err),
-                );
-                format!("code={}; {}", err.code(), err)
+pub(crate) fn set_file_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (path, mode);
+        Ok(())
+    }
+}
+
+/// Which entries to extract, by exact name, folder or glob, and how many leading folders
+/// to drop from their names. The default extracts everything as stored.
+#[derive(Default)]
+pub(crate) struct ExtractFilter {
+    patterns: Vec<String>,
+    strip: usize,
+}
+
+impl ExtractFilter {
+    pub(crate) fn new(entries: &[String], strip_components: u32) -> Self {
+        Self {
+            patterns: entries
+                .iter()
+                .map(|pattern| {
+                    pattern
+                        .trim()
+                        .trim_start_matches("./")
+                        .trim_end_matches('/')
+                        .to_string()
+                })
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+            strip: strip_components as usize,
+        }
+    }
+
+    /// Patterns with a `/` match the whole name, others only its last segment. Naming a
+    /// folder selects everything below it.
+    fn selects(&self, name: &str) -> bool {
+        if self.patterns.is_empty() {
+            return true;
+        }
+        let name = name.trim_start_matches("./").trim_end_matches('/');
+        let base = name.rsplit('/').next().unwrap_or(name);
+        self.patterns.iter().any(|pattern| {
+            name == pattern
+                || name
+                    .strip_prefix(pattern.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+                || if pattern.contains('/') {
+                    glob_match(pattern, name)
+                } else {
+                    glob_match(pattern, base)
+                }
+        })
+    }
+
+    /// `name` without its first `strip` components; `None` when nothing is left.
+    pub(crate) fn strip_name(&self, name: &str) -> Option<String> {
+        let rest: Vec<&str> = name
+            .split('/')
+            .filter(|part| !part.is_empty() && *part != ".")
+            .skip(self.strip)
+            .collect();
+        (!rest.is_empty()).then(|| rest.join("/"))
+    }
+
+    /// Name to extract the entry under, or `None` when it is not selected or stripped away.
+    /// The result still has to go through `zip_safe_path`.
+    pub(crate) fn target(&self, name: &str) -> Option<String> {
+        if self.selects(name) {
+            self.strip_name(name)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where extracted entries go. Existing files are handled by the conflict policy; a staged
+/// plan writes into a hidden sibling folder and moves the result into `dest` at the end.
+pub(crate) struct ExtractPlan {
+    dest: PathBuf,
+    root: PathBuf,
+    policy: ConflictPolicy,
+    staged: bool,
+    limits: ExtractLimits,
+}
+
+impl ExtractPlan {
+    pub(crate) fn new(dest: &Path, policy: ConflictPolicy, staged: bool) -> Result<Self, String> {
+        if !staged {
+            fs::create_dir_all(dest).map_err(io_failure)?;
+            let root = dest.canonicalize().map_err(io_failure)?;
+            return Ok(ExtractPlan {
+                dest: root.clone(),
+                root,
+                policy,
+                staged,
+                limits: ExtractLimits::default(),
+            });
+        }
+        let (Some(parent), Some(name)) = (dest.parent(), dest.file_name()) else {
+            return Err(format_error(
+                AppErrorKind::InvalidPath,
+                "destination has no parent folder",
+            ));
+        };
+        fs::create_dir_all(parent).map_err(io_failure)?;
+        let parent = parent.canonicalize().map_err(io_failure)?;
+        let root = parent.join(format!(".{}.rf-extract", name.to_string_lossy()));
+        if fs::symlink_metadata(&root).is_ok() {
+            // Left over from an interrupted run.
+            fs::remove_dir_all(&root).map_err(io_failure)?;
+        }
+        fs::create_dir(&root).map_err(io_failure)?;
+        Ok(ExtractPlan {
+            dest: parent.join(name),
+            root,
+            policy,
+            staged,
+            limits: ExtractLimits::default(),
+        })
+    }
+
+    pub(crate) fn with_limits(mut self, limits: ExtractLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Canonical folder entries are written into.
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Fresh running totals for this plan's limits and destination volume.
+    pub(crate) fn limit_guard(&self) -> LimitGuard {
+        LimitGuard::new(self.limits, &self.root)
+    }
+
+    /// Output path for the file entry `rel`, or `None` when the policy skips it.
+    pub(crate) fn file_target(
+        &self,
+        rel: &str,
+        modified: Option<SystemTime>,
+    ) -> Result<Option<PathBuf>, String> {
+        let target = zip_safe_path(&self.dest, rel)?;
+        let chosen = match plan_conflict(&target, modified, self.policy)
+            .map_err(|err| format!("code={}; {}: {}", io_error_code(&err), err, rel))?
+        {
+            ConflictAction::Skip => return Ok(None),
+            ConflictAction::Proceed(path) | ConflictAction::Replace(path) => path,
+        };
+        let chosen = chosen
+            .strip_prefix(&self.dest)
+            .map_err(|_| invalid_entry())?;
+        Ok(Some(self.root.join(chosen)))
+    }
+
+    /// Moves a staged result into place, or drops it when extraction failed.
+    pub(crate) fn finish(
+        self,
+        result: Result<ArchiveSummary, String>,
+    ) -> Result<ArchiveSummary, String> {
+        if !self.staged {
+            return result;
+        }
+        let result = result.and_then(|summary| {
+            merge_dir(&self.root, &self.dest)
+                .map_err(io_failure)
+                .map(|_| summary)
+        });
+        let _ = fs::remove_dir_all(&self.root);
+        result
+    }
+}
+
+/// First path under `to` where `from` has a folder and `to` something else, or the other
+/// way round.
+fn type_collision(from: &Path, to: &Path) -> std::io::Result<Option<PathBuf>> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let Ok(meta) = fs::symlink_metadata(&target) else {
+            continue;
+        };
+        let is_dir = entry.file_type()?.is_dir();
+        if is_dir != meta.is_dir() {
+            return Ok(Some(target));
+        }
+        if is_dir {
+            if let Some(found) = type_collision(&entry.path(), &target)? {
+                return Ok(Some(found));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Moves everything under `from` into `to`, merging into folders that already exist.
+/// Files replace files; a file never replaces a folder or the other way round, which is
+/// checked for the whole tree before anything moves.
+fn merge_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::symlink_metadata(to).is_err() {
+        return fs::rename(from, to);
+    }
+    if let Some(path) = type_collision(from, to)? {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("a file and a folder share the name {}", path.display()),
+        ));
+    }
+    move_merged(from, to)
+}
+
+fn move_merged(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let is_dir = entry.file_type()?.is_dir();
+        match fs::symlink_metadata(&target) {
+            Ok(meta) if is_dir && meta.is_dir() => move_merged(&entry.path(), &target)?,
+            Ok(meta) if !is_dir && !meta.is_dir() => {
+                fs::remove_file(&target)?;
+                fs::rename(entry.path(), &target)?;
+            }
+            _ => fs::rename(entry.path(), &target)?,
+        }
+    }
+    Ok(())
+}
+
+/// Error for a missing or wrong password; the UI prompts again when it sees this code.
+pub(crate) fn bad_password() -> String {
+    format_error(AppErrorKind::Unknown, "ZIP_BAD_PASSWORD")
+}
+
+/// Emits `ArchiveProgress` at most every `ARCHIVE_PROGRESS_INTERVAL`, plus at each entry.
+pub(crate) struct ArchiveTracker<'a> {
+    progress: ArchiveProgress,
+    skipped: Vec<String>,
+    skipped_bytes: u64,
+    byte_limit: Option<u64>,
+    last_emit: Instant,
+    on_progress: &'a mut dyn FnMut(&ArchiveProgress),
+}
+
+impl<'a> ArchiveTracker<'a> {
+    pub(crate) fn new(
+        op: ArchiveOp,
+        entries_total: u64,
+        bytes_total: u64,
+        on_progress: &'a mut dyn FnMut(&ArchiveProgress),
+    ) -> Self {
+        ArchiveTracker {
+            progress: ArchiveProgress {
+                op,
+                path: String::new(),
+                entries_done: 0,
+                entries_total,
+                bytes_done: 0,
+                bytes_total,
             },
-        )?;
-    let mut resolved_items: Vec<(String, PathBuf)> = Vec::with_capacity(items.len());
-    for item in &items {
-        let resolved = resolve_legacy_path_for(item, ProviderCapability::Read).map_err(|err| {
-            crate::log_error(
-                "zip_create",
-                item,
-                &destination,
-                &format!("code={}; {}", err.code(), err),
-            );
-            format!("code={}; {}", err.code(), err)
-        })?;
-        resolved_items.push((item.clone(), resolved));
-    }
-    let preflight_items: Vec<String> = resolved_items
-        .iter()
-        .map(|(_, path)| path.to_string_lossy().to_string())
-        .collect();
-    let resolved_destination_text = resolved_destination.to_string_lossy().to_string();
-    if let Err(err) = preflight_zip_create(&preflight_items, &resolved_destination_text) {
-        crate::log_error(
-            "zip_create",
-            "batch",
-            &destination,
-            &format!("code={}; {}", err.code, err.message),
+            skipped: Vec::new(),
+            skipped_bytes: 0,
+            byte_limit: None,
+            last_emit: Instant::now(),
+            on_progress,
+        }
+    }
+
+    pub(crate) fn start_entry(&mut self, name: &str) {
+        self.progress.path = name.to_string();
+        self.emit(true);
+    }
+
+    pub(crate) fn finish_entry(&mut self) {
+        self.progress.entries_done += 1;
+    }
+
+    /// Makes `stream_copy` fail once more than `max` bytes have been processed, for formats
+    /// whose readers do not hold entries to their declared sizes.
+    pub(crate) fn limit_bytes(&mut self, max: u64) {
+        self.byte_limit = Some(max);
+    }
+
+    /// Counts an entry the conflict policy left alone; it is reported in `skipped`.
+    pub(crate) fn skip_entry(&mut self, name: &str, bytes: u64) {
+        self.skipped.push(name.to_string());
+        self.skipped_bytes += bytes;
+        self.progress.entries_done += 1;
+        self.add_bytes(bytes);
+    }
+
+    pub(crate) fn add_bytes(&mut self, bytes: u64) {
+        self.progress.bytes_done += bytes;
+        self.emit(false);
+    }
+
+    fn emit(&mut self, force: bool) {
+        if force || self.last_emit.elapsed() >= ARCHIVE_PROGRESS_INTERVAL {
+            (self.on_progress)(&self.progress);
+            self.last_emit = Instant::now();
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> ArchiveSummary {
+        self.emit(true);
+        ArchiveSummary {
+            count: self.progress.entries_done - self.skipped.len() as u64,
+            bytes: self.progress.bytes_done - self.skipped_bytes,
+            skipped: self.skipped,
+        }
+    }
+
+    /// Ends an `archive_test` run with the results of the entries read back.
+    pub(crate) fn finish_test(self, entries: Vec<ArchiveTestEntry>) -> ArchiveTestReport {
+        let summary = self.finish();
+        ArchiveTestReport {
+            failed: entries.iter().filter(|entry| !entry.ok).count() as u64,
+            count: summary.count,
+            bytes: summary.bytes,
+            entries,
+        }
+    }
+}
+
+pub(crate) fn io_failure(err: std::io::Error) -> String {
+    if err
+        .get_ref()
+        .is_some_and(|inner| inner.is::<SizeExceeded>())
+    {
+        return format!("code={ARCHIVE_LIMIT_CODE}; {err}");
+    }
+    format!("code={}; {}", io_error_code(&err), err)
+}
+
+/// Copies `reader` into `writer` in chunks so large files never sit in memory.
+/// Checks `op_cancel` between chunks.
+pub(crate) fn stream_copy(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    tracker: &mut ArchiveTracker,
+) -> std::io::Result<u64> {
+    let mut buf = vec![0u8; ARCHIVE_COPY_CHUNK];
+    let mut copied = 0u64;
+    loop {
+        if cancel_requested() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "canceled",
+            ));
+        }
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            return Ok(copied);
+        }
+        if tracker
+            .byte_limit
+            .is_some_and(|max| tracker.progress.bytes_done + read as u64 > max)
+        {
+            return Err(std::io::Error::other(SizeExceeded));
+        }
+        writer.write_all(&buf[..read])?;
+        copied += read as u64;
+        tracker.add_bytes(read as u64);
+    }
+}
+
+/// Reads `reader` to the end for `archive_test`. Cancellation ends the test; any other
+/// error only fails the entry and comes back as the inner `Err`.
+pub(crate) fn read_back(
+    reader: &mut impl Read,
+    tracker: &mut ArchiveTracker,
+) -> Result<Result<(), String>, String> {
+    match stream_copy(reader, &mut std::io::sink(), tracker) {
+        Ok(_) => Ok(Ok(())),
+        Err(err) if err.kind() == std::io::ErrorKind::Interrupted => Err(io_failure(err)),
+        Err(err) => Ok(Err(io_failure(err))),
+    }
+}
+
+/// Counts the entries and bytes `write_tar` will write for `src`.
+pub(crate) fn scan_totals(src: &Path, entries: &mut u64, bytes: &mut u64) {
+    *entries += 1;
+    if src.is_dir() {
+        if let Ok(read_dir) = fs::read_dir(src) {
+            for entry in read_dir.flatten() {
+                scan_totals(&entry.path(), entries, bytes);
+            }
+        }
+    } else if let Ok(meta) = fs::metadata(src) {
+        *bytes += meta.len();
+    }
+}
+
+enum ZipSourceKind {
+    Dir,
+    File(u64),
+    Symlink(String),
+}
+
+/// One entry `write_zip` will add, named with `/` separators.
+pub(crate) struct ZipSource {
+    path: PathBuf,
+    name: String,
+    kind: ZipSourceKind,
+    modified: Option<SystemTime>,
+    /// Permission bits; `None` on platforms without them.
+    mode: Option<u32>,
+}
+
+impl ZipSource {
+    /// Name in the archive; folders end with `/`.
+    pub(crate) fn entry_name(&self) -> String {
+        match self.kind {
+            ZipSourceKind::Dir => format!("{}/", self.name),
+            _ => self.name.clone(),
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> u64 {
+        match self.kind {
+            ZipSourceKind::File(len) => len,
+            _ => 0,
+        }
+    }
+
+    fn new(path: &Path, name: String, kind: ZipSourceKind, meta: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(meta.permissions().mode() & 0o777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+        ZipSource {
+            path: path.to_path_buf(),
+            name,
+            kind,
+            modified: meta.modified().ok(),
+            mode,
+        }
+    }
+
+    /// `options` stamped with this source's modification time and permission bits. The
+    /// time goes into the DOS fields, read as local time, and into an extended timestamp
+    /// field, which keeps whole seconds in UTC.
+    fn stamp<'k>(&self, mut options: FullFileOptions<'k>) -> Result<FullFileOptions<'k>, String> {
+        if let Some(mode) = self.mode {
+            options = options.unix_permissions(mode);
+        }
+        let Some(modified) = self.modified else {
+            return Ok(options);
+        };
+        let local = chrono::DateTime::<Local>::from(modified);
+        let dos = DateTime::from_date_and_time(
+            local.year().try_into().unwrap_or(0),
+            local.month() as u8,
+            local.day() as u8,
+            local.hour() as u8,
+            local.minute() as u8,
+            local.second() as u8,
         );
-        return Err(format!("code={}; {}", err.code, err.message));
+        if let Ok(dos) = dos {
+            options = options.last_modified_time(dos);
+        }
+        let seconds = modified
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .and_then(|since| u32::try_from(since.as_secs()).ok());
+        if let Some(seconds) = seconds {
+            let mut field = vec![1];
+            field.extend_from_slice(&seconds.to_le_bytes());
+            options
+                .add_extra_data(EXTENDED_TIMESTAMP_ID, field, false)
+                .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+        }
+        Ok(options)
+    }
+}
+
+/// Lists `src` and, for folders, everything below it not matched by `options.exclude`.
+/// A followed symlink that is broken or points back at one of its parent folders is stored
+/// as a link, so the walk always ends.
+pub(crate) fn collect_zip_sources(
+    src: &Path,
+    name: String,
+    options: &ZipCreateOptions,
+    parents: &mut Vec<PathBuf>,
+    out: &mut Vec<ZipSource>,
+) -> std::io::Result<()> {
+    let mut meta = fs::symlink_metadata(src)?;
+    if meta.file_type().is_symlink() {
+        let followed = fs::metadata(src)
+            .ok()
+            .filter(|_| options.follow_symlinks)
+            .filter(|target| {
+                !target.is_dir() || fs::canonicalize(src).is_ok_and(|real| !parents.contains(&real))
+            });
+        match followed {
+            Some(target) => meta = target,
+            None => {
+                let target = fs::read_link(src)?.to_string_lossy().replace('\\', "/");
+                let kind = ZipSourceKind::Symlink(target);
+                out.push(ZipSource::new(src, name, kind, &meta));
+                return Ok(());
+            }
+        }
     }
-    let dest_path = resolved_destination;
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+    if !meta.is_dir() {
+        let kind = ZipSourceKind::File(meta.len());
+        out.push(ZipSource::new(src, name, kind, &meta));
+        return Ok(());
     }
-    let file =
-        fs::File::create(&dest_path).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-    let mut writer = ZipWriter::new(file);
-    let total = resolved_items.len();
-    for (_raw_item, src) in resolved_items {
+    out.push(ZipSource::new(src, name.clone(), ZipSourceKind::Dir, &meta));
+    parents.push(fs::canonicalize(src)?);
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let child = format!("{name}/{}", entry.file_name().to_string_lossy());
+        if matches_any_glob(&options.exclude, &child) {
+            continue;
+        }
+        collect_zip_sources(&entry.path(), child, options, parents, out)?;
+    }
+    parents.pop();
+    Ok(())
+}
+
+/// Entry options for `options.method`. Levels the encoder would reject fail up front.
+pub(crate) fn zip_file_options(
+    options: &ZipCreateOptions,
+) -> Result<FullFileOptions<'static>, String> {
+    let (method, levels) = match options.method {
+        ZipMethod::Store => (CompressionMethod::Stored, None),
+        ZipMethod::Zstd => (CompressionMethod::Zstd, Some(1..=22)),
+        ZipMethod::Bzip2 => (CompressionMethod::Bzip2, Some(1..=9)),
+        ZipMethod::Deflate | ZipMethod::Unknown => (CompressionMethod::Deflated, Some(0..=9)),
+    };
+    if let Some(level) = options.level {
+        if !levels.is_some_and(|range| range.contains(&level)) {
+            return Err(format!(
+                "code=invalid_compression_level; level {level} is not supported by {}",
+                options.method.as_str()
+            ));
+        }
+    }
+    Ok(FullFileOptions::default()
+        .compression_method(method)
+        .compression_level(options.level))
+}
+
+pub(crate) fn zip_add_source(
+    writer: &mut ZipWriter<fs::File>,
+    source: &ZipSource,
+    file_options: &FullFileOptions<'_>,
+    tracker: &mut ArchiveTracker,
+) -> Result<(), String> {
+    let zip_error = |e: ZipError| format_error(AppErrorKind::Io, e.to_string());
+    match &source.kind {
+        ZipSourceKind::Dir => {
+            let name = source.entry_name();
+            tracker.start_entry(&name);
+            let options = FullFileOptions::default().compression_method(CompressionMethod::Stored);
+            writer
+                .add_directory(name, source.stamp(options)?)
+                .map_err(zip_error)?;
+        }
+        ZipSourceKind::Symlink(target) => {
+            tracker.start_entry(&source.name);
+            let options = source.stamp(file_options.clone())?;
+            writer
+                .add_symlink(&source.name, target, options)
+                .map_err(zip_error)?;
+        }
+        ZipSourceKind::File(len) => {
+            tracker.start_entry(&source.name);
+            let mut file = fs::File::open(&source.path).map_err(io_failure)?;
+            let options = source.stamp(file_options.clone())?;
+            writer
+                .start_file(&source.name, options.large_file(*len >= ZIP64_THRESHOLD))
+                .map_err(zip_error)?;
+            stream_copy(&mut file, writer, tracker).map_err(io_failure)?;
+        }
+    }
+    tracker.finish_entry();
+    Ok(())
+}
+
+/// Writes `sources` into a new zip at `dest`. Names are stored as UTF-8 unless
+/// `cp932_names` asks for them to be rewritten once the archive is complete, and with
+/// `volume_size` the finished archive is cut into a split set, which needs free space for
+/// twice the size of the sources. The partial archive is removed when writing fails or is
+/// canceled.
+pub(crate) fn write_zip(
+    dest: &Path,
+    sources: &[PathBuf],
+    password: &Option<String>,
+    options: &ZipCreateOptions,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    check_volume_size(options.volume_size)?;
+    let mut file_options = zip_file_options(options)?;
+    if let Some(pass) = password {
+        file_options = file_options.with_aes_encryption(AesMode::Aes256, pass);
+    }
+    let mut entries = Vec::new();
+    for src in sources {
         let name = src.file_name().ok_or_else(|| "invalid path".to_string())?;
-        zip_add_path(&mut writer, &src, Path::new(name), &password)?;
-    }
-    writer
-        .finish()
-        .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-    crate::log_event(
-        "ZIP_CREATE",
-        "batch",
-        &destination,
-        &format!("count={}; ms={}", total, started.elapsed().as_millis()),
+        let name = name.to_string_lossy().to_string();
+        collect_zip_sources(src, name, options, &mut Vec::new(), &mut entries)
+            .map_err(io_failure)?;
+    }
+    let bytes_total = entries.iter().map(ZipSource::bytes).sum();
+    if options.volume_size.is_some() {
+        check_split_space(dest, bytes_total)?;
+    }
+    let mut tracker = ArchiveTracker::new(
+        ArchiveOp::Create,
+        entries.len() as u64,
+        bytes_total,
+        on_progress,
     );
-    Ok(())
+    let file = fs::File::create(dest).map_err(io_failure)?;
+    let mut writer = ZipWriter::new(file);
+    let result = entries
+        .iter()
+        .try_for_each(|entry| zip_add_source(&mut writer, entry, &file_options, &mut tracker));
+    let result = match result {
+        Ok(()) => writer
+            .finish()
+            .map(|_| ())
+            .map_err(|e| format_error(AppErrorKind::Io, e.to_string())),
+        Err(err) => {
+            // Close the handle first so the partial file can be removed on Windows.
+            drop(writer);
+            Err(err)
+        }
+    };
+    let result = result.and_then(|()| {
+        if options.cp932_names {
+            write_cp932_names(dest)
+        } else {
+            Ok(())
+        }
+    });
+    let result = result.and_then(|()| match options.volume_size {
+        Some(size) => split_zip(dest, size).map(|_| ()),
+        None => Ok(()),
+    });
+    if let Err(err) = result {
+        let _ = fs::remove_file(dest);
+        return Err(err);
+    }
+    Ok(tracker.finish())
 }
 
 #[tauri::command]
-pub fn zip_extract(
-    path: String,
+pub fn zip_create(
+    app: AppHandle,
+    items: Vec<String>,
     destination: String,
     password: Option<String>,
-) -> Result<(), String> {
-    let started = Instant::now();
-    let resolved_path = resolve_legacy_path_for(&path, ProviderCapability::ArchiveExtract)
-        .map_err(|err| {
-            crate::log_error(
-                "zip_extract",
-                &path,
-                &destination,
-                &format!("code={}; {}", err.code(), err),
-            );
-            format!("code={}; {}", err.code(), err)
-        })?;
-    let resolved_destination =
-        resolve_legacy_path_for(&destination, ProviderCapability::ArchiveExtract).map_err(
-            |err| {
-                crate::log_error(
-                    "zip_extract",
-                    &path,
-                    &destination,
-                    &format!("code={}; {}", err.code(), err),
-                );
-                format!("code={}; {}", err.code(), err)
-            },
-        )?;
-    let resolved_path_text = resolved_path.to_string_lossy().to_string();
-    let resolved_destination_text = resolved_destination.to_string_lossy().to_string();
-    if let Err(err) = preflight_zip_extract(&resolved_path_text, &resolved_destination_text) {
-        crate::log_error(
-            "zip_extract",
-            &path,
-            &destination,
-            &format!("code={}; {}", err.code, err.message),
-        );
-        return Err(format!("code={}; {}", err.code, err.message));
-    }
-    let file = fs::File::open(&resolved_path)
-        .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-    let mut archive =
-        ZipArchive::new(file).map_err(|e| format_error(AppErrorKind::Unknown, e.to_string()))?;
-    let dest_path = resolved_destination;
-    fs::create_dir_all(&dest_path).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-    let total = archive.len();
-    for i in 0..total {
-        let mut file = match password.as_deref() {
-            Some(pass) => archive.by_index_decrypt(i, pass.as_bytes()).map_err(|e| {
-                if matches!(e, ZipError::InvalidPassword) {
-                    format_error(AppErrorKind::Unknown, "ZIP_BAD_PASSWORD")
-                } else {
-                    format_error(AppErrorKind::Unknown, e.to_string())
-                }
-            })?,
-            None => archive
-                .by_index(i)
-                .map_err(|e| format_error(AppErrorKind::Unknown, e.to_string()))?,
+    options: Option<ZipCreateOptions>,
+) -> Result<ArchiveSummary, String> {
+    archive_create(
+        app,
+        items,
+        destination,
+        Some(ArchiveFormat::Zip),
+        password,
+        options,
+    )
+}
+
+pub(crate) fn zip_open_error(err: ZipError) -> String {
+    if matches!(err, ZipError::InvalidPassword) {
+        bad_password()
+    } else {
+        format_error(AppErrorKind::Unknown, err.to_string())
+    }
+}
+
+/// Opens a zip, reading the `.z01`, `.z02`, ... volumes along with it when `path` is the
+/// last volume of a split set.
+pub(crate) fn open_zip(path: &Path) -> Result<ZipArchive<ZipVolumes>, String> {
+    let file = ZipVolumes::open(path).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+    ZipArchive::new(file).map_err(|e| format_error(AppErrorKind::Unknown, e.to_string()))
+}
+
+fn zip_entry<'a>(
+    archive: &'a mut ZipArchive<ZipVolumes>,
+    index: usize,
+    password: Option<&str>,
+) -> Result<ZipFile<'a, ZipVolumes>, String> {
+    match password {
+        Some(pass) => archive
+            .by_index_decrypt(index, pass.as_bytes())
+            .map_err(zip_open_error),
+        None => archive.by_index(index).map_err(zip_open_error),
+    }
+}
+
+/// Extracts the entries of the zip at `path` that `filter` selects as laid out by `plan`,
+/// decoding unflagged names as `encoding`. Modification times and recorded Unix modes are
+/// restored. Cancellation stops between chunks; files already written stay in place.
+pub(crate) fn extract_zip(
+    path: &Path,
+    plan: &ExtractPlan,
+    password: Option<&str>,
+    filter: &ExtractFilter,
+    encoding: ZipNameEncoding,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    let mut archive = open_zip(path)?;
+    let names = zip_entry_names(&mut archive, encoding)?;
+    let root = plan.root();
+    let mut guard = plan.limit_guard();
+    let mut selected = Vec::new();
+    let mut bytes_total = 0;
+    for (i, name) in names.into_iter().enumerate() {
+        let file = archive.by_index_raw(i).map_err(zip_open_error)?;
+        if let Some(target) = filter.target(&name) {
+            guard.admit(&target, file.size(), Some(file.compressed_size()))?;
+            bytes_total += file.size();
+            selected.push((i, name, target));
+        }
+    }
+    let mut tracker = ArchiveTracker::new(
+        ArchiveOp::Extract,
+        selected.len() as u64,
+        bytes_total,
+        on_progress,
+    );
+    // Sizes in zip headers are not enforced by the decoder.
+    tracker.limit_bytes(bytes_total);
+    let mut dirs = Vec::new();
+    for (i, name, target) in selected {
+        let mut file = zip_entry(&mut archive, i, password)?;
+        let (modified, mode) = (zip_mtime(&file), zip_mode(&file));
+        tracker.start_entry(&name);
+        if name.ends_with('/') {
+            let outpath = zip_safe_path(root, &target)?;
+            fs::create_dir_all(&outpath).map_err(io_failure)?;
+            ensure_inside(root, &outpath)?;
+            // Applied once everything is written, which would otherwise bump the times and
+            // could be blocked by read-only folders.
+            dirs.push((outpath, modified, mode));
+            tracker.finish_entry();
+            continue;
+        }
+        let Some(outpath) = plan.file_target(&target, modified)? else {
+            tracker.skip_entry(&name, file.size());
+            continue;
         };
-        let outpath = zip_safe_path(&dest_path, file.name())?;
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)
-                .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+        prepare_file_target(root, &outpath)?;
+        let mut outfile = fs::File::create(&outpath).map_err(io_failure)?;
+        stream_copy(&mut file, &mut outfile, &mut tracker).map_err(io_failure)?;
+        if let Some(modified) = modified {
+            let _ = outfile.set_modified(modified);
+        }
+        drop(outfile);
+        if let Some(mode) = mode {
+            set_file_mode(&outpath, mode).map_err(io_failure)?;
+        }
+        tracker.finish_entry();
+    }
+    for (dir, modified, mode) in dirs.iter().rev() {
+        if modified.is_some() {
+            let _ = set_times(dir, *modified, None);
+        }
+        if let Some(mode) = mode {
+            let _ = set_file_mode(dir, *mode);
+        }
+    }
+    Ok(tracker.finish())
+}
+
+/// Names of selected file entries that already exist under `dest`.
+pub(crate) fn zip_conflicts(
+    path: &Path,
+    dest: &Path,
+    password: Option<&str>,
+    filter: &ExtractFilter,
+    encoding: ZipNameEncoding,
+) -> Result<Vec<String>, String> {
+    let mut archive = open_zip(path)?;
+    let names = zip_entry_names(&mut archive, encoding)?;
+    let mut conflicts = Vec::new();
+    for (i, name) in names.into_iter().enumerate() {
+        zip_entry(&mut archive, i, password)?;
+        if name.ends_with('/') {
             continue;
         }
-        if let Some(parent) = outpath.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+        let Some(target) = filter.target(&name) else {
+            continue;
+        };
+        let outpath = zip_safe_path(dest, &target)?;
+        if outpath.exists() {
+            conflicts.push(name);
+        }
+    }
+    Ok(conflicts)
+}
+
+/// A wall-clock time stored without a zone (zip and RAR headers), read as local time.
+pub(crate) fn local_time(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> Option<SystemTime> {
+    chrono::NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(hour, minute, second))
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(SystemTime::from)
+}
+
+/// The extended timestamp when the entry has one, else its DOS time.
+fn zip_mtime(file: &ZipFile<'_, ZipVolumes>) -> Option<SystemTime> {
+    let extended = file.extra_data_fields().find_map(|field| match field {
+        ExtraField::ExtendedTimestamp(stamp) => stamp.mod_time(),
+        _ => None,
+    });
+    if let Some(seconds) = extended {
+        return Some(UNIX_EPOCH + Duration::from_secs(u64::from(seconds)));
+    }
+    let time = file.last_modified()?;
+    local_time(
+        i32::from(time.year()),
+        u32::from(time.month()),
+        u32::from(time.day()),
+        u32::from(time.hour()),
+        u32::from(time.minute()),
+        u32::from(time.second()),
+    )
+}
+
+/// Permission bits from the upper half of the external attributes, where Unix-aware
+/// writers put the file mode. Special bits are dropped.
+fn zip_mode(file: &ZipFile<'_, ZipVolumes>) -> Option<u32> {
+    let mode = file.get_metadata().external_attributes >> 16;
+    // Links are extracted as plain files holding their target.
+    (mode != 0 && !file.is_symlink()).then_some(mode & 0o777)
+}
+
+fn zip_method_name(method: CompressionMethod) -> String {
+    match method {
+        CompressionMethod::Stored => "store".to_string(),
+        CompressionMethod::Deflated => "deflate".to_string(),
+        other => other.to_string().to_lowercase(),
+    }
+}
+
+/// Reads the central directory only, so encrypted entries are listed without a password.
+/// Unflagged names are decoded as `encoding`.
+pub(crate) fn list_zip(
+    path: &Path,
+    encoding: ZipNameEncoding,
+) -> Result<Vec<ArchiveListEntry>, String> {
+    let mut archive = open_zip(path)?;
+    let names = zip_entry_names(&mut archive, encoding)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for (i, name) in names.into_iter().enumerate() {
+        let file = archive.by_index_raw(i).map_err(zip_open_error)?;
+        entries.push(ArchiveListEntry {
+            name,
+            is_dir: file.is_dir(),
+            size: file.size(),
+            compressed_size: Some(file.compressed_size()),
+            method: zip_method_name(file.compression()),
+            modified: system_time_to_rfc3339(zip_mtime(&file)),
+            crc: Some(file.crc32()),
+            encrypted: file.encrypted(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads every entry of the zip at `path` back, checking CRCs and the authentication codes
+/// of AES entries. A missing or wrong password ends the test so the UI can ask again.
+pub(crate) fn verify_zip(
+    path: &Path,
+    password: Option<&str>,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveTestReport, String> {
+    let mut archive = open_zip(path)?;
+    let names = zip_entry_names(&mut archive, ZipNameEncoding::Auto)?;
+    let mut bytes_total = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(zip_open_error)?;
+        if file.encrypted() && password.is_none() {
+            return Err(bad_password());
         }
-        let mut outfile = fs::File::create(&outpath)
-            .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-        std::io::copy(&mut file, &mut outfile)
-            .map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+        bytes_total += file.size();
     }
-    crate::log_event(
-        "ZIP_EXTRACT",
-        &path,
-        &destination,
-        &format!("count={}; ms={}", total, started.elapsed().as_millis()),
+    let mut tracker = ArchiveTracker::new(
+        ArchiveOp::Test,
+        names.len() as u64,
+        bytes_total,
+        on_progress,
     );
-    Ok(())
+    let mut entries = Vec::with_capacity(names.len());
+    for (i, name) in names.into_iter().enumerate() {
+        tracker.start_entry(&name);
+        let result = match zip_entry(&mut archive, i, password) {
+            Ok(mut file) => read_back(&mut file, &mut tracker)?,
+            Err(err) if err == bad_password() => return Err(err),
+            Err(err) => Err(err),
+        };
+        tracker.finish_entry();
+        entries.push(ArchiveTestEntry::new(name, result));
+    }
+    Ok(tracker.finish_test(entries))
+}
+
+#[tauri::command]
+pub fn zip_extract(
+    app: AppHandle,
+    path: String,
+    destination: String,
+    password: Option<String>,
+    options: Option<ArchiveExtractOptions>,
+) -> Result<ArchiveSummary, String> {
+    archive_extract(
+        app,
+        path,
+        destination,
+        Some(ArchiveFormat::Zip),
+        password,
+        options,
+    )
 }
 
 #[tauri::command]
@@ -227,47 +1022,350 @@ pub fn zip_extract_list_conflicts(
     path: String,
     destination: String,
     password: Option<String>,
+    options: Option<ArchiveExtractOptions>,
 ) -> Result<Vec<String>, String> {
-    let resolved_path = resolve_legacy_path_for(&path, ProviderCapability::ArchiveExtract).map_err(
-        |err| format!("code={}; {}", err.code(), err),
-    )?;
-    let resolved_destination =
-        resolve_legacy_path_for(&destination, ProviderCapability::ArchiveExtract).map_err(
-            |err| format!("code={}; {}", err.code(), err),
-        )?;
-    let resolved_path_text = resolved_path.to_string_lossy().to_string();
-    let resolved_destination_text = resolved_destination.to_string_lossy().to_string();
-    if let Err(err) = preflight_zip_extract(&resolved_path_text, &resolved_destination_text) {
-        return Err(format!("code={}; {}", err.code, err.message));
-    }
-
-    let file = fs::File::open(&resolved_path).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
-    let mut archive =
-        ZipArchive::new(file).map_err(|e| format_error(AppErrorKind::Unknown, e.to_string()))?;
-    let dest_path = resolved_destination;
-    let mut conflicts = Vec::new();
-    let total = archive.len();
-    for i in 0..total {
-        let file = match password.as_deref() {
-            Some(pass) => archive.by_index_decrypt(i, pass.as_bytes()).map_err(|e| {
-                if matches!(e, ZipError::InvalidPassword) {
-                    format_error(AppErrorKind::Unknown, "ZIP_BAD_PASSWORD")
-                } else {
-                    format_error(AppErrorKind::Unknown, e.to_string())
-                }
-            })?,
-            None => archive
-                .by_index(i)
-                .map_err(|e| format_error(AppErrorKind::Unknown, e.to_string()))?,
+    archive_extract_list_conflicts(
+        path,
+        destination,
+        Some(ArchiveFormat::Zip),
+        password,
+        options,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_zip, merge_dir, write_zip, ExtractFilter, ExtractPlan};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{ConflictPolicy, ZipCreateOptions, ZipMethod, ZipNameEncoding};
+    use std::fs;
+    use std::io::Read;
+    use std::time::{Duration, UNIX_EPOCH};
+    use zip::ZipArchive;
+
+    #[test]
+    fn write_zip_streams_entries_and_reports_totals() {
+        let root = unique_temp_dir("rf-zip-create");
+        let src = root.join("docs");
+        fs::create_dir_all(src.join("sub")).expect("create src");
+        let big: Vec<u8> = (0..600_000u32).map(|n| (n % 253) as u8).collect();
+        fs::write(src.join("sub").join("big.bin"), &big).expect("write big");
+        fs::write(src.join("a.txt"), b"hello").expect("write small");
+        let dest = root.join("out.zip");
+
+        let mut last = None;
+        let summary = write_zip(
+            &dest,
+            &[src],
+            &None,
+            &ZipCreateOptions::default(),
+            &mut |progress| {
+                last = Some((
+                    progress.entries_done,
+                    progress.bytes_done,
+                    progress.bytes_total,
+                ));
+            },
+        )
+        .expect("write zip");
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.bytes, big.len() as u64 + 5);
+        assert_eq!(last, Some((4, summary.bytes, summary.bytes)));
+
+        let mut archive = ZipArchive::new(fs::File::open(&dest).expect("open")).expect("zip");
+        let mut content = Vec::new();
+        archive
+            .by_name("docs/sub/big.bin")
+            .expect("entry")
+            .read_to_end(&mut content)
+            .expect("read entry");
+        assert_eq!(content, big);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn write_zip_applies_method_excludes_and_symlink_mode() {
+        let root = unique_temp_dir("rf-zip-options");
+        let src = root.join("proj");
+        fs::create_dir_all(src.join(".git")).expect("create git");
+        fs::create_dir_all(src.join("src")).expect("create src");
+        fs::write(src.join(".git").join("HEAD"), b"ref").expect("write head");
+        fs::write(src.join("src").join("main.rs"), b"fn main() {}").expect("write main");
+        fs::write(src.join("src").join("scratch.tmp"), b"tmp").expect("write tmp");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("src", src.join("link")).expect("symlink");
+
+        let mut options = ZipCreateOptions {
+            method: ZipMethod::Zstd,
+            level: Some(19),
+            exclude: vec![".git".to_string(), "*.tmp".to_string()],
+            follow_symlinks: false,
+            cp932_names: false,
+            volume_size: None,
         };
-        let name = file.name().to_string();
-        if name.ends_with('/') {
-            continue;
+        let dest = root.join("out.zip");
+        write_zip(
+            &dest,
+            std::slice::from_ref(&src),
+            &None,
+            &options,
+            &mut |_| {},
+        )
+        .expect("write zip");
+        let mut archive = ZipArchive::new(fs::File::open(&dest).expect("open")).expect("zip");
+        let names: Vec<_> = archive.file_names().map(str::to_string).collect();
+        assert!(names.contains(&"proj/src/main.rs".to_string()));
+        assert!(names
+            .iter()
+            .all(|name| !name.contains(".git") && !name.ends_with(".tmp")));
+        let file = archive.by_name("proj/src/main.rs").expect("entry");
+        assert_eq!(file.compression(), zip::CompressionMethod::Zstd);
+        drop(file);
+        #[cfg(unix)]
+        assert!(archive.by_name("proj/link").expect("link").is_symlink());
+
+        #[cfg(unix)]
+        {
+            options.follow_symlinks = true;
+            write_zip(
+                &dest,
+                std::slice::from_ref(&src),
+                &None,
+                &options,
+                &mut |_| {},
+            )
+            .expect("follow");
+            let mut archive = ZipArchive::new(fs::File::open(&dest).expect("open")).expect("zip");
+            let mut content = String::new();
+            archive
+                .by_name("proj/link/main.rs")
+                .expect("followed entry")
+                .read_to_string(&mut content)
+                .expect("read");
+            assert_eq!(content, "fn main() {}");
         }
-        let outpath = zip_safe_path(&dest_path, &name)?;
-        if outpath.exists() {
-            conflicts.push(name);
+
+        options.method = ZipMethod::Store;
+        let err = write_zip(&dest, &[src], &None, &options, &mut |_| {}).expect_err("level");
+        assert!(err.starts_with("code=invalid_compression_level;"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn extract_zip_selects_entries_and_strips_components() {
+        let root = unique_temp_dir("rf-zip-select");
+        let src = root.join("docs");
+        fs::create_dir_all(src.join("sub")).expect("create src");
+        fs::write(src.join("a.txt"), b"alpha").expect("write a");
+        fs::write(src.join("b.md"), b"beta").expect("write b");
+        fs::write(src.join("sub").join("c.txt"), b"gamma").expect("write c");
+        let archive = root.join("docs.zip");
+        write_zip(
+            &archive,
+            &[src],
+            &None,
+            &ZipCreateOptions::default(),
+            &mut |_| {},
+        )
+        .expect("write zip");
+
+        let out = root.join("out");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        let filter = ExtractFilter::new(&["*.txt".to_string()], 1);
+        let summary = extract_zip(
+            &archive,
+            &plan,
+            None,
+            &filter,
+            ZipNameEncoding::Auto,
+            &mut |_| {},
+        )
+        .expect("extract");
+        assert_eq!(summary.count, 2);
+        assert_eq!(fs::read(out.join("a.txt")).expect("read a"), b"alpha");
+        assert_eq!(
+            fs::read(out.join("sub").join("c.txt")).expect("read c"),
+            b"gamma"
+        );
+        assert!(!out.join("b.md").exists());
+        assert!(!out.join("docs").exists());
+
+        let out = root.join("folder");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        let filter = ExtractFilter::new(&["docs/sub/".to_string()], 0);
+        extract_zip(
+            &archive,
+            &plan,
+            None,
+            &filter,
+            ZipNameEncoding::Auto,
+            &mut |_| {},
+        )
+        .expect("extract folder");
+        assert!(out.join("docs").join("sub").join("c.txt").is_file());
+        assert!(!out.join("docs").join("a.txt").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn extract_zip_applies_conflict_policy_and_staging() {
+        let root = unique_temp_dir("rf-zip-conflict");
+        let src = root.join("docs");
+        fs::create_dir_all(&src).expect("create src");
+        fs::write(src.join("a.txt"), b"new a").expect("write a");
+        fs::write(src.join("b.txt"), b"new b").expect("write b");
+        let archive = root.join("docs.zip");
+        write_zip(
+            &archive,
+            &[src],
+            &None,
+            &ZipCreateOptions::default(),
+            &mut |_| {},
+        )
+        .expect("write zip");
+        let out = root.join("out");
+        fs::create_dir_all(out.join("docs")).expect("create out");
+        fs::write(out.join("docs").join("a.txt"), b"old a").expect("write old");
+        let filter = ExtractFilter::default();
+
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Skip, false).expect("plan");
+        let summary = extract_zip(
+            &archive,
+            &plan,
+            None,
+            &filter,
+            ZipNameEncoding::Auto,
+            &mut |_| {},
+        )
+        .expect("skip");
+        assert_eq!(summary.skipped, vec!["docs/a.txt".to_string()]);
+        assert_eq!(
+            fs::read(out.join("docs").join("a.txt")).expect("read"),
+            b"old a"
+        );
+
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Rename, true).expect("plan");
+        let result = extract_zip(
+            &archive,
+            &plan,
+            None,
+            &filter,
+            ZipNameEncoding::Auto,
+            &mut |_| {},
+        );
+        plan.finish(result).expect("rename");
+        assert_eq!(
+            fs::read(out.join("docs").join("a (2).txt")).expect("read renamed"),
+            b"new a"
+        );
+        assert_eq!(
+            fs::read(out.join("docs").join("a.txt")).expect("read"),
+            b"old a"
+        );
+
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Fail, true).expect("plan");
+        let result = extract_zip(
+            &archive,
+            &plan,
+            None,
+            &filter,
+            ZipNameEncoding::Auto,
+            &mut |_| {},
+        );
+        let err = plan.finish(result).expect_err("fail policy");
+        assert!(err.starts_with("code=already_exists;"));
+        let staging: Vec<_> = fs::read_dir(&root)
+            .expect("list root")
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".rf-extract"))
+            .collect();
+        assert!(staging.is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn merge_dir_checks_every_type_collision_before_moving() {
+        let root = unique_temp_dir("rf-merge-dir");
+        let (staged, dest) = (root.join("staged"), root.join("dest"));
+        fs::create_dir_all(staged.join("a")).expect("create staged");
+        fs::create_dir_all(staged.join("z").join("inner")).expect("create staged folder");
+        fs::write(staged.join("a").join("new.txt"), b"new").expect("write staged");
+        fs::create_dir_all(dest.join("z")).expect("create dest");
+        fs::write(dest.join("z").join("inner"), b"file").expect("write dest file");
+
+        let err = merge_dir(&staged, &dest).expect_err("collision");
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(!dest.join("a").exists());
+        assert!(staged.join("a").join("new.txt").exists());
+
+        fs::remove_file(dest.join("z").join("inner")).expect("clear collision");
+        merge_dir(&staged, &dest).expect("merge");
+        assert_eq!(
+            fs::read(dest.join("a").join("new.txt")).expect("read"),
+            b"new"
+        );
+        assert!(dest.join("z").join("inner").is_dir());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn zip_round_trip_keeps_mtimes_and_modes() {
+        let root = unique_temp_dir("rf-zip-meta");
+        let src = root.join("project");
+        fs::create_dir_all(src.join("bin")).expect("create src");
+        let script = src.join("bin").join("run.sh");
+        fs::write(&script, b"#!/bin/sh\n").expect("write script");
+        // An odd second, which the DOS time fields alone cannot hold.
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_001);
+        let file = fs::File::options()
+            .write(true)
+            .open(&script)
+            .expect("open script");
+        file.set_modified(modified).expect("set mtime");
+        drop(file);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o750)).expect("chmod");
+            fs::set_permissions(src.join("bin"), fs::Permissions::from_mode(0o700))
+                .expect("chmod dir");
         }
+        let dir_modified = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        crate::fs_ops_attributes::set_times(&src.join("bin"), Some(dir_modified), None)
+            .expect("set dir mtime");
+        let archive = root.join("project.zip");
+        let options = ZipCreateOptions::default();
+        write_zip(&archive, &[src], &None, &options, &mut |_| {}).expect("write zip");
+
+        let out = root.join("out");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        let filter = ExtractFilter::default();
+        extract_zip(
+            &archive,
+            &plan,
+            None,
+            &filter,
+            ZipNameEncoding::Auto,
+            &mut |_| {},
+        )
+        .expect("extract");
+        let bin = out.join("project").join("bin");
+        let meta = fs::metadata(bin.join("run.sh")).expect("script meta");
+        assert_eq!(meta.modified().expect("mtime"), modified);
+        assert_eq!(
+            fs::metadata(&bin)
+                .expect("dir meta")
+                .modified()
+                .expect("mtime"),
+            dir_modified
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(meta.permissions().mode() & 0o777, 0o750);
+            let dir_mode = fs::metadata(&bin).expect("dir meta").permissions().mode();
+            assert_eq!(dir_mode & 0o777, 0o700);
+        }
+        let _ = fs::remove_dir_all(root);
     }
-    Ok(conflicts)
 }
@@ -0,0 +1,319 @@
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::SystemTime;
+
+use sevenz_rust2::{ArchiveEntry, ArchiveReader, EncoderMethod, Error as SevenZipError, Password};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_archive::{
+    bad_password, ensure_inside, io_failure, prepare_file_target, read_back, stream_copy,
+    zip_safe_path, ArchiveTracker, ExtractFilter, ExtractPlan,
+};
+use crate::types::{
+    ArchiveListEntry, ArchiveOp, ArchiveProgress, ArchiveSummary, ArchiveTestEntry,
+    ArchiveTestReport,
+};
+use crate::utils::system_time_to_rfc3339;
+
+fn sevenz_error(err: SevenZipError) -> String {
+    match err {
+        SevenZipError::PasswordRequired | SevenZipError::MaybeBadPassword(_) => bad_password(),
+        SevenZipError::Io(err, _) | SevenZipError::FileOpen(err, _) => io_failure(err),
+        other => format_error(AppErrorKind::Unknown, other.to_string()),
+    }
+}
+
+fn open_7z(path: &Path, password: Option<&str>) -> Result<ArchiveReader<File>, String> {
+    let password = password.map(Password::from).unwrap_or_else(Password::empty);
+    ArchiveReader::open(path, password).map_err(sevenz_error)
+}
+
+/// Remembers whether reading the entry failed, to tell a wrong key from a failed write.
+struct DecodeReader<'a> {
+    inner: &'a mut dyn std::io::Read,
+    failed: bool,
+}
+
+impl std::io::Read for DecodeReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let result = self.inner.read(buf);
+        self.failed |= result.is_err();
+        result
+    }
+}
+
+fn extract_entry(
+    entry: &ArchiveEntry,
+    data: &mut dyn std::io::Read,
+    plan: &ExtractPlan,
+    target: &str,
+    has_password: bool,
+    tracker: &mut ArchiveTracker,
+) -> Result<(), String> {
+    let root = plan.root();
+    tracker.start_entry(&entry.name);
+    if entry.is_directory {
+        let outpath = zip_safe_path(root, target)?;
+        fs::create_dir_all(&outpath).map_err(io_failure)?;
+        ensure_inside(root, &outpath)?;
+        tracker.finish_entry();
+        return Ok(());
+    }
+    let modified = entry
+        .has_last_modified_date
+        .then(|| SystemTime::from(entry.last_modified_date));
+    let mut data = DecodeReader {
+        inner: data,
+        failed: false,
+    };
+    // A wrong key only shows up as undecodable data.
+    let failure = |err: std::io::Error, decode_failed: bool| {
+        if has_password && decode_failed {
+            bad_password()
+        } else {
+            io_failure(err)
+        }
+    };
+    let Some(outpath) = plan.file_target(target, modified)? else {
+        std::io::copy(&mut data, &mut std::io::sink()).map_err(|err| failure(err, data.failed))?;
+        tracker.skip_entry(&entry.name, entry.size);
+        return Ok(());
+    };
+    prepare_file_target(root, &outpath)?;
+    let mut out = File::create(&outpath).map_err(io_failure)?;
+    if let Err(err) = stream_copy(&mut data, &mut out, tracker) {
+        drop(out);
+        let _ = fs::remove_file(&outpath);
+        return Err(failure(err, data.failed));
+    }
+    if let Some(modified) = modified {
+        let _ = out.set_modified(modified);
+    }
+    tracker.finish_entry();
+    Ok(())
+}
+
+/// Extracts the entries of the 7z archive at `path` that `filter` selects as laid out by
+/// `plan`. Entries that would land outside the destination are rejected.
+pub(crate) fn extract_7z(
+    path: &Path,
+    plan: &ExtractPlan,
+    password: Option<&str>,
+    filter: &ExtractFilter,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    let mut reader = open_7z(path, password)?;
+    let mut guard = plan.limit_guard();
+    let mut selected = 0;
+    let mut bytes_total = 0;
+    for entry in &reader.archive().files {
+        if entry.is_anti_item {
+            continue;
+        }
+        if let Some(target) = filter.target(&entry.name) {
+            let packed = (entry.compressed_size > 0).then_some(entry.compressed_size);
+            guard.admit(&target, entry.size, packed)?;
+            selected += 1;
+            bytes_total += entry.size;
+        }
+    }
+    let mut tracker = ArchiveTracker::new(ArchiveOp::Extract, selected, bytes_total, on_progress);
+    let mut failure = None;
+    reader
+        .for_each_entries(|entry, data| {
+            if entry.is_anti_item {
+                return Ok(true);
+            }
+            // Skipped entries of a solid block still have to be decoded to reach the next.
+            let Some(target) = filter.target(&entry.name) else {
+                std::io::copy(data, &mut std::io::sink())?;
+                return Ok(true);
+            };
+            match extract_entry(entry, data, plan, &target, password.is_some(), &mut tracker) {
+                Ok(()) => Ok(true),
+                Err(err) => {
+                    failure = Some(err);
+                    Ok(false)
+                }
+            }
+        })
+        .map_err(sevenz_error)?;
+    if let Some(err) = failure {
+        return Err(err);
+    }
+    Ok(tracker.finish())
+}
+
+/// Names of selected file entries that already exist under `dest`.
+pub(crate) fn sevenz_conflicts(
+    path: &Path,
+    dest: &Path,
+    password: Option<&str>,
+    filter: &ExtractFilter,
+) -> Result<Vec<String>, String> {
+    let reader = open_7z(path, password)?;
+    let mut conflicts = Vec::new();
+    for entry in &reader.archive().files {
+        if entry.is_directory || entry.is_anti_item {
+            continue;
+        }
+        let Some(target) = filter.target(&entry.name) else {
+            continue;
+        };
+        let outpath = zip_safe_path(dest, &target)?;
+        if fs::symlink_metadata(&outpath).is_ok() {
+            conflicts.push(entry.name.clone());
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Header-level listing of a 7z archive. A solid block records its packed size on its
+/// first file only, so the rest report no compressed size.
+pub(crate) fn list_7z(
+    path: &Path,
+    password: Option<&str>,
+) -> Result<Vec<ArchiveListEntry>, String> {
+    let reader = open_7z(path, password)?;
+    let mut entries = Vec::new();
+    let mut methods = Vec::new();
+    for entry in &reader.archive().files {
+        if entry.is_anti_item {
+            continue;
+        }
+        methods.clear();
+        reader
+            .file_compression_methods(&entry.name, &mut methods)
+            .map_err(sevenz_error)?;
+        let encrypted = methods.contains(&EncoderMethod::AES256_SHA256);
+        let method = methods
+            .iter()
+            .filter(|method| **method != EncoderMethod::AES256_SHA256)
+            .map(|method| method.name().to_lowercase())
+            .collect::<Vec<_>>()
+            .join("+");
+        let modified = entry
+            .has_last_modified_date
+            .then(|| SystemTime::from(entry.last_modified_date));
+        entries.push(ArchiveListEntry {
+            name: entry.name.clone(),
+            is_dir: entry.is_directory,
+            size: entry.size,
+            compressed_size: (entry.compressed_size > 0).then_some(entry.compressed_size),
+            method,
+            modified: system_time_to_rfc3339(modified),
+            crc: entry.has_crc.then_some(entry.crc as u32),
+            encrypted,
+        });
+    }
+    Ok(entries)
+}
+
+/// Decodes every entry of the 7z archive at `path`, which checks the stored CRCs. Entries
+/// after a damaged one may share its solid block, so the first failure ends the test.
+pub(crate) fn verify_7z(
+    path: &Path,
+    password: Option<&str>,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveTestReport, String> {
+    let mut reader = open_7z(path, password)?;
+    let files = reader
+        .archive()
+        .files
+        .iter()
+        .filter(|entry| !entry.is_anti_item);
+    let (count, bytes_total) = files.fold((0, 0), |(count, bytes), entry| {
+        (count + 1, bytes + entry.size)
+    });
+    let mut tracker = ArchiveTracker::new(ArchiveOp::Test, count, bytes_total, on_progress);
+    let mut entries = Vec::new();
+    let mut canceled = None;
+    let outcome = reader.for_each_entries(|entry, data| {
+        if entry.is_anti_item {
+            return Ok(true);
+        }
+        tracker.start_entry(&entry.name);
+        match read_back(&mut &mut *data, &mut tracker) {
+            Ok(result) => {
+                tracker.finish_entry();
+                let intact = result.is_ok();
+                entries.push(ArchiveTestEntry::new(entry.name.clone(), result));
+                Ok(intact)
+            }
+            Err(err) => {
+                canceled = Some(err);
+                Ok(false)
+            }
+        }
+    });
+    if let Some(err) = canceled {
+        return Err(err);
+    }
+    if let Err(err) = outcome {
+        let err = sevenz_error(err);
+        if err == bad_password() {
+            return Err(err);
+        }
+        entries.push(ArchiveTestEntry::new("", Err(err)));
+    }
+    Ok(tracker.finish_test(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_7z, sevenz_conflicts};
+    use crate::fs_ops_archive::{ExtractFilter, ExtractPlan};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::ConflictPolicy;
+    use sevenz_rust2::{ArchiveEntry, ArchiveWriter};
+    use std::fs;
+    use std::path::Path;
+
+    fn write_7z(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = ArchiveWriter::create(path).expect("create 7z");
+        for (name, data) in entries {
+            writer
+                .push_archive_entry(ArchiveEntry::new_file(name), Some(*data))
+                .expect("push entry");
+        }
+        writer.finish().expect("finish 7z");
+    }
+
+    #[test]
+    fn extract_7z_writes_entries_and_rejects_traversal() {
+        let root = unique_temp_dir("rf-7z");
+        let archive = root.join("docs.7z");
+        write_7z(
+            &archive,
+            &[("docs/a.txt", b"alpha"), ("docs/sub/b.txt", b"beta")],
+        );
+        let out = root.join("out");
+
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        let summary = extract_7z(
+            &archive,
+            &plan,
+            None,
+            &ExtractFilter::default(),
+            &mut |_| {},
+        )
+        .expect("extract");
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.bytes, 9);
+        assert_eq!(
+            fs::read(out.join("docs").join("sub").join("b.txt")).expect("read"),
+            b"beta"
+        );
+        let conflicts =
+            sevenz_conflicts(&archive, &out, None, &ExtractFilter::default()).expect("conflicts");
+        assert_eq!(conflicts.len(), 2);
+
+        let evil = root.join("evil.7z");
+        write_7z(&evil, &[("../escaped.txt", b"x")]);
+        let err = extract_7z(&evil, &plan, None, &ExtractFilter::default(), &mut |_| {})
+            .expect_err("traversal");
+        assert!(err.starts_with("code=invalid_path;"));
+        assert!(!root.join("escaped.txt").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+}
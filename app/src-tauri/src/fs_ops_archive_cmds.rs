@@ -0,0 +1,532 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_archive::{
+    extract_zip, io_failure, list_zip, verify_zip, write_zip, zip_conflicts, ExtractFilter,
+    ExtractPlan,
+};
+use crate::fs_ops_archive_7z::{extract_7z, list_7z, sevenz_conflicts, verify_7z};
+use crate::fs_ops_archive_limits::ExtractLimits;
+use crate::fs_ops_archive_rar::{extract_rar, list_rar, rar_conflicts, verify_rar};
+use crate::fs_ops_archive_tar::{extract_tar, list_tar, tar_conflicts, verify_tar, write_tar};
+use crate::fs_ops_preflight::{preflight_zip_create, preflight_zip_extract};
+use crate::fs_ops_transfer_helpers::reset_cancel_request;
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{
+    ArchiveExtractOptions, ArchiveFormat, ArchiveListTotals, ArchiveListing, ArchiveProgress,
+    ArchiveSummary, ArchiveTestReport, ConflictPolicy, ZipCreateOptions, ZipNameEncoding,
+    EVENT_ARCHIVE_PROGRESS,
+};
+
+/// Picks the archive format from the file name, e.g. `.tar.gz` or `.tgz`.
+pub(crate) fn detect_format(path: &Path) -> Option<ArchiveFormat> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let format = if name.ends_with(".zip") {
+        ArchiveFormat::Zip
+    } else if name.ends_with(".tar") {
+        ArchiveFormat::Tar
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        ArchiveFormat::TarGz
+    } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+        ArchiveFormat::TarXz
+    } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+        ArchiveFormat::TarZst
+    } else if name.ends_with(".7z") {
+        ArchiveFormat::SevenZip
+    } else if name.ends_with(".rar") {
+        ArchiveFormat::Rar
+    } else {
+        return None;
+    };
+    Some(format)
+}
+
+fn resolve_format(format: Option<ArchiveFormat>, path: &Path) -> Result<ArchiveFormat, String> {
+    format.or_else(|| detect_format(path)).ok_or_else(|| {
+        format!(
+            "code=unsupported_format; unsupported archive format: {}",
+            path.display()
+        )
+    })
+}
+
+/// Tarballs have no encryption; zip, 7z and RAR do.
+fn check_password(format: ArchiveFormat, password: &Option<String>) -> Result<(), String> {
+    let encryptable = matches!(
+        format,
+        ArchiveFormat::Zip | ArchiveFormat::SevenZip | ArchiveFormat::Rar
+    );
+    if !encryptable && password.is_some() {
+        return Err("code=password_unsupported; this format does not support passwords".into());
+    }
+    Ok(())
+}
+
+fn resolve_extract_paths(path: &str, destination: &str) -> Result<(PathBuf, PathBuf), String> {
+    let resolved_path = resolve_legacy_path_for(path, ProviderCapability::ArchiveExtract)
+        .map_err(|err| format!("code={}; {}", err.code(), err))?;
+    let resolved_destination =
+        resolve_legacy_path_for(destination, ProviderCapability::ArchiveExtract)
+            .map_err(|err| format!("code={}; {}", err.code(), err))?;
+    let resolved_path_text = resolved_path.to_string_lossy().to_string();
+    let resolved_destination_text = resolved_destination.to_string_lossy().to_string();
+    if let Err(err) = preflight_zip_extract(&resolved_path_text, &resolved_destination_text) {
+        return Err(format!("code={}; {}", err.code, err.message));
+    }
+    Ok((resolved_path, resolved_destination))
+}
+
+#[tauri::command]
+pub fn archive_create(
+    app: AppHandle,
+    items: Vec<String>,
+    destination: String,
+    format: Option<ArchiveFormat>,
+    password: Option<String>,
+    options: Option<ZipCreateOptions>,
+) -> Result<ArchiveSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let resolved_destination =
+        resolve_legacy_path_for(&destination, ProviderCapability::ArchiveCreate).map_err(
+            |err| {
+                crate::log_error(
+                    "archive_create",
+                    "batch",
+                    &destination,
+                    &format!("code={}; {}", err.code(), err),
+                );
+                format!("code={}; {}", err.code(), err)
+            },
+        )?;
+    let mut sources: Vec<PathBuf> = Vec::with_capacity(items.len());
+    for item in &items {
+        let resolved = resolve_legacy_path_for(item, ProviderCapability::Read).map_err(|err| {
+            crate::log_error(
+                "archive_create",
+                item,
+                &destination,
+                &format!("code={}; {}", err.code(), err),
+            );
+            format!("code={}; {}", err.code(), err)
+        })?;
+        sources.push(resolved);
+    }
+    let preflight_items: Vec<String> = sources
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    let resolved_destination_text = resolved_destination.to_string_lossy().to_string();
+    let volume_size = options.as_ref().and_then(|options| options.volume_size);
+    if let Err(err) =
+        preflight_zip_create(&preflight_items, &resolved_destination_text, volume_size)
+    {
+        crate::log_error(
+            "archive_create",
+            "batch",
+            &destination,
+            &format!("code={}; {}", err.code, err.message),
+        );
+        return Err(format!("code={}; {}", err.code, err.message));
+    }
+    let format = resolve_format(format, &resolved_destination)
+        .and_then(|format| check_password(format, &password).map(|_| format))
+        .inspect_err(|err| crate::log_error("archive_create", "batch", &destination, err))?;
+    let dest_path = resolved_destination;
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format_error(AppErrorKind::Io, e.to_string()))?;
+    }
+    let mut on_progress = |progress: &ArchiveProgress| {
+        let _ = app.emit(EVENT_ARCHIVE_PROGRESS, progress.clone());
+    };
+    let summary = match format {
+        ArchiveFormat::Zip => {
+            let options = options.unwrap_or_else(|| {
+                ZipCreateOptions::from_config(&crate::config::load_config_fast())
+            });
+            write_zip(&dest_path, &sources, &password, &options, &mut on_progress)
+        }
+        ArchiveFormat::SevenZip | ArchiveFormat::Rar => Err(format!(
+            "code=unsupported_format; {:?} archives can only be extracted",
+            format
+        )),
+        _ => write_tar(&dest_path, &sources, format, &mut on_progress),
+    }
+    .inspect_err(|err| crate::log_error("archive_create", "batch", &destination, err))?;
+    crate::log_event(
+        "ARCHIVE_CREATE",
+        "batch",
+        &destination,
+        &format!(
+            "format={:?}; items={}; count={}; bytes={}; ms={}",
+            format,
+            sources.len(),
+            summary.count,
+            summary.bytes,
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(summary)
+}
+
+#[tauri::command]
+pub fn archive_extract(
+    app: AppHandle,
+    path: String,
+    destination: String,
+    format: Option<ArchiveFormat>,
+    password: Option<String>,
+    options: Option<ArchiveExtractOptions>,
+) -> Result<ArchiveSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let options = options.unwrap_or_default();
+    let filter = ExtractFilter::new(&options.entries, options.strip_components);
+    let policy = options
+        .conflict
+        .as_deref()
+        .map(|value| ConflictPolicy::parse(value.trim()))
+        .unwrap_or(ConflictPolicy::Overwrite);
+    let (resolved_path, plan, format) = resolve_extract_paths(&path, &destination)
+        .and_then(|(resolved_path, resolved_destination)| {
+            let format = resolve_format(format, &resolved_path)?;
+            check_password(format, &password)?;
+            let limits = ExtractLimits::from_config(&crate::config::load_config_fast());
+            let plan = ExtractPlan::new(&resolved_destination, policy, options.staged)?
+                .with_limits(limits);
+            Ok((resolved_path, plan, format))
+        })
+        .inspect_err(|err| crate::log_error("archive_extract", &path, &destination, err))?;
+    let mut on_progress = |progress: &ArchiveProgress| {
+        let _ = app.emit(EVENT_ARCHIVE_PROGRESS, progress.clone());
+    };
+    let password = password.as_deref();
+    let result = match format {
+        ArchiveFormat::Zip => {
+            let encoding = options.name_encoding;
+            extract_zip(
+                &resolved_path,
+                &plan,
+                password,
+                &filter,
+                encoding,
+                &mut on_progress,
+            )
+        }
+        ArchiveFormat::SevenZip => {
+            extract_7z(&resolved_path, &plan, password, &filter, &mut on_progress)
+        }
+        ArchiveFormat::Rar => {
+            extract_rar(&resolved_path, &plan, password, &filter, &mut on_progress)
+        }
+        _ => extract_tar(&resolved_path, &plan, format, &filter, &mut on_progress),
+    };
+    let summary = plan
+        .finish(result)
+        .inspect_err(|err| crate::log_error("archive_extract", &path, &destination, err))?;
+    crate::log_event(
+        "ARCHIVE_EXTRACT",
+        &path,
+        &destination,
+        &format!(
+            "format={:?}; count={}; bytes={}; skipped={}; ms={}",
+            format,
+            summary.count,
+            summary.bytes,
+            summary.skipped.len(),
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(summary)
+}
+
+#[tauri::command]
+pub fn archive_extract_list_conflicts(
+    path: String,
+    destination: String,
+    format: Option<ArchiveFormat>,
+    password: Option<String>,
+    options: Option<ArchiveExtractOptions>,
+) -> Result<Vec<String>, String> {
+    let (resolved_path, resolved_destination) = resolve_extract_paths(&path, &destination)?;
+    let format = resolve_format(format, &resolved_path)?;
+    check_password(format, &password)?;
+    let options = options.unwrap_or_default();
+    let filter = ExtractFilter::new(&options.entries, options.strip_components);
+    let password = password.as_deref();
+    match format {
+        ArchiveFormat::Zip => {
+            let encoding = options.name_encoding;
+            zip_conflicts(
+                &resolved_path,
+                &resolved_destination,
+                password,
+                &filter,
+                encoding,
+            )
+        }
+        ArchiveFormat::SevenZip => {
+            sevenz_conflicts(&resolved_path, &resolved_destination, password, &filter)
+        }
+        ArchiveFormat::Rar => {
+            rar_conflicts(&resolved_path, &resolved_destination, password, &filter)
+        }
+        _ => tar_conflicts(&resolved_path, &resolved_destination, format, &filter),
+    }
+}
+
+/// Lists the entries of an archive in `format` with their totals. `encoding` only applies
+/// to zip names without the UTF-8 flag.
+pub(crate) fn list_archive(
+    path: &Path,
+    format: ArchiveFormat,
+    password: Option<&str>,
+    encoding: ZipNameEncoding,
+) -> Result<ArchiveListing, String> {
+    let entries = match format {
+        ArchiveFormat::Zip => list_zip(path, encoding)?,
+        ArchiveFormat::SevenZip => list_7z(path, password)?,
+        ArchiveFormat::Rar => list_rar(path, password)?,
+        _ => list_tar(path, format)?,
+    };
+    let mut totals = ArchiveListTotals::default();
+    for entry in &entries {
+        if entry.is_dir {
+            totals.dirs += 1;
+        } else {
+            totals.files += 1;
+        }
+        totals.size += entry.size;
+        totals.compressed_size += entry.compressed_size.unwrap_or(0);
+        if entry.encrypted {
+            totals.encrypted += 1;
+        }
+    }
+    if !matches!(
+        format,
+        ArchiveFormat::Zip | ArchiveFormat::SevenZip | ArchiveFormat::Rar
+    ) {
+        totals.compressed_size = fs::metadata(path).map_err(io_failure)?.len();
+    }
+    Ok(ArchiveListing {
+        format,
+        entries,
+        totals,
+    })
+}
+
+#[tauri::command]
+pub fn archive_list(
+    path: String,
+    format: Option<ArchiveFormat>,
+    password: Option<String>,
+    name_encoding: Option<ZipNameEncoding>,
+) -> Result<ArchiveListing, String> {
+    let resolved = resolve_legacy_path_for(&path, ProviderCapability::Read)
+        .map_err(|err| format!("code={}; {}", err.code(), err))?;
+    let format = resolve_format(format, &resolved)?;
+    check_password(format, &password)?;
+    list_archive(
+        &resolved,
+        format,
+        password.as_deref(),
+        name_encoding.unwrap_or_default(),
+    )
+}
+
+/// Reads every entry of an archive in `format` back without writing anything.
+pub(crate) fn test_archive(
+    path: &Path,
+    format: ArchiveFormat,
+    password: Option<&str>,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveTestReport, String> {
+    match format {
+        ArchiveFormat::Zip => verify_zip(path, password, on_progress),
+        ArchiveFormat::SevenZip => verify_7z(path, password, on_progress),
+        ArchiveFormat::Rar => verify_rar(path, password, on_progress),
+        _ => verify_tar(path, format, on_progress),
+    }
+}
+
+#[tauri::command]
+pub fn archive_test(
+    app: AppHandle,
+    path: String,
+    format: Option<ArchiveFormat>,
+    password: Option<String>,
+) -> Result<ArchiveTestReport, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let (resolved, format) = resolve_legacy_path_for(&path, ProviderCapability::Read)
+        .map_err(|err| format!("code={}; {}", err.code(), err))
+        .and_then(|resolved| {
+            let format = resolve_format(format, &resolved)?;
+            check_password(format, &password)?;
+            Ok((resolved, format))
+        })
+        .inspect_err(|err| crate::log_error("archive_test", &path, "-", err))?;
+    let mut on_progress = |progress: &ArchiveProgress| {
+        let _ = app.emit(EVENT_ARCHIVE_PROGRESS, progress.clone());
+    };
+    let report = test_archive(&resolved, format, password.as_deref(), &mut on_progress)
+        .inspect_err(|err| crate::log_error("archive_test", &path, "-", err))?;
+    crate::log_event(
+        "ARCHIVE_TEST",
+        &path,
+        "-",
+        &format!(
+            "format={:?}; count={}; failed={}; bytes={}; ms={}",
+            format,
+            report.count,
+            report.failed,
+            report.bytes,
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_format, list_archive, test_archive};
+    use crate::fs_ops_archive::write_zip;
+    use crate::fs_ops_archive_tar::write_tar;
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{ArchiveFormat, ZipCreateOptions, ZipMethod, ZipNameEncoding};
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn detect_format_uses_full_extension() {
+        assert_eq!(
+            detect_format(Path::new("/tmp/a.ZIP")),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            detect_format(Path::new("rel-1.0.tar.gz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            detect_format(Path::new("x.tgz")),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            detect_format(Path::new("x.tar.zst")),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(
+            detect_format(Path::new("x.txz")),
+            Some(ArchiveFormat::TarXz)
+        );
+        assert_eq!(
+            detect_format(Path::new("vendor.7z")),
+            Some(ArchiveFormat::SevenZip)
+        );
+        assert_eq!(detect_format(Path::new("x.RAR")), Some(ArchiveFormat::Rar));
+        assert_eq!(detect_format(Path::new("x.gz")), None);
+    }
+
+    #[test]
+    fn list_archive_reports_entries_and_totals() {
+        let root = unique_temp_dir("rf-archive-list");
+        let src = root.join("docs");
+        fs::create_dir_all(src.join("sub")).expect("create src");
+        fs::write(src.join("a.txt"), "alpha".repeat(100)).expect("write a");
+        fs::write(src.join("sub").join("b.txt"), b"beta").expect("write b");
+        let sources = vec![src.clone()];
+
+        let zip = root.join("docs.zip");
+        write_zip(
+            &zip,
+            &sources,
+            &Some("secret".to_string()),
+            &ZipCreateOptions::default(),
+            &mut |_| {},
+        )
+        .expect("zip");
+        let listing =
+            list_archive(&zip, ArchiveFormat::Zip, None, ZipNameEncoding::Auto).expect("list zip");
+        let a = listing
+            .entries
+            .iter()
+            .find(|entry| entry.name == "docs/a.txt")
+            .expect("a.txt listed");
+        assert_eq!(a.size, 500);
+        assert!(a.encrypted);
+        assert!(a.crc.is_some());
+        assert!(!a.modified.is_empty());
+        assert_eq!(listing.totals.files, 2);
+        assert_eq!(listing.totals.size, 504);
+        assert_eq!(listing.totals.encrypted, 2);
+
+        let tgz = root.join("docs.tar.gz");
+        write_tar(&tgz, &sources, ArchiveFormat::TarGz, &mut |_| {}).expect("tar");
+        let listing = list_archive(&tgz, ArchiveFormat::TarGz, None, ZipNameEncoding::Auto)
+            .expect("list tar");
+        assert_eq!(listing.totals.files, 2);
+        assert_eq!(listing.totals.dirs, 2);
+        assert_eq!(listing.totals.size, 504);
+        assert_eq!(
+            listing.totals.compressed_size,
+            fs::metadata(&tgz).expect("meta").len()
+        );
+        assert!(listing.entries.iter().all(|entry| entry.method == "gzip"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_archive_reports_damaged_entries() {
+        let root = unique_temp_dir("rf-archive-test");
+        let src = root.join("docs");
+        fs::create_dir_all(&src).expect("create src");
+        fs::write(src.join("a.txt"), "alpha".repeat(20)).expect("write a");
+        fs::write(src.join("b.txt"), "bravo".repeat(20)).expect("write b");
+        let sources = vec![src.clone()];
+
+        let zip = root.join("docs.zip");
+        let options = ZipCreateOptions {
+            method: ZipMethod::Store,
+            ..ZipCreateOptions::default()
+        };
+        write_zip(&zip, &sources, &None, &options, &mut |_| {}).expect("zip");
+        let mut progress = 0;
+        let report =
+            test_archive(&zip, ArchiveFormat::Zip, None, &mut |_| progress += 1).expect("test");
+        assert_eq!((report.count, report.failed, report.bytes), (3, 0, 200));
+        assert!(progress > 0);
+
+        let mut bytes = fs::read(&zip).expect("read zip");
+        let at = bytes
+            .windows(10)
+            .position(|window| window == b"bravobravo")
+            .expect("stored data");
+        bytes[at] = b'X';
+        fs::write(&zip, bytes).expect("damage zip");
+        let report = test_archive(&zip, ArchiveFormat::Zip, None, &mut |_| {}).expect("test");
+        assert_eq!(report.failed, 1);
+        let bad = report
+            .entries
+            .iter()
+            .find(|entry| !entry.ok)
+            .expect("failure");
+        assert_eq!(bad.name, "docs/b.txt");
+        assert!(!bad.error.is_empty());
+
+        let tgz = root.join("docs.tar.gz");
+        write_tar(&tgz, &sources, ArchiveFormat::TarGz, &mut |_| {}).expect("tar");
+        let report = test_archive(&tgz, ArchiveFormat::TarGz, None, &mut |_| {}).expect("test");
+        assert_eq!((report.count, report.failed), (3, 0));
+        let mut bytes = fs::read(&tgz).expect("read tgz");
+        let crc_at = bytes.len() - 8;
+        bytes[crc_at] ^= 0xff;
+        fs::write(&tgz, bytes).expect("damage tgz");
+        let report = test_archive(&tgz, ArchiveFormat::TarGz, None, &mut |_| {}).expect("test");
+        assert_eq!(report.failed, 1);
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt;
+use std::path::Path;
+
+use crate::types::AppConfig;
+use crate::utils::available_space;
+
+/// Error code for archives that break one of the extraction limits.
+pub(crate) const ARCHIVE_LIMIT_CODE: &str = "archive_limit";
+/// Entries below this size skip the ratio check; blank or repetitive data legitimately
+/// compresses far beyond any useful limit.
+const RATIO_GRACE_BYTES: u64 = 1024 * 1024;
+
+/// Marks an I/O error raised because an entry produced more data than its header declared.
+#[derive(Debug)]
+pub(crate) struct SizeExceeded;
+
+impl fmt::Display for SizeExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("archive expands beyond its declared size")
+    }
+}
+
+impl std::error::Error for SizeExceeded {}
+
+/// Extraction limits from `AppConfig`. 0 disables a limit.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct ExtractLimits {
+    pub(crate) max_total_size: u64,
+    pub(crate) max_entries: u64,
+    pub(crate) max_ratio: u64,
+    pub(crate) max_depth: u64,
+}
+
+impl ExtractLimits {
+    pub(crate) fn from_config(config: &AppConfig) -> Self {
+        ExtractLimits {
+            max_total_size: config.archive_max_total_size_mb.saturating_mul(1024 * 1024),
+            max_entries: config.archive_max_entries,
+            max_ratio: config.archive_max_ratio,
+            max_depth: config.archive_max_path_depth,
+        }
+    }
+}
+
+fn limit_error(message: String) -> String {
+    format!("code={ARCHIVE_LIMIT_CODE}; {message}")
+}
+
+/// Running totals checked against `ExtractLimits` and the free space at the destination.
+/// Entries are admitted from their headers, before any of their data is written.
+pub(crate) struct LimitGuard {
+    limits: ExtractLimits,
+    available: Option<u64>,
+    entries: u64,
+    bytes: u64,
+}
+
+impl LimitGuard {
+    pub(crate) fn new(limits: ExtractLimits, root: &Path) -> Self {
+        LimitGuard {
+            limits,
+            available: available_space(root),
+            entries: 0,
+            bytes: 0,
+        }
+    }
+
+    /// `name` is the output name of the entry; `packed` its compressed size when the
+    /// format records one.
+    pub(crate) fn admit(
+        &mut self,
+        name: &str,
+        size: u64,
+        packed: Option<u64>,
+    ) -> Result<(), String> {
+        let limits = self.limits;
+        self.entries += 1;
+        self.bytes = self.bytes.saturating_add(size);
+        if limits.max_entries > 0 && self.entries > limits.max_entries {
+            return Err(limit_error(format!(
+                "archive has more than {} entries",
+                limits.max_entries
+            )));
+        }
+        if limits.max_total_size > 0 && self.bytes > limits.max_total_size {
+            return Err(limit_error(format!(
+                "extracted size exceeds {} bytes",
+                limits.max_total_size
+            )));
+        }
+        let depth = name.split('/').filter(|part| !part.is_empty()).count() as u64;
+        if limits.max_depth > 0 && depth > limits.max_depth {
+            return Err(limit_error(format!(
+                "{name} is nested deeper than {} levels",
+                limits.max_depth
+            )));
+        }
+        if let Some(packed) = packed {
+            let ratio = size / packed.max(1);
+            if limits.max_ratio > 0 && size >= RATIO_GRACE_BYTES && ratio > limits.max_ratio {
+                return Err(limit_error(format!(
+                    "{name} expands {ratio}:1, over the limit of {}:1",
+                    limits.max_ratio
+                )));
+            }
+        }
+        match self.available {
+            Some(available) if self.bytes > available => Err(format!(
+                "code=insufficient_space; extraction needs {} bytes but {} are free",
+                self.bytes, available
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExtractLimits, LimitGuard};
+
+    fn guard(limits: ExtractLimits, available: Option<u64>) -> LimitGuard {
+        LimitGuard {
+            limits,
+            available,
+            entries: 0,
+            bytes: 0,
+        }
+    }
+
+    #[test]
+    fn limit_guard_rejects_entries_over_limits() {
+        let limits = ExtractLimits {
+            max_total_size: 10 * 1024 * 1024,
+            max_entries: 3,
+            max_ratio: 100,
+            max_depth: 3,
+        };
+
+        let mut g = guard(limits, None);
+        assert!(g.admit("a/b/c.txt", 10, Some(5)).is_ok());
+        let err = g.admit("a/b/c/d.txt", 10, Some(5)).expect_err("depth");
+        assert!(err.starts_with("code=archive_limit;"));
+
+        let mut g = guard(limits, None);
+        // Small entries may compress arbitrarily well.
+        assert!(g.admit("zeros.bin", 64 * 1024, Some(1)).is_ok());
+        let err = g
+            .admit("bomb.bin", 2 * 1024 * 1024, Some(1024))
+            .expect_err("ratio");
+        assert!(err.contains("2048:1"));
+
+        let mut g = guard(limits, None);
+        for name in ["1", "2", "3"] {
+            assert!(g.admit(name, 0, None).is_ok());
+        }
+        assert!(g.admit("4", 0, None).is_err());
+
+        let mut g = guard(limits, None);
+        assert!(g.admit("big", 11 * 1024 * 1024, None).is_err());
+
+        let mut g = guard(ExtractLimits::default(), Some(100));
+        assert!(g.admit("a", 60, None).is_ok());
+        let err = g.admit("b", 60, None).expect_err("space");
+        assert!(err.starts_with("code=insufficient_space;"));
+    }
+}
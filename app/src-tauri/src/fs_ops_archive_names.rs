@@ -0,0 +1,355 @@
+use std::borrow::Cow;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use encoding_rs::SHIFT_JIS;
+use zip::{HasZipMetadata, ZipArchive};
+
+use crate::fs_ops_archive::{io_failure, open_zip, zip_open_error};
+use crate::fs_ops_archive_split::ZipVolumes;
+use crate::types::ZipNameEncoding;
+
+/// General purpose flag bit 11: the name is UTF-8.
+const UTF8_FLAG: u16 = 1 << 11;
+const LOCAL_HEADER_LEN: usize = 30;
+pub(crate) const CENTRAL_HEADER_LEN: usize = 46;
+pub(crate) const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+pub(crate) const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+pub(crate) const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+const MOVE_CHUNK: usize = 256 * 1024;
+
+fn cp932_decode(raw: &[u8]) -> Option<String> {
+    SHIFT_JIS
+        .decode_without_bom_handling_and_without_replacement(raw)
+        .map(Cow::into_owned)
+}
+
+/// Picks the encoding for the unflagged names `raw`: UTF-8 when they all are valid UTF-8,
+/// CP932 when they all decode as CP932, CP437 otherwise. Plain ASCII names say nothing.
+fn detect_encoding<'a>(raw: impl Iterator<Item = &'a [u8]> + Clone) -> ZipNameEncoding {
+    let mut names = raw.filter(|name| !name.is_ascii());
+    if names.clone().all(|name| std::str::from_utf8(name).is_ok()) {
+        ZipNameEncoding::Utf8
+    } else if names.all(|name| cp932_decode(name).is_some()) {
+        ZipNameEncoding::Cp932
+    } else {
+        ZipNameEncoding::Cp437
+    }
+}
+
+/// Entry names of `archive` by index. Names flagged as UTF-8 are taken as they are; the
+/// rest are decoded as `encoding`, with `Auto` choosing one encoding for the whole archive.
+pub(crate) fn zip_entry_names(
+    archive: &mut ZipArchive<ZipVolumes>,
+    encoding: ZipNameEncoding,
+) -> Result<Vec<String>, String> {
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(zip_open_error)?;
+        let raw = (!file.get_metadata().is_utf8).then(|| file.name_raw().to_vec());
+        entries.push((file.name().to_string(), raw));
+    }
+    let encoding = match encoding {
+        ZipNameEncoding::Auto => {
+            detect_encoding(entries.iter().filter_map(|(_, raw)| raw.as_deref()))
+        }
+        other => other,
+    };
+    Ok(entries
+        .into_iter()
+        .map(|(name, raw)| match (raw, encoding) {
+            (Some(raw), ZipNameEncoding::Utf8) => String::from_utf8_lossy(&raw).into_owned(),
+            (Some(raw), ZipNameEncoding::Cp932) => {
+                SHIFT_JIS.decode_without_bom_handling(&raw).0.into_owned()
+            }
+            // The zip crate decodes unflagged names as CP437.
+            _ => name,
+        })
+        .collect())
+}
+
+/// The CP932 bytes for `name`, or `None` when it is plain ASCII or CP932 cannot represent it.
+fn cp932_name(name: &str) -> Option<Vec<u8>> {
+    if name.is_ascii() {
+        return None;
+    }
+    let (bytes, _, unmappable) = SHIFT_JIS.encode(name);
+    (!unmappable).then(|| bytes.into_owned())
+}
+
+pub(crate) fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+pub(crate) fn u32_at(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+pub(crate) fn u64_at(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+pub(crate) fn read_at(file: &mut fs::File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_at(file: &mut fs::File, offset: u64, buf: &[u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.write_all(buf)
+}
+
+/// Moves `from..end` down to `to`; chunks go in ascending order so nothing unread is
+/// overwritten.
+fn move_down(file: &mut fs::File, from: u64, end: u64, to: u64) -> io::Result<()> {
+    let mut buf = vec![0; MOVE_CHUNK];
+    let mut done = 0;
+    while from + done < end {
+        let len = MOVE_CHUNK.min((end - from - done) as usize);
+        file.seek(SeekFrom::Start(from + done))?;
+        file.read_exact(&mut buf[..len])?;
+        write_at(file, to + done, &buf[..len])?;
+        done += len as u64;
+    }
+    Ok(())
+}
+
+/// `header` with the name at `name_at` (length field at `len_at`) replaced by `name` and the
+/// UTF-8 flag at `flags_at` cleared.
+fn rename_header(
+    header: &[u8],
+    name_at: usize,
+    len_at: usize,
+    flags_at: usize,
+    name: &[u8],
+) -> Vec<u8> {
+    let old_len = usize::from(u16_at(header, len_at));
+    let mut out = Vec::with_capacity(header.len() - old_len + name.len());
+    out.extend_from_slice(&header[..name_at]);
+    out.extend_from_slice(name);
+    out.extend_from_slice(&header[name_at + old_len..]);
+    out[len_at..len_at + 2].copy_from_slice(&(name.len() as u16).to_le_bytes());
+    let flags = u16_at(&out, flags_at) & !UTF8_FLAG;
+    out[flags_at..flags_at + 2].copy_from_slice(&flags.to_le_bytes());
+    out
+}
+
+/// Length of the central directory header at `at` in `buf`, name, extra and comment included.
+pub(crate) fn central_header_len(buf: &[u8], at: usize) -> io::Result<usize> {
+    buf.get(at..at + CENTRAL_HEADER_LEN)
+        .map(|fixed| {
+            CENTRAL_HEADER_LEN
+                + usize::from(u16_at(fixed, 28))
+                + usize::from(u16_at(fixed, 30))
+                + usize::from(u16_at(fixed, 32))
+        })
+        .filter(|len| at + len <= buf.len())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated central directory"))
+}
+
+/// Position of the local header offset in the zip64 extra field of the central `header`.
+fn zip64_offset_at(header: &[u8]) -> io::Result<usize> {
+    let extra_at = CENTRAL_HEADER_LEN + usize::from(u16_at(header, 28));
+    let extra_end = (extra_at + usize::from(u16_at(header, 30))).min(header.len());
+    let mut at = extra_at;
+    while at + 4 <= extra_end {
+        let (id, len) = (u16_at(header, at), usize::from(u16_at(header, at + 2)));
+        if id == ZIP64_EXTRA_ID {
+            // Only the saturated fields are present, in this order.
+            let mut field = at + 4;
+            field += 8 * usize::from(u32_at(header, 24) == u32::MAX);
+            field += 8 * usize::from(u32_at(header, 20) == u32::MAX);
+            if field + 8 <= (at + 4 + len).min(extra_end) {
+                return Ok(field);
+            }
+        }
+        at += 4 + len;
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "zip64 extra field missing",
+    ))
+}
+
+/// Offset of the local header the central `header` points at.
+pub(crate) fn local_offset(header: &[u8]) -> io::Result<u64> {
+    match u32_at(header, 42) {
+        u32::MAX => zip64_offset_at(header).map(|at| u64_at(header, at)),
+        offset => Ok(u64::from(offset)),
+    }
+}
+
+/// Points the central `header` at the local header now at `offset`, through its zip64
+/// extra field when the fixed field is saturated.
+pub(crate) fn set_local_offset(header: &mut [u8], offset: u64) -> io::Result<()> {
+    if u32_at(header, 42) != u32::MAX {
+        header[42..46].copy_from_slice(&(offset as u32).to_le_bytes());
+        return Ok(());
+    }
+    let at = zip64_offset_at(header)?;
+    header[at..at + 8].copy_from_slice(&offset.to_le_bytes());
+    Ok(())
+}
+
+/// Rewrites the finished zip at `path` in place so entry names CP932 can represent are
+/// stored in CP932 without the UTF-8 flag. CP932 names are never longer than their UTF-8
+/// form, so every header only moves towards the start of the file.
+pub(crate) fn write_cp932_names(path: &Path) -> Result<(), String> {
+    let mut archive = open_zip(path)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(zip_open_error)?;
+        entries.push((
+            file.header_start(),
+            file.data_start(),
+            cp932_name(file.name()),
+        ));
+    }
+    let cd_start = archive.central_directory_start();
+    drop(archive);
+    rewrite_names(path, &entries, cd_start).map_err(io_failure)
+}
+
+fn rewrite_names(
+    path: &Path,
+    entries: &[(u64, u64, Option<Vec<u8>>)],
+    cd_start: u64,
+) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let file_len = file.metadata()?.len();
+
+    // Local headers and data, in file order.
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries[i].0);
+    let mut offsets = vec![0; entries.len()];
+    let mut pos = 0;
+    for (k, &i) in order.iter().enumerate() {
+        let (header_start, data_start, name) = &entries[i];
+        let end = order.get(k + 1).map_or(cd_start, |&next| entries[next].0);
+        let mut header = read_at(
+            &mut file,
+            *header_start,
+            (data_start - header_start) as usize,
+        )?;
+        if let Some(name) = name {
+            header = rename_header(&header, LOCAL_HEADER_LEN, 26, 6, name);
+        }
+        write_at(&mut file, pos, &header)?;
+        offsets[i] = pos;
+        pos += header.len() as u64;
+        move_down(&mut file, *data_start, end, pos)?;
+        pos += end - data_start;
+    }
+
+    // Everything from the central directory on is read before any of it is overwritten.
+    let rest = read_at(&mut file, cd_start, (file_len - cd_start) as usize)?;
+    let new_cd_start = pos;
+    let mut directory = Vec::with_capacity(rest.len());
+    let mut at = 0;
+    for (i, (_, _, name)) in entries.iter().enumerate() {
+        let len = central_header_len(&rest, at)?;
+        let mut header = rest[at..at + len].to_vec();
+        if let Some(name) = name {
+            header = rename_header(&header, CENTRAL_HEADER_LEN, 28, 8, name);
+        }
+        set_local_offset(&mut header, offsets[i])?;
+        directory.extend_from_slice(&header);
+        at += len;
+    }
+    let cd_size = directory.len() as u64;
+
+    let mut tail = rest[at..].to_vec();
+    let mut end = 0;
+    if tail.len() >= 56 && u32_at(&tail, 0) == ZIP64_EOCD_SIGNATURE {
+        tail[40..48].copy_from_slice(&cd_size.to_le_bytes());
+        tail[48..56].copy_from_slice(&new_cd_start.to_le_bytes());
+        end = 12 + u64_at(&tail, 4) as usize;
+    }
+    if tail.len() >= end + 20 && u32_at(&tail, end) == ZIP64_LOCATOR_SIGNATURE {
+        let record = new_cd_start + cd_size;
+        tail[end + 8..end + 16].copy_from_slice(&record.to_le_bytes());
+        end += 20;
+    }
+    if tail.len() < end + 22 || u32_at(&tail, end) != EOCD_SIGNATURE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "end of central directory not found",
+        ));
+    }
+    if u32_at(&tail, end + 12) != u32::MAX {
+        tail[end + 12..end + 16].copy_from_slice(&(cd_size as u32).to_le_bytes());
+    }
+    if u32_at(&tail, end + 16) != u32::MAX {
+        tail[end + 16..end + 20].copy_from_slice(&(new_cd_start as u32).to_le_bytes());
+    }
+
+    write_at(&mut file, new_cd_start, &directory)?;
+    file.write_all(&tail)?;
+    file.set_len(new_cd_start + cd_size + tail.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::zip_entry_names;
+    use crate::fs_ops_archive::{open_zip, write_zip};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{ZipCreateOptions, ZipNameEncoding};
+    use std::fs;
+    use std::io::Read;
+    use zip::HasZipMetadata;
+
+    #[test]
+    fn cp932_names_are_written_and_detected() {
+        let root = unique_temp_dir("rf-zip-names");
+        let src = root.join("資料");
+        fs::create_dir_all(&src).expect("create src");
+        fs::write(src.join("見積書.txt"), b"quote").expect("write quote");
+        fs::write(src.join("emoji 🎉.txt"), b"party").expect("write emoji");
+        fs::write(src.join("plain.txt"), b"plain").expect("write plain");
+        let archive = root.join("docs.zip");
+        let options = ZipCreateOptions {
+            cp932_names: true,
+            ..ZipCreateOptions::default()
+        };
+        let password = Some("secret".to_string());
+        write_zip(&archive, &[src], &password, &options, &mut |_| {}).expect("write zip");
+
+        let mut zip = open_zip(&archive).expect("open");
+        let quote = "資料/見積書.txt";
+        let raw: Vec<_> = (0..zip.len())
+            .map(|i| {
+                let file = zip.by_index_raw(i).expect("entry");
+                (file.name_raw().to_vec(), file.get_metadata().is_utf8)
+            })
+            .collect();
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode(quote);
+        assert!(raw.contains(&(sjis.into_owned(), false)));
+        // Names CP932 cannot hold keep UTF-8 and its flag.
+        assert!(raw.contains(&("資料/emoji 🎉.txt".as_bytes().to_vec(), true)));
+
+        let mut names = zip_entry_names(&mut zip, ZipNameEncoding::Auto).expect("names");
+        names.sort();
+        assert_eq!(
+            names,
+            ["資料/", "資料/emoji 🎉.txt", "資料/plain.txt", quote]
+        );
+        let index = zip_entry_names(&mut zip, ZipNameEncoding::Cp932)
+            .expect("names")
+            .iter()
+            .position(|name| name == quote)
+            .expect("quote entry");
+        let mut content = String::new();
+        zip.by_index_decrypt(index, b"secret")
+            .expect("decrypt")
+            .read_to_string(&mut content)
+            .expect("read");
+        assert_eq!(content, "quote");
+        let cp437 = zip_entry_names(&mut zip, ZipNameEncoding::Cp437).expect("names");
+        assert!(!cp437.iter().any(|name| name == quote));
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use unrar::error::{Code, UnrarError};
+use unrar::Archive;
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_archive::{
+    bad_password, ensure_inside, io_failure, local_time, prepare_file_target, zip_safe_path,
+    ArchiveTracker, ExtractFilter, ExtractPlan,
+};
+use crate::fs_ops_transfer_helpers::cancel_requested;
+use crate::types::{
+    ArchiveListEntry, ArchiveOp, ArchiveProgress, ArchiveSummary, ArchiveTestEntry,
+    ArchiveTestReport,
+};
+use crate::utils::system_time_to_rfc3339;
+
+fn rar_error(err: UnrarError) -> String {
+    match err.code {
+        Code::MissingPassword | Code::BadPassword => bad_password(),
+        _ => format_error(AppErrorKind::Unknown, err.to_string()),
+    }
+}
+
+fn rar_archive<'a>(path: &'a Path, password: Option<&'a str>) -> Archive<'a> {
+    match password {
+        Some(pass) => Archive::with_password(path, pass),
+        None => Archive::new(path),
+    }
+}
+
+fn rar_method_name(method: u32) -> String {
+    match method {
+        0x30 => "store".to_string(),
+        0x31 => "fastest".to_string(),
+        0x32 => "fast".to_string(),
+        0x33 => "normal".to_string(),
+        0x34 => "good".to_string(),
+        0x35 => "best".to_string(),
+        other => format!("0x{other:x}"),
+    }
+}
+
+/// `file_time` is packed in MS-DOS format, in local time.
+fn rar_time(packed: u32) -> Option<SystemTime> {
+    local_time(
+        1980 + (packed >> 25) as i32,
+        (packed >> 21) & 0x0f,
+        (packed >> 16) & 0x1f,
+        (packed >> 11) & 0x1f,
+        (packed >> 5) & 0x3f,
+        (packed & 0x1f) * 2,
+    )
+}
+
+/// Header-level listing with `/` separators. unrar does not report packed sizes.
+pub(crate) fn list_rar(
+    path: &Path,
+    password: Option<&str>,
+) -> Result<Vec<ArchiveListEntry>, String> {
+    let mut cursor = rar_archive(path, password)
+        .open_for_listing()
+        .map_err(rar_error)?;
+    let mut entries = Vec::new();
+    while let Some(header) = cursor.read_header().map_err(rar_error)? {
+        let entry = header.entry();
+        entries.push(ArchiveListEntry {
+            name: entry.filename.to_string_lossy().replace('\\', "/"),
+            is_dir: entry.is_directory(),
+            size: entry.unpacked_size,
+            compressed_size: None,
+            method: rar_method_name(entry.method),
+            modified: system_time_to_rfc3339(rar_time(entry.file_time)),
+            crc: Some(entry.file_crc),
+            encrypted: entry.is_encrypted(),
+        });
+        cursor = header.skip().map_err(rar_error)?;
+    }
+    Ok(entries)
+}
+
+/// Extracts the entries of the RAR archive at `path` that `filter` selects as laid out by
+/// `plan`. Every target is checked against the plan's root before unrar writes it;
+/// cancellation is honored between entries.
+pub(crate) fn extract_rar(
+    path: &Path,
+    plan: &ExtractPlan,
+    password: Option<&str>,
+    filter: &ExtractFilter,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    let root = plan.root();
+    let mut guard = plan.limit_guard();
+    let mut selected = 0;
+    let mut bytes_total = 0;
+    for entry in list_rar(path, password)? {
+        if let Some(target) = filter.target(&entry.name) {
+            guard.admit(&target, entry.size, entry.compressed_size)?;
+            selected += 1;
+            bytes_total += entry.size;
+        }
+    }
+    let mut tracker = ArchiveTracker::new(ArchiveOp::Extract, selected, bytes_total, on_progress);
+    let mut cursor = rar_archive(path, password)
+        .open_for_processing()
+        .map_err(rar_error)?;
+    while let Some(header) = cursor.read_header().map_err(rar_error)? {
+        if cancel_requested() {
+            return Err("code=canceled; canceled".to_string());
+        }
+        let entry = header.entry();
+        let name = entry.filename.to_string_lossy().replace('\\', "/");
+        let Some(target) = filter.target(&name) else {
+            cursor = header.skip().map_err(rar_error)?;
+            continue;
+        };
+        tracker.start_entry(&name);
+        if entry.is_directory() {
+            let outpath = zip_safe_path(root, &target)?;
+            fs::create_dir_all(&outpath).map_err(io_failure)?;
+            ensure_inside(root, &outpath)?;
+            cursor = header.skip().map_err(rar_error)?;
+        } else {
+            let size = entry.unpacked_size;
+            let Some(outpath) = plan.file_target(&target, rar_time(entry.file_time))? else {
+                tracker.skip_entry(&name, size);
+                cursor = header.skip().map_err(rar_error)?;
+                continue;
+            };
+            prepare_file_target(root, &outpath)?;
+            let encrypted = entry.is_encrypted();
+            cursor = header.extract_to(&outpath).map_err(|err| {
+                // RAR4 reports a wrong key as a CRC failure.
+                if encrypted && err.code == Code::BadData {
+                    bad_password()
+                } else {
+                    rar_error(err)
+                }
+            })?;
+            tracker.add_bytes(size);
+        }
+        tracker.finish_entry();
+    }
+    Ok(tracker.finish())
+}
+
+/// Names of selected file entries that already exist under `dest`.
+pub(crate) fn rar_conflicts(
+    path: &Path,
+    dest: &Path,
+    password: Option<&str>,
+    filter: &ExtractFilter,
+) -> Result<Vec<String>, String> {
+    let mut conflicts = Vec::new();
+    for entry in list_rar(path, password)? {
+        if entry.is_dir {
+            continue;
+        }
+        let Some(target) = filter.target(&entry.name) else {
+            continue;
+        };
+        let outpath = zip_safe_path(dest, &target)?;
+        if fs::symlink_metadata(&outpath).is_ok() {
+            conflicts.push(entry.name);
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Runs unrar's test mode over the RAR archive at `path`, which checks every entry's CRC.
+/// A failed entry consumes the cursor, so it ends the test.
+pub(crate) fn verify_rar(
+    path: &Path,
+    password: Option<&str>,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveTestReport, String> {
+    let listed = list_rar(path, password)?;
+    let bytes_total = listed.iter().map(|entry| entry.size).sum();
+    let mut tracker = ArchiveTracker::new(
+        ArchiveOp::Test,
+        listed.len() as u64,
+        bytes_total,
+        on_progress,
+    );
+    let mut entries = Vec::with_capacity(listed.len());
+    let mut cursor = rar_archive(path, password)
+        .open_for_processing()
+        .map_err(rar_error)?;
+    while let Some(header) = cursor.read_header().map_err(rar_error)? {
+        if cancel_requested() {
+            return Err("code=canceled; canceled".to_string());
+        }
+        let entry = header.entry();
+        let name = entry.filename.to_string_lossy().replace('\\', "/");
+        let size = entry.unpacked_size;
+        let encrypted = entry.is_encrypted();
+        tracker.start_entry(&name);
+        match header.test() {
+            Ok(next) => {
+                cursor = next;
+                tracker.add_bytes(size);
+                tracker.finish_entry();
+                entries.push(ArchiveTestEntry::new(name, Ok(())));
+            }
+            // RAR4 reports a wrong key as a CRC failure.
+            Err(err) if encrypted && err.code == Code::BadData => return Err(bad_password()),
+            Err(err) => {
+                let err = rar_error(err);
+                if err == bad_password() {
+                    return Err(err);
+                }
+                entries.push(ArchiveTestEntry::new(name, Err(err)));
+                break;
+            }
+        }
+    }
+    Ok(tracker.finish_test(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_rar, list_rar, rar_method_name, rar_time};
+    use crate::fs_ops_archive::{local_time, ExtractFilter, ExtractPlan};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::ConflictPolicy;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn rar_methods_and_dos_times_decode() {
+        assert_eq!(rar_method_name(0x30), "store");
+        assert_eq!(rar_method_name(0x33), "normal");
+        assert_eq!(rar_method_name(0x35), "best");
+        assert_eq!(rar_method_name(0x40), "0x40");
+
+        // 2024-01-02 03:04:10: seconds are stored halved.
+        let packed = (44 << 25) | (1 << 21) | (2 << 16) | (3 << 11) | (4 << 5) | 5;
+        assert_eq!(rar_time(packed), local_time(2024, 1, 2, 3, 4, 10));
+        assert_eq!(rar_time(0), None);
+    }
+
+    #[test]
+    fn extract_rar_rejects_traversal() {
+        // Stored RAR 4 archive holding `ok.txt` and `../evil.txt`.
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/traversal.rar");
+        let names: Vec<String> = list_rar(&fixture, None)
+            .expect("list")
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, ["ok.txt", "../evil.txt"]);
+
+        let root = unique_temp_dir("rf-rar");
+        let out = root.join("out");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        let err = extract_rar(
+            &fixture,
+            &plan,
+            None,
+            &ExtractFilter::default(),
+            &mut |_| {},
+        )
+        .expect_err("traversal");
+        assert!(err.starts_with("code=invalid_path;"));
+        assert!(!root.join("evil.txt").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,677 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::fs_ops_archive::{io_failure, open_zip, zip_open_error};
+use crate::fs_ops_archive_names::{
+    central_header_len, local_offset, read_at, set_local_offset, u16_at, u32_at, u64_at,
+    EOCD_SIGNATURE, ZIP64_EOCD_SIGNATURE, ZIP64_LOCATOR_SIGNATURE,
+};
+use crate::fs_ops_transfer_helpers::ensure_not_canceled;
+use crate::utils::available_space;
+
+/// Opens the first volume of a split archive.
+const SPLIT_SIGNATURE: u32 = 0x0807_4b50;
+/// Left by writers that meant to split but ended up with a single volume.
+const SINGLE_SPLIT_SIGNATURE: u32 = 0x3030_4b50;
+/// Every header has to fit into one volume, so volumes cannot be arbitrarily small.
+const MIN_VOLUME_SIZE: u64 = 64 * 1024;
+const COPY_CHUNK: usize = 256 * 1024;
+const EOCD_LEN: usize = 22;
+const ZIP64_LOCATOR_LEN: usize = 20;
+/// The end record, a zip64 locator before it and the longest possible archive comment.
+const EOCD_SEARCH: u64 = (EOCD_LEN + ZIP64_LOCATOR_LEN + u16::MAX as usize) as u64;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Path of volume `index` (from 0) of the split set whose last volume is `path`:
+/// `.z01`, `.z02`, ... in the case of the `.zip` extension.
+pub(crate) fn volume_path(path: &Path, index: usize) -> PathBuf {
+    let upper = path
+        .extension()
+        .is_some_and(|ext| ext.to_string_lossy().starts_with('Z'));
+    let prefix = if upper { 'Z' } else { 'z' };
+    path.with_extension(format!("{prefix}{:02}", index + 1))
+}
+
+pub(crate) fn check_volume_size(volume_size: Option<u64>) -> Result<(), String> {
+    match volume_size {
+        Some(size) if size < MIN_VOLUME_SIZE => Err(format!(
+            "code=invalid_volume_size; volumes must hold at least {MIN_VOLUME_SIZE} bytes"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A `.z01`-style volume already next to `path`, which a split set written there would
+/// collide with.
+pub(crate) fn existing_volume(path: &Path) -> Option<PathBuf> {
+    let stem = path.file_stem()?;
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty());
+    let entries = fs::read_dir(parent.unwrap_or(Path::new("."))).ok()?;
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|candidate| {
+            candidate.file_stem() == Some(stem)
+                && candidate
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        let ext = ext.as_bytes();
+                        ext.len() == 3
+                            && ext[0].eq_ignore_ascii_case(&b'z')
+                            && ext[1..].iter().all(u8::is_ascii_digit)
+                    })
+        })
+}
+
+/// Splitting copies the finished archive into its volumes before the original goes, so
+/// `dest` needs room for it twice over. `bytes` is the uncompressed size of the sources,
+/// which bounds the archive apart from its headers.
+pub(crate) fn check_split_space(dest: &Path, bytes: u64) -> Result<(), String> {
+    let needed = bytes.saturating_mul(2);
+    match dest.parent().and_then(available_space) {
+        Some(available) if needed > available => Err(format!(
+            "code=insufficient_space; splitting needs {needed} bytes but {available} are free"
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Writes volumes of a split set one after another, created fresh so nothing already on
+/// disk is overwritten.
+struct VolumeWriter<'a> {
+    path: &'a Path,
+    size: u64,
+    created: Vec<PathBuf>,
+    file: Option<BufWriter<File>>,
+    written: u64,
+}
+
+impl<'a> VolumeWriter<'a> {
+    fn new(path: &'a Path, size: u64) -> Self {
+        VolumeWriter {
+            path,
+            size,
+            created: Vec::new(),
+            file: None,
+            written: 0,
+        }
+    }
+
+    /// Index of the volume being written.
+    fn disk(&self) -> u32 {
+        self.created.len().saturating_sub(1) as u32
+    }
+
+    fn next_volume(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        let path = volume_path(self.path, self.created.len());
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        self.created.push(path);
+        self.file = Some(BufWriter::new(file));
+        self.written = 0;
+        Ok(())
+    }
+
+    /// Starts the next volume unless `len` more bytes fit into the current one, and returns
+    /// where they will go.
+    fn reserve(&mut self, len: usize) -> io::Result<(u32, u64)> {
+        if len as u64 > self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "volume size is too small for the archive headers",
+            ));
+        }
+        if self.file.is_none() || self.written + len as u64 > self.size {
+            self.next_volume()?;
+        }
+        Ok((self.disk(), self.written))
+    }
+
+    fn write_raw(&mut self, buf: &[u8]) -> io::Result<()> {
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| invalid_data("no volume"))?;
+        file.write_all(buf)?;
+        self.written += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Writes `buf` into a single volume.
+    fn write_whole(&mut self, buf: &[u8]) -> io::Result<(u32, u64)> {
+        let at = self.reserve(buf.len())?;
+        self.write_raw(buf)?;
+        Ok(at)
+    }
+
+    /// Writes `buf`, continuing in the next volumes as they fill up.
+    fn write_spanning(&mut self, mut buf: &[u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            if self.file.is_none() || self.written == self.size {
+                self.next_volume()?;
+            }
+            let len = buf.len().min((self.size - self.written) as usize);
+            self.write_raw(&buf[..len])?;
+            buf = &buf[len..];
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.file.take() {
+            Some(mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Splits the finished zip at `path` into volumes of at most `volume_size` bytes: `.z01`,
+/// `.z02`, ... and lastly `path` itself. Only file data is cut across volumes; headers and
+/// the end records always sit whole in one. Archives that fit into one volume are left as
+/// they are. The volumes are copied out of the finished archive, so both exist on disk
+/// until the split is done; see `check_split_space`. Returns the number of volumes.
+pub(crate) fn split_zip(path: &Path, volume_size: u64) -> Result<usize, String> {
+    let len = fs::metadata(path).map_err(io_failure)?.len();
+    if len <= volume_size {
+        return Ok(1);
+    }
+    let mut archive = open_zip(path)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).map_err(zip_open_error)?;
+        entries.push((file.header_start(), file.data_start()));
+    }
+    let cd_start = archive.central_directory_start();
+    drop(archive);
+    let mut writer = VolumeWriter::new(path, volume_size);
+    let result = write_volumes(path, &mut writer, &entries, cd_start, len);
+    let result = result.and_then(|()| {
+        let last = writer
+            .created
+            .last()
+            .ok_or_else(|| invalid_data("no volume"))?;
+        fs::rename(last, path)
+    });
+    if let Err(err) = result {
+        drop(writer.file.take());
+        for volume in &writer.created {
+            let _ = fs::remove_file(volume);
+        }
+        return Err(io_failure(err));
+    }
+    Ok(writer.created.len())
+}
+
+fn write_volumes(
+    path: &Path,
+    writer: &mut VolumeWriter,
+    entries: &[(u64, u64)],
+    cd_start: u64,
+    len: u64,
+) -> io::Result<()> {
+    let mut input = File::open(path)?;
+    writer.write_whole(&SPLIT_SIGNATURE.to_le_bytes())?;
+
+    // Local headers stay whole; the data after them may span volumes.
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries[i].0);
+    let mut locations = vec![(0, 0); entries.len()];
+    let mut buf = vec![0; COPY_CHUNK];
+    for (k, &i) in order.iter().enumerate() {
+        let (header_start, data_start) = entries[i];
+        let end = order.get(k + 1).map_or(cd_start, |&next| entries[next].0);
+        let header = read_at(
+            &mut input,
+            header_start,
+            (data_start - header_start) as usize,
+        )?;
+        locations[i] = writer.write_whole(&header)?;
+        let mut left = end - data_start;
+        while left > 0 {
+            ensure_not_canceled()?;
+            let chunk = &mut buf[..left.min(COPY_CHUNK as u64) as usize];
+            input.read_exact(chunk)?;
+            writer.write_spanning(chunk)?;
+            left -= chunk.len() as u64;
+        }
+    }
+
+    let rest = read_at(&mut input, cd_start, (len - cd_start) as usize)?;
+    let mut at = 0;
+    let mut cd_location = None;
+    // Volume of the latest directory entry and how many entries went into it.
+    let mut last_entries = (u32::MAX, 0u64);
+    for &(disk, offset) in &locations {
+        let entry_len = central_header_len(&rest, at)?;
+        let mut header = rest[at..at + entry_len].to_vec();
+        let disk_field = u16::try_from(disk).map_err(|_| invalid_data("too many volumes"))?;
+        header[34..36].copy_from_slice(&disk_field.to_le_bytes());
+        set_local_offset(&mut header, offset)?;
+        let location = writer.write_whole(&header)?;
+        cd_location.get_or_insert(location);
+        if last_entries.0 != location.0 {
+            last_entries = (location.0, 0);
+        }
+        last_entries.1 += 1;
+        at += entry_len;
+    }
+
+    // The end records go into the last volume, so the disk they land on is known first.
+    let mut tail = rest[at..].to_vec();
+    let (disk, tail_offset) = writer.reserve(tail.len())?;
+    let cd_location = cd_location.unwrap_or((disk, tail_offset));
+    let entries_on_disk = if last_entries.0 == disk {
+        last_entries.1
+    } else {
+        0
+    };
+    let last_disk = u16::try_from(disk)
+        .ok()
+        .filter(|disk| *disk != u16::MAX)
+        .ok_or_else(|| invalid_data("too many volumes"))?;
+    let mut eocd = 0;
+    if tail.len() >= 56 && u32_at(&tail, 0) == ZIP64_EOCD_SIGNATURE {
+        tail[16..20].copy_from_slice(&disk.to_le_bytes());
+        tail[20..24].copy_from_slice(&cd_location.0.to_le_bytes());
+        tail[24..32].copy_from_slice(&entries_on_disk.to_le_bytes());
+        tail[48..56].copy_from_slice(&cd_location.1.to_le_bytes());
+        eocd = 12 + u64_at(&tail, 4) as usize;
+        if tail.len() >= eocd + ZIP64_LOCATOR_LEN && u32_at(&tail, eocd) == ZIP64_LOCATOR_SIGNATURE
+        {
+            tail[eocd + 4..eocd + 8].copy_from_slice(&disk.to_le_bytes());
+            tail[eocd + 8..eocd + 16].copy_from_slice(&tail_offset.to_le_bytes());
+            tail[eocd + 16..eocd + 20].copy_from_slice(&(disk + 1).to_le_bytes());
+            eocd += ZIP64_LOCATOR_LEN;
+        }
+    }
+    if tail.len() < eocd + EOCD_LEN || u32_at(&tail, eocd) != EOCD_SIGNATURE {
+        return Err(invalid_data("end of central directory not found"));
+    }
+    tail[eocd + 4..eocd + 6].copy_from_slice(&last_disk.to_le_bytes());
+    tail[eocd + 6..eocd + 8].copy_from_slice(&(cd_location.0 as u16).to_le_bytes());
+    if u16_at(&tail, eocd + 8) != u16::MAX {
+        tail[eocd + 8..eocd + 10].copy_from_slice(&(entries_on_disk as u16).to_le_bytes());
+    }
+    if u32_at(&tail, eocd + 16) != u32::MAX {
+        tail[eocd + 16..eocd + 20].copy_from_slice(&(cd_location.1 as u32).to_le_bytes());
+    }
+    writer.write_raw(&tail)?;
+    writer.finish()
+}
+
+enum Segment {
+    /// `len` bytes of volume `index` from `offset`.
+    Volume {
+        index: usize,
+        offset: u64,
+        len: u64,
+    },
+    Bytes(Vec<u8>),
+}
+
+/// Read access to a zip that may be split into volumes. A split set reads as the single
+/// archive it was cut from: the volumes back to back, followed by a central directory whose
+/// offsets count from the start of that stream.
+pub(crate) struct ZipVolumes {
+    paths: Vec<PathBuf>,
+    /// Segments with their start in the stream.
+    segments: Vec<(u64, Segment)>,
+    len: u64,
+    pos: u64,
+    open: Option<(usize, File)>,
+}
+
+impl ZipVolumes {
+    /// Opens `path`, together with the volumes before it when it ends a split set.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        if volume_path(path, 0).is_file() {
+            if let Some(volumes) = Self::open_split(path)? {
+                return Ok(volumes);
+            }
+        }
+        let len = fs::metadata(path)?.len();
+        let segment = Segment::Volume {
+            index: 0,
+            offset: 0,
+            len,
+        };
+        Ok(Self::new(vec![path.to_path_buf()], vec![segment]))
+    }
+
+    pub(crate) fn is_split(&self) -> bool {
+        self.paths.len() > 1
+    }
+
+    fn new(paths: Vec<PathBuf>, segments: Vec<Segment>) -> Self {
+        let mut len = 0;
+        let segments = segments
+            .into_iter()
+            .map(|segment| {
+                let start = len;
+                len += match &segment {
+                    Segment::Volume { len, .. } => *len,
+                    Segment::Bytes(bytes) => bytes.len() as u64,
+                };
+                (start, segment)
+            })
+            .collect();
+        ZipVolumes {
+            paths,
+            segments,
+            len,
+            pos: 0,
+            open: None,
+        }
+    }
+
+    /// `None` when the end record of `path` does not describe a split set.
+    fn open_split(path: &Path) -> io::Result<Option<Self>> {
+        let mut last = File::open(path)?;
+        let last_len = last.metadata()?.len();
+        let search = last_len.min(EOCD_SEARCH);
+        let tail = read_at(&mut last, last_len - search, search as usize)?;
+        let found = (0..=tail.len().saturating_sub(EOCD_LEN)).rev().find(|&at| {
+            tail.len() >= at + EOCD_LEN
+                && u32_at(&tail, at) == EOCD_SIGNATURE
+                && at + EOCD_LEN + usize::from(u16_at(&tail, at + 20)) <= tail.len()
+        });
+        let Some(eocd_at) = found else {
+            return Ok(None);
+        };
+        let eocd_end = eocd_at + EOCD_LEN + usize::from(u16_at(&tail, eocd_at + 20));
+        let locator = (eocd_at >= ZIP64_LOCATOR_LEN)
+            .then(|| eocd_at - ZIP64_LOCATOR_LEN)
+            .filter(|&at| u32_at(&tail, at) == ZIP64_LOCATOR_SIGNATURE);
+        let volumes = match locator {
+            Some(at) => u32_at(&tail, at + 16) as usize,
+            None => usize::from(u16_at(&tail, eocd_at + 4)) + 1,
+        };
+        if volumes < 2 {
+            return Ok(None);
+        }
+        let mut paths: Vec<PathBuf> = (0..volumes - 1).map(|i| volume_path(path, i)).collect();
+        if let Some(missing) = paths.iter().find(|volume| !volume.is_file()) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("volume {} is missing", missing.display()),
+            ));
+        }
+        paths.push(path.to_path_buf());
+        let lens = paths
+            .iter()
+            .map(|volume| fs::metadata(volume).map(|meta| meta.len()))
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut volumes = Self::new(paths, Vec::new());
+        let mut signature = [0; 4];
+        volumes.read_span(&lens, 0, 0, &mut signature)?;
+        let skip = match u32::from_le_bytes(signature) {
+            SPLIT_SIGNATURE | SINGLE_SPLIT_SIGNATURE => 4,
+            _ => 0,
+        };
+        let mut bases = Vec::with_capacity(lens.len());
+        let mut base = 0;
+        for (index, len) in lens.iter().enumerate() {
+            let skipped = if index == 0 { skip } else { 0 };
+            bases.push(base);
+            base += len - skipped;
+        }
+        let stream_at = |disk: u64, offset: u64| -> io::Result<u64> {
+            let disk = usize::try_from(disk).map_err(|_| invalid_data("bad volume number"))?;
+            let skipped = if disk == 0 { skip } else { 0 };
+            match bases.get(disk) {
+                Some(base) if offset >= skipped && offset <= lens[disk] => {
+                    Ok(base + offset - skipped)
+                }
+                _ => Err(invalid_data("offset outside the split set")),
+            }
+        };
+
+        let (cd_disk, cd_offset, cd_size, zip64) = match locator {
+            Some(at) => {
+                let disk = u32_at(&tail, at + 4) as usize;
+                let offset = u64_at(&tail, at + 8);
+                let mut fixed = [0; 56];
+                volumes.read_span(&lens, disk, offset, &mut fixed)?;
+                if u32_at(&fixed, 0) != ZIP64_EOCD_SIGNATURE {
+                    return Err(invalid_data("zip64 end record not found"));
+                }
+                let mut record = vec![0; 12 + u64_at(&fixed, 4) as usize];
+                volumes.read_span(&lens, disk, offset, &mut record)?;
+                let cd_disk = u64::from(u32_at(&record, 20));
+                (
+                    cd_disk,
+                    u64_at(&record, 48),
+                    u64_at(&record, 40),
+                    Some(record),
+                )
+            }
+            None => (
+                u64::from(u16_at(&tail, eocd_at + 6)),
+                u64::from(u32_at(&tail, eocd_at + 16)),
+                u64::from(u32_at(&tail, eocd_at + 12)),
+                None,
+            ),
+        };
+        let cd_start = stream_at(cd_disk, cd_offset)?;
+        let mut directory = vec![0; cd_size as usize];
+        volumes.read_span(&lens, cd_disk as usize, cd_offset, &mut directory)?;
+
+        let mut at = 0;
+        while at < directory.len() {
+            let len = central_header_len(&directory, at)?;
+            let header = &mut directory[at..at + len];
+            let disk = u16_at(header, 34);
+            if disk == u16::MAX {
+                return Err(invalid_data("zip64 volume numbers are not supported"));
+            }
+            let offset = stream_at(u64::from(disk), local_offset(header)?)?;
+            if u32_at(header, 42) != u32::MAX && offset >= u64::from(u32::MAX) {
+                return Err(invalid_data("split archive is too large without zip64"));
+            }
+            header[34..36].copy_from_slice(&0u16.to_le_bytes());
+            set_local_offset(header, offset)?;
+            at += len;
+        }
+
+        let mut end = directory;
+        if let Some(mut record) = zip64 {
+            let record_at = cd_start + cd_size;
+            let total = u64_at(&record, 32);
+            record[16..24].fill(0);
+            record[24..32].copy_from_slice(&total.to_le_bytes());
+            record[48..56].copy_from_slice(&cd_start.to_le_bytes());
+            end.extend_from_slice(&record);
+            let locator = locator.unwrap_or_default();
+            let mut locator = tail[locator..locator + ZIP64_LOCATOR_LEN].to_vec();
+            locator[4..8].fill(0);
+            locator[8..16].copy_from_slice(&record_at.to_le_bytes());
+            locator[16..20].copy_from_slice(&1u32.to_le_bytes());
+            end.extend_from_slice(&locator);
+        }
+        let mut eocd = tail[eocd_at..eocd_end].to_vec();
+        eocd[4..8].fill(0);
+        let total = u16_at(&eocd, 10);
+        eocd[8..10].copy_from_slice(&total.to_le_bytes());
+        if u32_at(&eocd, 16) != u32::MAX {
+            let offset = u32::try_from(cd_start)
+                .map_err(|_| invalid_data("split archive is too large without zip64"))?;
+            eocd[16..20].copy_from_slice(&offset.to_le_bytes());
+        }
+        end.extend_from_slice(&eocd);
+
+        let mut segments = Vec::new();
+        let mut left = cd_start;
+        for (index, len) in lens.iter().enumerate() {
+            let offset = if index == 0 { skip } else { 0 };
+            let len = (len - offset).min(left);
+            if len > 0 {
+                segments.push(Segment::Volume { index, offset, len });
+            }
+            left -= len;
+        }
+        segments.push(Segment::Bytes(end));
+        Ok(Some(Self::new(volumes.paths, segments)))
+    }
+
+    /// Fills `buf` from volume `disk` at `offset`, continuing into the following volumes.
+    fn read_span(
+        &mut self,
+        lens: &[u64],
+        mut disk: usize,
+        mut offset: u64,
+        mut buf: &mut [u8],
+    ) -> io::Result<()> {
+        while !buf.is_empty() {
+            let len = lens
+                .get(disk)
+                .ok_or_else(|| invalid_data("truncated split set"))?;
+            let available = len.saturating_sub(offset).min(buf.len() as u64) as usize;
+            let file = self.volume(disk)?;
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut buf[..available])?;
+            buf = &mut buf[available..];
+            disk += 1;
+            offset = 0;
+        }
+        Ok(())
+    }
+
+    fn volume(&mut self, index: usize) -> io::Result<&mut File> {
+        if self.open.as_ref().is_none_or(|(open, _)| *open != index) {
+            self.open = Some((index, File::open(&self.paths[index])?));
+        }
+        Ok(&mut self.open.as_mut().expect("volume just opened").1)
+    }
+}
+
+impl Read for ZipVolumes {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let index = self
+            .segments
+            .partition_point(|(start, _)| *start <= self.pos)
+            - 1;
+        let within = self.pos - self.segments[index].0;
+        let read = match &self.segments[index].1 {
+            Segment::Bytes(bytes) => {
+                let len = buf.len().min(bytes.len() - within as usize);
+                buf[..len].copy_from_slice(&bytes[within as usize..within as usize + len]);
+                len
+            }
+            &Segment::Volume { index, offset, len } => {
+                let len = (len - within).min(buf.len() as u64) as usize;
+                let file = self.volume(index)?;
+                file.seek(SeekFrom::Start(offset + within))?;
+                file.read(&mut buf[..len])?
+            }
+        };
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for ZipVolumes {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = target.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek before start of archive")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_split_space, volume_path, MIN_VOLUME_SIZE};
+    use crate::fs_ops_archive::{open_zip, write_zip};
+    use crate::fs_ops_preflight::preflight_zip_create;
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{ZipCreateOptions, ZipMethod};
+    use std::fs;
+    use std::io::Read;
+
+    #[test]
+    fn split_zip_writes_volumes_that_read_back() {
+        let root = unique_temp_dir("reflexfiles-zip-split");
+        let src = root.join("src");
+        fs::create_dir_all(src.join("sub")).expect("create src");
+        let mut seed = 0x2545_f491_u32;
+        let big: Vec<u8> = (0..150_000)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                seed as u8
+            })
+            .collect();
+        fs::write(src.join("big.bin"), &big).expect("write big");
+        fs::write(src.join("sub").join("small.txt"), b"small").expect("write small");
+
+        let archive = root.join("out.zip");
+        let options = ZipCreateOptions {
+            method: ZipMethod::Store,
+            volume_size: Some(MIN_VOLUME_SIZE),
+            ..ZipCreateOptions::default()
+        };
+        write_zip(&archive, &[src], &None, &options, &mut |_| {}).expect("write zip");
+        assert_eq!(volume_path(&archive, 0), root.join("out.z01"));
+        for volume in ["out.z01", "out.z02", "out.zip"] {
+            let len = fs::metadata(root.join(volume)).expect("volume").len();
+            assert!(len <= MIN_VOLUME_SIZE, "{volume} holds {len} bytes");
+        }
+        assert!(!root.join("out.z03").exists());
+
+        let mut zip = open_zip(&archive).expect("open split zip");
+        let mut read = |name: &str| {
+            let mut data = Vec::new();
+            let mut file = zip.by_name(name).expect("entry");
+            file.read_to_end(&mut data).expect("read entry");
+            data
+        };
+        assert_eq!(read("src/big.bin"), big);
+        assert_eq!(read("src/sub/small.txt"), b"small");
+
+        fs::remove_file(root.join("out.z02")).expect("remove volume");
+        let err = open_zip(&archive).err().expect("missing volume");
+        assert!(err.contains("out.z02"), "{err}");
+
+        let tiny = ZipCreateOptions {
+            volume_size: Some(1024),
+            ..ZipCreateOptions::default()
+        };
+        let err = write_zip(&root.join("tiny.zip"), &[], &None, &tiny, &mut |_| {})
+            .expect_err("too small");
+        assert!(err.starts_with("code=invalid_volume_size"), "{err}");
+
+        // A leftover volume is caught before anything is written.
+        fs::write(root.join("next.Z03"), b"stale").expect("write stale volume");
+        let item = root.join("src").to_string_lossy().to_string();
+        let next = root.join("next.zip").to_string_lossy().to_string();
+        let err = preflight_zip_create(std::slice::from_ref(&item), &next, Some(MIN_VOLUME_SIZE))
+            .expect_err("stale volume");
+        assert_eq!(err.code, "already_exists");
+        assert!(err.message.contains("next.Z03"), "{}", err.message);
+        preflight_zip_create(&[item], &next, None).expect("unsplit archive");
+        assert!(check_split_space(&archive, u64::MAX / 2).is_err());
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,646 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Archive, Builder, EntryType, Header, HeaderMode};
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+use crate::fs_ops_archive::{
+    ensure_inside, invalid_entry, io_failure, read_back, scan_totals, set_file_mode, stream_copy,
+    zip_safe_path, ArchiveTracker, ExtractFilter, ExtractPlan,
+};
+use crate::fs_ops_mutate_helpers::create_symlink;
+use crate::fs_ops_transfer_helpers::ensure_not_canceled;
+use crate::types::{
+    ArchiveFormat, ArchiveListEntry, ArchiveOp, ArchiveProgress, ArchiveSummary, ArchiveTestEntry,
+    ArchiveTestReport,
+};
+use crate::utils::system_time_to_rfc3339;
+
+const XZ_PRESET: u32 = 6;
+const ZSTD_LEVEL: i32 = 0;
+
+/// Feeds bytes read from a source file into the tracker and honors `op_cancel`.
+struct TrackedReader<'a, 'b, R> {
+    inner: R,
+    tracker: &'a mut ArchiveTracker<'b>,
+}
+
+impl<R: Read> Read for TrackedReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        ensure_not_canceled()?;
+        let read = self.inner.read(buf)?;
+        self.tracker.add_bytes(read as u64);
+        Ok(read)
+    }
+}
+
+fn header_for(meta: &fs::Metadata) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_metadata_in_mode(meta, HeaderMode::Complete);
+    header
+}
+
+/// Adds `src` as `name`. Symlinks are stored as links, never followed.
+fn append_path<W: Write>(
+    builder: &mut Builder<W>,
+    src: &Path,
+    name: &Path,
+    tracker: &mut ArchiveTracker,
+) -> io::Result<()> {
+    let meta = fs::symlink_metadata(src)?;
+    tracker.start_entry(&name.to_string_lossy().replace('\\', "/"));
+    if meta.file_type().is_symlink() {
+        let mut header = header_for(&meta);
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, name, fs::read_link(src)?)?;
+    } else if meta.is_dir() {
+        let mut header = header_for(&meta);
+        header.set_size(0);
+        builder.append_data(&mut header, name, io::empty())?;
+        tracker.finish_entry();
+        for entry in fs::read_dir(src)? {
+            let entry = entry?;
+            append_path(
+                builder,
+                &entry.path(),
+                &name.join(entry.file_name()),
+                tracker,
+            )?;
+        }
+        return Ok(());
+    } else {
+        let mut header = header_for(&meta);
+        let reader = TrackedReader {
+            inner: File::open(src)?,
+            tracker: &mut *tracker,
+        };
+        builder.append_data(&mut header, name, reader)?;
+    }
+    tracker.finish_entry();
+    Ok(())
+}
+
+fn build_tar<W: Write>(
+    writer: W,
+    sources: &[PathBuf],
+    tracker: &mut ArchiveTracker,
+) -> io::Result<W> {
+    let mut builder = Builder::new(writer);
+    builder.follow_symlinks(false);
+    for src in sources {
+        let name = src
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))?;
+        append_path(&mut builder, src, Path::new(name), tracker)?;
+    }
+    builder.into_inner()
+}
+
+/// Writes `sources` into a new tarball at `dest`, compressed as `format` says. The partial
+/// archive is removed when writing fails or is canceled.
+pub(crate) fn write_tar(
+    dest: &Path,
+    sources: &[PathBuf],
+    format: ArchiveFormat,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    let (mut entries_total, mut bytes_total) = (0u64, 0u64);
+    for src in sources {
+        scan_totals(src, &mut entries_total, &mut bytes_total);
+    }
+    let mut tracker =
+        ArchiveTracker::new(ArchiveOp::Create, entries_total, bytes_total, on_progress);
+    let file = File::create(dest).map_err(io_failure)?;
+    let result = match format {
+        ArchiveFormat::TarGz => build_tar(
+            GzEncoder::new(file, Compression::default()),
+            sources,
+            &mut tracker,
+        )
+        .and_then(|encoder| encoder.finish())
+        .map(|_| ()),
+        ArchiveFormat::TarXz => build_tar(XzEncoder::new(file, XZ_PRESET), sources, &mut tracker)
+            .and_then(|encoder| encoder.finish())
+            .map(|_| ()),
+        ArchiveFormat::TarZst => zstd::Encoder::new(file, ZSTD_LEVEL)
+            .and_then(|encoder| build_tar(encoder, sources, &mut tracker))
+            .and_then(|encoder| encoder.finish())
+            .map(|_| ()),
+        _ => build_tar(file, sources, &mut tracker).and_then(|file| file.sync_all()),
+    };
+    if let Err(err) = result {
+        let _ = fs::remove_file(dest);
+        return Err(io_failure(err));
+    }
+    Ok(tracker.finish())
+}
+
+fn open_tar(path: &Path, format: ArchiveFormat) -> io::Result<Archive<Box<dyn Read>>> {
+    let file = BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read> = match format {
+        ArchiveFormat::TarGz => Box::new(MultiGzDecoder::new(file)),
+        ArchiveFormat::TarXz => Box::new(XzDecoder::new_multi_decoder(file)),
+        ArchiveFormat::TarZst => Box::new(zstd::Decoder::with_buffer(file)?),
+        _ => Box::new(file),
+    };
+    Ok(Archive::new(reader))
+}
+
+/// Link targets must stay inside `root` once resolved from the link's real folder. The
+/// kernel resolves `..` after following a link, not on the text, so `..` behind a
+/// component that is already a link is refused.
+fn check_link_target(root: &Path, parent: &Path, target: &Path) -> Result<(), String> {
+    let mut resolved = parent.canonicalize().map_err(io_failure)?;
+    let mut through_link = false;
+    for comp in target.components() {
+        match comp {
+            Component::Normal(part) => {
+                resolved.push(part);
+                through_link = through_link
+                    || fs::symlink_metadata(&resolved)
+                        .is_ok_and(|meta| meta.file_type().is_symlink());
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if through_link || !resolved.pop() {
+                    return Err(invalid_entry());
+                }
+            }
+            _ => return Err(invalid_entry()),
+        }
+    }
+    if resolved.starts_with(root) {
+        Ok(())
+    } else {
+        Err(invalid_entry())
+    }
+}
+
+fn extract_entry<R: Read>(
+    entry: &mut tar::Entry<R>,
+    root: &Path,
+    outpath: &Path,
+    filter: &ExtractFilter,
+    tracker: &mut ArchiveTracker,
+    dir_modes: &mut Vec<(PathBuf, u32)>,
+) -> Result<(), String> {
+    let kind = entry.header().entry_type();
+    // setuid/setgid/sticky bits are dropped.
+    let mode = entry.header().mode().ok().map(|mode| mode & 0o777);
+    if kind.is_dir() {
+        fs::create_dir_all(outpath).map_err(io_failure)?;
+        ensure_inside(root, outpath)?;
+        if let Some(mode) = mode {
+            // Applied once everything is written, so read-only folders can still be filled.
+            dir_modes.push((outpath.to_path_buf(), mode));
+        }
+        return Ok(());
+    }
+    let parent = outpath.parent().ok_or_else(invalid_entry)?;
+    fs::create_dir_all(parent).map_err(io_failure)?;
+    ensure_inside(root, parent)?;
+    // Never write through a link that already sits at the target, and make room for new
+    // links, which cannot replace an existing file.
+    if let Ok(meta) = fs::symlink_metadata(outpath) {
+        let is_link = kind.is_symlink() || kind.is_hard_link();
+        if meta.file_type().is_symlink() || (!meta.is_dir() && is_link) {
+            fs::remove_file(outpath).map_err(io_failure)?;
+        }
+    }
+    if kind.is_symlink() {
+        let target = entry
+            .link_name()
+            .map_err(io_failure)?
+            .ok_or_else(invalid_entry)?
+            .into_owned();
+        check_link_target(root, parent, &target)?;
+        return create_symlink(&target, outpath, parent.join(&target).is_dir()).map_err(io_failure);
+    }
+    if kind.is_hard_link() {
+        let target = entry
+            .link_name()
+            .map_err(io_failure)?
+            .ok_or_else(invalid_entry)?;
+        // Hard links name another entry, which was stored under the same prefix.
+        let target = filter
+            .strip_name(&target.to_string_lossy())
+            .ok_or_else(invalid_entry)?;
+        let source = zip_safe_path(root, &target)?;
+        ensure_inside(root, &source)?;
+        return fs::hard_link(&source, outpath).map_err(io_failure);
+    }
+    if !kind.is_file() && !kind.is_contiguous() {
+        // Devices, fifos and other special entries are skipped.
+        return Ok(());
+    }
+    let mut out = File::create(outpath).map_err(io_failure)?;
+    stream_copy(entry, &mut out, tracker).map_err(io_failure)?;
+    if let Ok(mtime) = entry.header().mtime() {
+        let _ = out.set_modified(UNIX_EPOCH + Duration::from_secs(mtime));
+    }
+    drop(out);
+    if let Some(mode) = mode {
+        set_file_mode(outpath, mode).map_err(io_failure)?;
+    }
+    Ok(())
+}
+
+/// Extracts the entries of the tarball at `path` that `filter` selects as laid out by
+/// `plan`. Unix modes are kept without special bits, and links may only point inside the
+/// destination.
+pub(crate) fn extract_tar(
+    path: &Path,
+    plan: &ExtractPlan,
+    format: ArchiveFormat,
+    filter: &ExtractFilter,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    let root = plan.root();
+    // Tar headers only arrive with the stream, so a first pass reads just the headers and
+    // admits every selected entry before anything is written.
+    let mut guard = plan.limit_guard();
+    let mut selected = 0;
+    let mut bytes_total = 0;
+    for entry in list_tar(path, format)? {
+        if let Some(target) = filter.target(&entry.name) {
+            guard.admit(&target, entry.size, None)?;
+            selected += 1;
+            bytes_total += entry.size;
+        }
+    }
+    let mut archive = open_tar(path, format).map_err(io_failure)?;
+    let mut tracker = ArchiveTracker::new(ArchiveOp::Extract, selected, bytes_total, on_progress);
+    let mut dir_modes = Vec::new();
+    for entry in archive.entries().map_err(io_failure)? {
+        let mut entry = entry.map_err(io_failure)?;
+        let name = entry
+            .path()
+            .map_err(io_failure)?
+            .to_string_lossy()
+            .to_string();
+        let Some(target) = filter.target(&name) else {
+            continue;
+        };
+        tracker.start_entry(&name);
+        let outpath = if entry.header().entry_type().is_dir() {
+            zip_safe_path(root, &target)?
+        } else {
+            let modified = entry
+                .header()
+                .mtime()
+                .ok()
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+            match plan.file_target(&target, modified)? {
+                Some(outpath) => outpath,
+                None => {
+                    tracker.skip_entry(&name, entry.size());
+                    continue;
+                }
+            }
+        };
+        extract_entry(
+            &mut entry,
+            root,
+            &outpath,
+            filter,
+            &mut tracker,
+            &mut dir_modes,
+        )?;
+        tracker.finish_entry();
+    }
+    for (dir, mode) in dir_modes.iter().rev() {
+        let _ = set_file_mode(dir, *mode);
+    }
+    Ok(tracker.finish())
+}
+
+/// Names of selected non-folder entries that already exist under `dest`.
+pub(crate) fn tar_conflicts(
+    path: &Path,
+    dest: &Path,
+    format: ArchiveFormat,
+    filter: &ExtractFilter,
+) -> Result<Vec<String>, String> {
+    let mut archive = open_tar(path, format).map_err(io_failure)?;
+    let mut conflicts = Vec::new();
+    for entry in archive.entries().map_err(io_failure)? {
+        let entry = entry.map_err(io_failure)?;
+        if entry.header().entry_type().is_dir() {
+            continue;
+        }
+        let name = entry
+            .path()
+            .map_err(io_failure)?
+            .to_string_lossy()
+            .to_string();
+        let Some(target) = filter.target(&name) else {
+            continue;
+        };
+        let outpath = zip_safe_path(dest, &target)?;
+        if fs::symlink_metadata(&outpath).is_ok() {
+            conflicts.push(name);
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Header-level listing of a tarball. The compression covers the whole stream, so entries
+/// carry the stream's method and no compressed size.
+pub(crate) fn list_tar(
+    path: &Path,
+    format: ArchiveFormat,
+) -> Result<Vec<ArchiveListEntry>, String> {
+    let method = match format {
+        ArchiveFormat::TarGz => "gzip",
+        ArchiveFormat::TarXz => "xz",
+        ArchiveFormat::TarZst => "zstd",
+        _ => "store",
+    };
+    let mut archive = open_tar(path, format).map_err(io_failure)?;
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(io_failure)? {
+        let entry = entry.map_err(io_failure)?;
+        let header = entry.header();
+        let modified = header
+            .mtime()
+            .ok()
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        entries.push(ArchiveListEntry {
+            name: entry
+                .path()
+                .map_err(io_failure)?
+                .to_string_lossy()
+                .to_string(),
+            is_dir: header.entry_type().is_dir(),
+            size: header.size().map_err(io_failure)?,
+            compressed_size: None,
+            method: method.to_string(),
+            modified: system_time_to_rfc3339(modified),
+            crc: None,
+            encrypted: false,
+        });
+    }
+    Ok(entries)
+}
+
+/// Reads the tarball at `path` to the end, which checks the header checksums and the
+/// compression stream. Nothing after damage can be trusted, so the first failure ends the
+/// test.
+pub(crate) fn verify_tar(
+    path: &Path,
+    format: ArchiveFormat,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveTestReport, String> {
+    let mut archive = open_tar(path, format).map_err(io_failure)?;
+    let mut tracker = ArchiveTracker::new(ArchiveOp::Test, 0, 0, on_progress);
+    let mut entries = Vec::new();
+    for entry in archive.entries().map_err(io_failure)? {
+        let (name, result) = match entry {
+            Ok(mut entry) => {
+                let name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+                tracker.start_entry(&name);
+                let result = read_back(&mut entry, &mut tracker)?;
+                tracker.finish_entry();
+                (name, result)
+            }
+            Err(err) => (String::new(), Err(io_failure(err))),
+        };
+        let failed = result.is_err();
+        entries.push(ArchiveTestEntry::new(name, result));
+        if failed {
+            return Ok(tracker.finish_test(entries));
+        }
+    }
+    // The end-of-archive blocks come before the compressed stream's own checksum.
+    if let Err(err) = io::copy(&mut archive.into_inner(), &mut io::sink()) {
+        entries.push(ArchiveTestEntry::new("", Err(io_failure(err))));
+    }
+    Ok(tracker.finish_test(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_tar, tar_conflicts, write_tar};
+    use crate::fs_ops_archive::{ExtractFilter, ExtractPlan};
+    use crate::fs_ops_archive_limits::ExtractLimits;
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{ArchiveFormat, ConflictPolicy};
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+
+    #[test]
+    fn tar_formats_round_trip() {
+        let root = unique_temp_dir("rf-tar");
+        let src = root.join("pkg");
+        fs::create_dir_all(src.join("bin")).expect("create src");
+        fs::write(src.join("bin").join("run.sh"), b"#!/bin/sh\necho hi\n").expect("write");
+        fs::write(src.join("README"), b"readme").expect("write readme");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = src.join("bin").join("run.sh");
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+            std::os::unix::fs::symlink("bin/run.sh", src.join("run")).expect("symlink");
+        }
+
+        for (format, ext) in [
+            (ArchiveFormat::Tar, "tar"),
+            (ArchiveFormat::TarGz, "tar.gz"),
+            (ArchiveFormat::TarXz, "tar.xz"),
+            (ArchiveFormat::TarZst, "tar.zst"),
+        ] {
+            let archive = root.join(format!("pkg.{ext}"));
+            let out = root.join(format!("out-{ext}"));
+            let created = write_tar(&archive, std::slice::from_ref(&src), format, &mut |_| {})
+                .expect("write tar");
+            assert!(created.count >= 4);
+            let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+            let extracted = extract_tar(
+                &archive,
+                &plan,
+                format,
+                &ExtractFilter::default(),
+                &mut |_| {},
+            )
+            .expect("extract");
+            assert_eq!(extracted.bytes, created.bytes);
+            // Extracting again over the same folder overwrites everything, links included.
+            extract_tar(
+                &archive,
+                &plan,
+                format,
+                &ExtractFilter::default(),
+                &mut |_| {},
+            )
+            .expect("extract again");
+            assert_eq!(
+                fs::read(out.join("pkg").join("README")).expect("read"),
+                b"readme"
+            );
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let script = out.join("pkg").join("bin").join("run.sh");
+                let mode = fs::metadata(&script).expect("meta").permissions().mode();
+                assert_eq!(mode & 0o777, 0o755);
+                let link = fs::read_link(out.join("pkg").join("run")).expect("link");
+                assert_eq!(link, PathBuf::from("bin/run.sh"));
+            }
+            let conflicts = tar_conflicts(&archive, &out, format, &ExtractFilter::default())
+                .expect("conflicts");
+            assert!(conflicts.contains(&"pkg/README".to_string()));
+        }
+
+        // `write_tar` stores no hard links, so this archive is built by hand.
+        let archive = root.join("linked.tar");
+        let mut builder = tar::Builder::new(fs::File::create(&archive).expect("create"));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "pkg/data", &b"data"[..])
+            .expect("append file");
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "pkg/alias", "pkg/data")
+            .expect("append hard link");
+        builder.finish().expect("finish");
+        drop(builder);
+        let out = root.join("out-linked");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        for _ in 0..2 {
+            extract_tar(
+                &archive,
+                &plan,
+                ArchiveFormat::Tar,
+                &ExtractFilter::default(),
+                &mut |_| {},
+            )
+            .expect("extract hard link");
+            assert_eq!(
+                fs::read(out.join("pkg").join("alias")).expect("read"),
+                b"data"
+            );
+        }
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_tar_rejects_links_escaping_destination() {
+        let root = unique_temp_dir("rf-tar-escape");
+        let archive = root.join("evil.tar");
+        let mut builder = tar::Builder::new(fs::File::create(&archive).expect("create"));
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "up", "../..")
+            .expect("append link");
+        builder.finish().expect("finish");
+        drop(builder);
+
+        let plan =
+            ExtractPlan::new(&root.join("out"), ConflictPolicy::Overwrite, false).expect("plan");
+        let err = extract_tar(
+            &archive,
+            &plan,
+            ArchiveFormat::Tar,
+            &ExtractFilter::default(),
+            &mut |_| {},
+        )
+        .expect_err("escaping link");
+        assert!(err.starts_with("code=invalid_path;"));
+        assert!(fs::symlink_metadata(root.join("out").join("up")).is_err());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_tar_rejects_parent_dirs_behind_links() {
+        let root = unique_temp_dir("rf-tar-link-chain");
+        let archive = root.join("evil.tar");
+        let mut builder = tar::Builder::new(fs::File::create(&archive).expect("create"));
+        for (name, target) in [("d", "."), ("e", "d/..")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_mode(0o777);
+            builder
+                .append_link(&mut header, name, target)
+                .expect("append link");
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "e/x/", io::empty())
+            .expect("append dir");
+        builder.finish().expect("finish");
+        drop(builder);
+
+        let out = root.join("out");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false).expect("plan");
+        let err = extract_tar(
+            &archive,
+            &plan,
+            ArchiveFormat::Tar,
+            &ExtractFilter::default(),
+            &mut |_| {},
+        )
+        .expect_err("link through link");
+        assert!(err.starts_with("code=invalid_path;"), "{err}");
+        assert!(fs::symlink_metadata(out.join("e")).is_err());
+        assert!(!root.join("x").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn extract_tar_checks_limits_before_writing() {
+        let root = unique_temp_dir("rf-tar-limits");
+        let src = root.join("pkg");
+        fs::create_dir_all(&src).expect("create src");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(src.join(name), name).expect("write");
+        }
+        let archive = root.join("pkg.tar");
+        write_tar(
+            &archive,
+            std::slice::from_ref(&src),
+            ArchiveFormat::Tar,
+            &mut |_| {},
+        )
+        .expect("write tar");
+
+        let out = root.join("out");
+        let plan = ExtractPlan::new(&out, ConflictPolicy::Overwrite, false)
+            .expect("plan")
+            .with_limits(ExtractLimits {
+                max_entries: 3,
+                ..ExtractLimits::default()
+            });
+        let err = extract_tar(
+            &archive,
+            &plan,
+            ArchiveFormat::Tar,
+            &ExtractFilter::default(),
+            &mut |_| {},
+        )
+        .expect_err("too many entries");
+        assert!(err.starts_with("code=archive_limit"));
+        assert_eq!(fs::read_dir(&out).expect("list out").count(), 0);
+        let _ = fs::remove_dir_all(root);
+    }
+}
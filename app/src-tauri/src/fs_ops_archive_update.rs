@@ -0,0 +1,344 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tauri::{AppHandle, Emitter};
+use zip::write::FullFileOptions;
+use zip::{AesMode, HasZipMetadata, ZipArchive, ZipWriter};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_archive::{
+    bad_password, collect_zip_sources, io_failure, open_zip, zip_add_source, zip_file_options,
+    zip_open_error, ArchiveTracker, ZipSource,
+};
+use crate::fs_ops_archive_names::{write_cp932_names, zip_entry_names};
+use crate::fs_ops_archive_split::ZipVolumes;
+use crate::fs_ops_preflight::preflight_zip_update;
+use crate::fs_ops_transfer_helpers::{cancel_requested, reset_cancel_request};
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{
+    ArchiveOp, ArchiveProgress, ArchiveSummary, ZipCreateOptions, ZipNameEncoding,
+    ZipUpdateOptions, EVENT_ARCHIVE_PROGRESS,
+};
+
+/// Whether `spec` from `ZipUpdateOptions::remove` names the entry `name` or a folder above it.
+fn removes(spec: &str, name: &str) -> bool {
+    let spec = spec.trim().trim_matches('/');
+    !spec.is_empty()
+        && name
+            .strip_prefix(spec)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn zip_write_error(err: zip::result::ZipError) -> String {
+    format_error(AppErrorKind::Io, err.to_string())
+}
+
+fn write_update(
+    archive: &mut ZipArchive<ZipVolumes>,
+    temp: &Path,
+    kept: &[(usize, String)],
+    added: &[ZipSource],
+    file_options: &FullFileOptions<'_>,
+    tracker: &mut ArchiveTracker,
+) -> Result<(), String> {
+    let file = fs::File::create(temp).map_err(io_failure)?;
+    let mut writer = ZipWriter::new(file);
+    for (index, name) in kept {
+        if cancel_requested() {
+            return Err("code=canceled; canceled".to_string());
+        }
+        let file = archive.by_index_raw(*index).map_err(zip_open_error)?;
+        let packed = file.compressed_size();
+        tracker.start_entry(name);
+        // Kept entries are renamed to their decoded names, which the writer stores as UTF-8.
+        writer
+            .raw_copy_file_rename(file, name)
+            .map_err(zip_write_error)?;
+        tracker.add_bytes(packed);
+        tracker.finish_entry();
+    }
+    for source in added {
+        zip_add_source(&mut writer, source, file_options, tracker)?;
+    }
+    writer.finish().map_err(zip_write_error)?;
+    Ok(())
+}
+
+/// Rewrites the zip at `path` without the entries `remove` names and with `sources` added
+/// under `folder`, replacing entries of the same name. Names are matched as
+/// `archive_list` shows them by default. Kept entries are copied without being decompressed,
+/// so AES entries stay encrypted. The new archive is written next to
+/// the original and only replaces it once complete.
+pub(crate) fn update_zip(
+    path: &Path,
+    sources: &[PathBuf],
+    folder: &str,
+    remove: &[String],
+    password: &Option<String>,
+    options: &ZipCreateOptions,
+    on_progress: &mut dyn FnMut(&ArchiveProgress),
+) -> Result<ArchiveSummary, String> {
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(format_error(
+            AppErrorKind::InvalidPath,
+            "invalid archive path",
+        ));
+    };
+    if ZipVolumes::open(path).is_ok_and(|volumes| volumes.is_split()) {
+        return Err("code=unsupported_format; split archives cannot be updated".to_string());
+    }
+    let mut archive = open_zip(path)?;
+    let names = zip_entry_names(&mut archive, ZipNameEncoding::Auto)?;
+    let folder = folder.trim().trim_matches('/');
+    let mut added = Vec::new();
+    for src in sources {
+        let name = src.file_name().ok_or_else(|| "invalid path".to_string())?;
+        let name = match folder {
+            "" => name.to_string_lossy().to_string(),
+            folder => format!("{folder}/{}", name.to_string_lossy()),
+        };
+        collect_zip_sources(src, name, options, &mut Vec::new(), &mut added).map_err(io_failure)?;
+    }
+    let added_names: HashSet<String> = added.iter().map(ZipSource::entry_name).collect();
+
+    let mut kept = Vec::new();
+    let mut matched = vec![false; remove.len()];
+    let mut replaced_encrypted = false;
+    let mut aes_entry = None;
+    let mut bytes_total = added.iter().map(ZipSource::bytes).sum::<u64>();
+    for (index, name) in names.into_iter().enumerate() {
+        let file = archive.by_index_raw(index).map_err(zip_open_error)?;
+        let aes = file.get_metadata().aes_mode.is_some();
+        if aes {
+            aes_entry.get_or_insert(index);
+        }
+        let mut dropped = false;
+        for (spec, hit) in remove.iter().zip(matched.iter_mut()) {
+            if removes(spec, &name) {
+                *hit = true;
+                dropped = true;
+            }
+        }
+        if added_names.contains(&name) {
+            replaced_encrypted |= file.encrypted();
+            dropped = true;
+        }
+        if dropped {
+            continue;
+        }
+        if file.encrypted() && !aes {
+            // The writer cannot reproduce ZipCrypto headers for a raw copy.
+            return Err(format!(
+                "code=unsupported_encryption; {name} uses ZipCrypto encryption and cannot be kept"
+            ));
+        }
+        bytes_total += file.compressed_size();
+        kept.push((index, name));
+    }
+    if let Some((spec, _)) = remove.iter().zip(&matched).find(|(_, hit)| !**hit) {
+        return Err(format_error(
+            AppErrorKind::NotFound,
+            format!("entry not found in archive: {spec}"),
+        ));
+    }
+
+    let mut file_options = zip_file_options(options)?;
+    match (password, aes_entry) {
+        (None, _) if replaced_encrypted => return Err(bad_password()),
+        (Some(pass), Some(index)) => {
+            // Opening an AES entry checks the password before anything is written.
+            archive
+                .by_index_decrypt(index, pass.as_bytes())
+                .map_err(zip_open_error)?;
+        }
+        _ => {}
+    }
+    if let Some(pass) = password {
+        file_options = file_options.with_aes_encryption(AesMode::Aes256, pass);
+    }
+
+    let temp = parent.join(format!(".{}.rf-update", file_name.to_string_lossy()));
+    let mut tracker = ArchiveTracker::new(
+        ArchiveOp::Update,
+        (kept.len() + added.len()) as u64,
+        bytes_total,
+        on_progress,
+    );
+    let result = write_update(
+        &mut archive,
+        &temp,
+        &kept,
+        &added,
+        &file_options,
+        &mut tracker,
+    );
+    // Close the original first so it can be replaced on Windows.
+    drop(archive);
+    let result = result.and_then(|()| {
+        if options.cp932_names {
+            write_cp932_names(&temp)?;
+        }
+        fs::rename(&temp, path).map_err(io_failure)
+    });
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    Ok(tracker.finish())
+}
+
+#[tauri::command]
+pub fn zip_update(
+    app: AppHandle,
+    path: String,
+    items: Vec<String>,
+    password: Option<String>,
+    options: Option<ZipUpdateOptions>,
+) -> Result<ArchiveSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let options = options.unwrap_or_default();
+    let resolved_path = resolve_legacy_path_for(&path, ProviderCapability::ArchiveCreate)
+        .map_err(|err| format!("code={}; {}", err.code(), err))
+        .inspect_err(|err| crate::log_error("zip_update", &path, "-", err))?;
+    let mut sources: Vec<PathBuf> = Vec::with_capacity(items.len());
+    for item in &items {
+        let resolved = resolve_legacy_path_for(item, ProviderCapability::Read)
+            .map_err(|err| format!("code={}; {}", err.code(), err))
+            .inspect_err(|err| crate::log_error("zip_update", item, &path, err))?;
+        sources.push(resolved);
+    }
+    let preflight_items: Vec<String> = sources
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    preflight_zip_update(
+        &resolved_path.to_string_lossy(),
+        &preflight_items,
+        options.remove.len(),
+    )
+    .map_err(|err| format!("code={}; {}", err.code, err.message))
+    .inspect_err(|err| crate::log_error("zip_update", &path, "-", err))?;
+    let create = options
+        .create
+        .unwrap_or_else(|| ZipCreateOptions::from_config(&crate::config::load_config_fast()));
+    let mut on_progress = |progress: &ArchiveProgress| {
+        let _ = app.emit(EVENT_ARCHIVE_PROGRESS, progress.clone());
+    };
+    let summary = update_zip(
+        &resolved_path,
+        &sources,
+        &options.folder,
+        &options.remove,
+        &password,
+        &create,
+        &mut on_progress,
+    )
+    .inspect_err(|err| crate::log_error("zip_update", &path, "-", err))?;
+    crate::log_event(
+        "ZIP_UPDATE",
+        &path,
+        "-",
+        &format!(
+            "items={}; removed={}; count={}; ms={}",
+            sources.len(),
+            options.remove.len(),
+            summary.count,
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::update_zip;
+    use crate::fs_ops_archive::write_zip;
+    use crate::test_support::unique_temp_dir;
+    use crate::types::ZipCreateOptions;
+    use std::fs;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn read_entry(archive: &mut ZipArchive<fs::File>, name: &str, password: &str) -> String {
+        let mut file = archive
+            .by_name_decrypt(name, password.as_bytes())
+            .expect("entry");
+        let mut content = String::new();
+        file.read_to_string(&mut content).expect("read entry");
+        content
+    }
+
+    #[test]
+    fn update_zip_adds_replaces_and_removes_entries() {
+        let root = unique_temp_dir("rf-zip-update");
+        let src = root.join("docs");
+        fs::create_dir_all(src.join("old")).expect("create src");
+        fs::write(src.join("a.txt"), b"alpha").expect("write a");
+        fs::write(src.join("b.txt"), b"beta").expect("write b");
+        fs::write(src.join("old").join("c.txt"), b"gamma").expect("write c");
+        let archive = root.join("docs.zip");
+        let password = Some("secret".to_string());
+        let options = ZipCreateOptions::default();
+        write_zip(&archive, &[src], &password, &options, &mut |_| {}).expect("write zip");
+
+        let incoming = root.join("incoming");
+        fs::create_dir_all(&incoming).expect("create incoming");
+        fs::write(incoming.join("a.txt"), b"ALPHA").expect("write new a");
+        fs::write(incoming.join("new.txt"), b"new").expect("write new");
+        let sources = [incoming.join("a.txt"), incoming.join("new.txt")];
+        let remove = ["docs/old".to_string()];
+
+        let err = update_zip(
+            &archive,
+            &sources,
+            "docs",
+            &remove,
+            &None,
+            &options,
+            &mut |_| {},
+        )
+        .expect_err("replacing an encrypted entry needs the password");
+        assert!(err.contains("ZIP_BAD_PASSWORD"));
+        let wrong = Some("wrong".to_string());
+        assert!(update_zip(
+            &archive,
+            &sources,
+            "docs",
+            &remove,
+            &wrong,
+            &options,
+            &mut |_| {}
+        )
+        .is_err());
+
+        update_zip(
+            &archive,
+            &sources,
+            "docs",
+            &remove,
+            &password,
+            &options,
+            &mut |_| {},
+        )
+        .expect("update");
+        let mut zip = ZipArchive::new(fs::File::open(&archive).expect("open")).expect("zip");
+        let mut names: Vec<_> = zip.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, ["docs/", "docs/a.txt", "docs/b.txt", "docs/new.txt"]);
+        assert!(
+            zip.by_name("docs/b.txt").is_err(),
+            "kept entry stays encrypted"
+        );
+        assert_eq!(read_entry(&mut zip, "docs/a.txt", "secret"), "ALPHA");
+        assert_eq!(read_entry(&mut zip, "docs/b.txt", "secret"), "beta");
+        assert!(!root.join(".docs.zip.rf-update").exists());
+
+        let err = update_zip(&archive, &[], "", &remove, &password, &options, &mut |_| {})
+            .expect_err("missing entry");
+        assert!(err.starts_with("code=not_found;"));
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,364 @@
+use std::fs;
+#[cfg(not(unix))]
+use std::fs::{FileTimes, OpenOptions};
+use std::io;
+use std::path::Path;
+#[cfg(unix)]
+use std::time::UNIX_EPOCH;
+use std::time::{Instant, SystemTime};
+
+use crate::fs_ops_mutate_helpers::{io_error_code, record_failure};
+use crate::fs_ops_preflight::preflight_set_attributes;
+use crate::storage_provider::{resolve_legacy_paths_for, ProviderCapability};
+use crate::types::{AttributeChanges, OpFailure, OpSummary};
+use crate::utils::{parse_mode, parse_time};
+
+struct ParsedChanges {
+    readonly: Option<bool>,
+    file_mode: Option<u32>,
+    dir_mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    mtime: Option<SystemTime>,
+    atime: Option<SystemTime>,
+}
+
+impl ParsedChanges {
+    /// Expects input that already passed `preflight_set_attributes`.
+    fn from_changes(changes: &AttributeChanges) -> Self {
+        ParsedChanges {
+            readonly: changes.readonly,
+            file_mode: changes.file_mode.as_deref().and_then(parse_mode),
+            dir_mode: changes.dir_mode.as_deref().and_then(parse_mode),
+            uid: changes.uid,
+            gid: changes.gid,
+            mtime: changes.mtime.as_deref().and_then(parse_time),
+            atime: changes.atime.as_deref().and_then(parse_time),
+        }
+    }
+}
+
+fn set_readonly(path: &Path, readonly: bool) -> io::Result<()> {
+    let mut perms = fs::metadata(path)?.permissions();
+    #[cfg(unix)]
+    {
+        // Only the owner's write bit, so clearing readonly never makes a file world-writable.
+        use std::os::unix::fs::PermissionsExt;
+        let mode = perms.mode();
+        perms.set_mode(if readonly {
+            mode & !0o222
+        } else {
+            mode | 0o200
+        });
+    }
+    #[cfg(not(unix))]
+    {
+        #[allow(clippy::permissions_set_readonly_false)]
+        perms.set_readonly(readonly);
+    }
+    fs::set_permissions(path, perms)
+}
+
+/// Sets times by path, so neither read nor write access to the item is needed.
+#[cfg(unix)]
+pub(crate) fn set_times(
+    path: &Path,
+    mtime: Option<SystemTime>,
+    atime: Option<SystemTime>,
+) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    fn timespec(time: Option<SystemTime>) -> libc::timespec {
+        let mut spec: libc::timespec = unsafe { std::mem::zeroed() };
+        let Some(time) = time else {
+            spec.tv_nsec = libc::UTIME_OMIT;
+            return spec;
+        };
+        let (secs, nanos) = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => (since.as_secs() as i64, since.subsec_nanos()),
+            Err(err) => {
+                let before = err.duration();
+                match before.subsec_nanos() {
+                    0 => (-(before.as_secs() as i64), 0),
+                    nanos => (-(before.as_secs() as i64) - 1, 1_000_000_000 - nanos),
+                }
+            }
+        };
+        spec.tv_sec = secs as libc::time_t;
+        spec.tv_nsec = nanos as libc::c_long;
+        spec
+    }
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))?;
+    let times = [timespec(atime), timespec(mtime)];
+    if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn set_times(
+    path: &Path,
+    mtime: Option<SystemTime>,
+    atime: Option<SystemTime>,
+) -> io::Result<()> {
+    let mut times = FileTimes::new();
+    if let Some(mtime) = mtime {
+        times = times.set_modified(mtime);
+    }
+    if let Some(atime) = atime {
+        times = times.set_accessed(atime);
+    }
+    let mut options = OpenOptions::new();
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+        const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+        // Backup semantics let folders be opened as handles too.
+        options
+            .access_mode(FILE_WRITE_ATTRIBUTES)
+            .custom_flags(FILE_FLAG_BACKUP_SEMANTICS);
+    }
+    options.open(path)?.set_times(times)
+}
+
+fn apply_to_path(path: &Path, is_dir: bool, changes: &ParsedChanges) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if is_dir {
+            changes.dir_mode
+        } else {
+            changes.file_mode
+        };
+        if let Some(mode) = mode {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+        }
+        if changes.uid.is_some() || changes.gid.is_some() {
+            std::os::unix::fs::chown(path, changes.uid, changes.gid)?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = is_dir;
+    // Clear readonly before touching times and set it last, so it never gets in the way.
+    if changes.readonly == Some(false) {
+        set_readonly(path, false)?;
+    }
+    if changes.mtime.is_some() || changes.atime.is_some() {
+        set_times(path, changes.mtime, changes.atime)?;
+    }
+    if changes.readonly == Some(true) {
+        set_readonly(path, true)?;
+    }
+    Ok(())
+}
+
+/// True when the folder mode being set still lets the owner list and enter the folder.
+fn keeps_folder_open(changes: &ParsedChanges) -> bool {
+    cfg!(unix) && changes.dir_mode.is_some_and(|mode| mode & 0o500 == 0o500)
+}
+
+/// Applies `changes` to `path` and, when `recursive`, to everything below it. A folder
+/// mode that keeps the folder open is applied before the walk, so it can open up a locked
+/// folder; any other change comes after the children, so it cannot lock the walk out.
+/// Links found while walking are skipped. Returns whether every path succeeded.
+fn apply_tree(
+    path: &Path,
+    changes: &ParsedChanges,
+    recursive: bool,
+    failures: &mut Vec<OpFailure>,
+) -> bool {
+    let is_dir = path.is_dir();
+    let mut all_ok = true;
+    let own_change_first = is_dir && keeps_folder_open(changes);
+    let apply_own = |failures: &mut Vec<OpFailure>| {
+        if let Err(err) = apply_to_path(path, is_dir, changes) {
+            let path_text = path.to_string_lossy();
+            crate::log_error("attributes", &path_text, "-", &err.to_string());
+            record_failure(failures, &path_text, io_error_code(&err), &err.to_string());
+            return false;
+        }
+        true
+    };
+    if own_change_first {
+        all_ok &= apply_own(failures);
+    }
+    if recursive && is_dir {
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let child = entry.path();
+                    let is_link = fs::symlink_metadata(&child)
+                        .map(|meta| meta.file_type().is_symlink())
+                        .unwrap_or(false);
+                    if !is_link {
+                        all_ok &= apply_tree(&child, changes, recursive, failures);
+                    }
+                }
+            }
+            Err(err) => {
+                let path_text = path.to_string_lossy();
+                record_failure(failures, &path_text, io_error_code(&err), &err.to_string());
+                all_ok = false;
+            }
+        }
+    }
+    if !own_change_first {
+        all_ok &= apply_own(failures);
+    }
+    all_ok
+}
+
+#[tauri::command]
+pub fn fs_set_attributes(
+    items: Vec<String>,
+    changes: AttributeChanges,
+) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    let resolved_items = match resolve_legacy_paths_for(&items, ProviderCapability::Rename) {
+        Ok(paths) => paths,
+        Err(err) => {
+            crate::log_error(
+                "attributes",
+                "batch",
+                "-",
+                &format!("code={}; {}", err.code(), err),
+            );
+            return Err(format!("code={}; {}", err.code(), err));
+        }
+    };
+    let preflight_items: Vec<String> = resolved_items
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    if let Err(err) = preflight_set_attributes(&preflight_items, &changes) {
+        crate::log_error(
+            "attributes",
+            "batch",
+            "-",
+            &format!("code={}; {}", err.code, err.message),
+        );
+        return Err(format!("code={}; {}", err.code, err.message));
+    }
+    let parsed = ParsedChanges::from_changes(&changes);
+    let total = resolved_items.len();
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    for (raw_item, path) in items.iter().zip(resolved_items) {
+        if apply_tree(&path, &parsed, changes.recursive, &mut failures) {
+            crate::log_event(
+                "ATTRIBUTES",
+                raw_item,
+                "-",
+                &format!(
+                    "recursive={}; count={}; ms={}",
+                    changes.recursive,
+                    total,
+                    started.elapsed().as_millis()
+                ),
+            );
+            ok += 1;
+        } else {
+            failed += 1;
+        }
+    }
+    Ok(OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_tree, ParsedChanges};
+    use crate::test_support::unique_temp_dir;
+    use crate::utils::parse_mode;
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn parse_mode_accepts_octal_only() {
+        assert_eq!(parse_mode("755"), Some(0o755));
+        assert_eq!(parse_mode("0o644"), Some(0o644));
+        assert_eq!(parse_mode("0644"), Some(0o644));
+        assert_eq!(parse_mode("778"), None);
+        assert_eq!(parse_mode(""), None);
+    }
+
+    #[test]
+    fn apply_tree_sets_times_and_readonly_recursively() {
+        let root = unique_temp_dir("rf-attributes");
+        let dir = root.join("dir");
+        fs::create_dir_all(dir.join("sub")).expect("create dirs");
+        fs::write(dir.join("sub").join("a.txt"), b"a").expect("write file");
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let changes = ParsedChanges {
+            readonly: Some(true),
+            file_mode: None,
+            dir_mode: None,
+            uid: None,
+            gid: None,
+            mtime: Some(mtime),
+            atime: None,
+        };
+
+        let mut failures = Vec::new();
+        let file = dir.join("sub").join("a.txt");
+        assert!(apply_tree(&file, &changes, true, &mut failures));
+        assert!(failures.is_empty());
+        let meta = fs::metadata(&file).expect("metadata");
+        assert!(meta.permissions().readonly());
+        assert_eq!(meta.modified().expect("modified"), mtime);
+
+        let writable = ParsedChanges {
+            readonly: Some(false),
+            mtime: None,
+            ..changes
+        };
+        assert!(apply_tree(&dir, &writable, true, &mut failures));
+        assert!(!fs::metadata(&file)
+            .expect("metadata")
+            .permissions()
+            .readonly());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_tree_opens_a_locked_folder_before_walking_it() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = unique_temp_dir("rf-attributes-locked");
+        let dir = root.join("locked");
+        fs::create_dir_all(&dir).expect("create dir");
+        let file = dir.join("a.txt");
+        fs::write(&file, b"a").expect("write file");
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o200)).expect("write-only file");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o000)).expect("lock dir");
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let changes = ParsedChanges {
+            readonly: None,
+            file_mode: Some(0o640),
+            dir_mode: Some(0o750),
+            uid: None,
+            gid: None,
+            mtime: Some(mtime),
+            atime: None,
+        };
+
+        let mut failures = Vec::new();
+        assert!(apply_tree(&dir, &changes, true, &mut failures));
+        assert!(failures.is_empty());
+        let meta = fs::metadata(&file).expect("metadata");
+        assert_eq!(meta.permissions().mode() & 0o777, 0o640);
+        assert_eq!(meta.modified().expect("modified"), mtime);
+        let dir_meta = fs::metadata(&dir).expect("dir metadata");
+        assert_eq!(dir_meta.permissions().mode() & 0o777, 0o750);
+        assert_eq!(dir_meta.modified().expect("modified"), mtime);
+        let _ = fs::remove_dir_all(root);
+    }
+}
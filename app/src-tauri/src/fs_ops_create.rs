@@ -1,11 +1,18 @@
-use crate::fs_ops_mutate_helpers::{io_error_code, log_and_fail};
-use crate::fs_ops_preflight::preflight_create;
+use crate::fs_ops_mutate_helpers::{create_symlink, io_error_code, log_and_fail};
+use crate::fs_ops_preflight::{preflight_create, preflight_create_link};
 use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::undo_journal::JournalOp;
 use std::fs;
+use std::path::Path;
 use std::time::Instant;
 
 #[tauri::command]
-pub fn fs_create(parent: String, name: String, kind: String) -> Result<(), String> {
+pub fn fs_create(
+    parent: String,
+    name: String,
+    kind: String,
+    target: Option<String>,
+) -> Result<(), String> {
     let started = Instant::now();
     let resolved_parent = match resolve_legacy_path_for(&parent, ProviderCapability::Create) {
         Ok(path) => path,
@@ -20,8 +27,8 @@ pub fn fs_create(parent: String, name: String, kind: String) -> Result<(), Strin
         }
     };
     let resolved_parent_text = resolved_parent.to_string_lossy().to_string();
-    let target = match preflight_create(&resolved_parent_text, &name, &kind) {
-        Ok(target) => target,
+    let path = match preflight_create(&resolved_parent_text, &name, &kind) {
+        Ok(path) => path,
         Err(err) => {
             crate::log_error(
                 "create",
@@ -32,25 +39,64 @@ pub fn fs_create(parent: String, name: String, kind: String) -> Result<(), Strin
             return Err(format!("code={}; {}", err.code, err.message));
         }
     };
-    let result = if kind == "folder" {
-        fs::create_dir(&target).map(|_| ())
+    let is_link = kind == "symlink" || kind == "hardlink";
+    let link_target = if is_link {
+        let raw = target.unwrap_or_default();
+        // Absolute targets go through the provider; relative ones stay relative to the link.
+        let text = if Path::new(&raw).is_absolute() {
+            match resolve_legacy_path_for(&raw, ProviderCapability::Read) {
+                Ok(path) => path.to_string_lossy().to_string(),
+                Err(err) => {
+                    let message = format!("code={}; {}", err.code(), err);
+                    crate::log_error("create", &parent, &name, &message);
+                    return Err(message);
+                }
+            }
+        } else {
+            raw
+        };
+        match preflight_create_link(&path, &kind, &text) {
+            Ok(resolved) => Some((text, resolved)),
+            Err(err) => {
+                crate::log_error(
+                    "create",
+                    &parent,
+                    &name,
+                    &format!("code={}; {}", err.code, err.message),
+                );
+                return Err(format!("code={}; {}", err.code, err.message));
+            }
+        }
     } else {
-        fs::File::create(&target).map(|_| ())
+        None
+    };
+    let result = match (kind.as_str(), &link_target) {
+        ("folder", _) => fs::create_dir(&path).map(|_| ()),
+        ("symlink", Some((text, resolved))) => {
+            create_symlink(Path::new(text), &path, resolved.is_dir())
+        }
+        ("hardlink", Some((_, resolved))) => fs::hard_link(resolved, &path),
+        _ => fs::File::create(&path).map(|_| ()),
     };
     if let Err(err) = result {
         let code = io_error_code(&err);
         return log_and_fail(
             "create",
             &parent,
-            &target.to_string_lossy(),
+            &path.to_string_lossy(),
             &format!("code={}; {}", code, err),
         );
     }
     crate::log_event(
         "CREATE",
         &parent,
-        &target.to_string_lossy(),
+        &path.to_string_lossy(),
         &format!("kind={}; ms={}", kind, started.elapsed().as_millis()),
     );
+    crate::undo_journal::record(JournalOp::Create {
+        path: path.to_string_lossy().to_string(),
+        create_kind: kind,
+        target: link_target.map(|(text, _)| text),
+    });
     Ok(())
 }
@@ -1,14 +1,17 @@
 use std::fs;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::path::Path;
+use std::time::Instant;
 
 use trash::delete;
 
 use crate::fs_ops_mutate_helpers::{
-    io_error_code, log_and_fail, move_recursively, record_failure, undo_trash_root,
+    io_error_code, log_and_fail, move_to_undo_trash, record_failure, undo_trash_root,
+    undo_trash_stamp,
 };
 use crate::fs_ops_preflight::preflight_delete;
 use crate::storage_provider::{resolve_legacy_paths_for, ProviderCapability};
 use crate::types::dto::{DeleteSummary, TrashItem};
+use crate::undo_journal::{JournalOp, JournalPair};
 
 #[tauri::command]
 pub fn fs_delete_trash(items: Vec<String>) -> Result<(), String> {
@@ -98,42 +101,31 @@ pub fn fs_delete_with_undo(items: Vec<String>) -> Result<DeleteSummary, String>
     let mut trashed = Vec::new();
     let root = undo_trash_root()?;
     fs::create_dir_all(&root).map_err(|e| e.to_string())?;
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis();
+    let stamp = undo_trash_stamp();
+    let mut journal_pairs = Vec::new();
     for (index, (raw_item, from)) in items
         .into_iter()
         .zip(resolved_items.into_iter())
         .enumerate()
     {
-        let name = match from.file_name() {
-            Some(name) => name.to_owned(),
-            None => {
-                let err = "invalid path";
-                crate::log_error("delete", &raw_item, "-", err);
+        if from.file_name().is_none() {
+            let err = "invalid path";
+            crate::log_error("delete", &raw_item, "-", err);
+            failed += 1;
+            record_failure(&mut failures, &raw_item, "invalid_path", err);
+            continue;
+        }
+        let to = match move_to_undo_trash(&from, stamp, index) {
+            Ok(to) => to,
+            Err(err) => {
+                let code = io_error_code(&err);
+                let msg = err.to_string();
+                crate::log_error("delete", &raw_item, "-", &msg);
                 failed += 1;
-                record_failure(&mut failures, &raw_item, "invalid_path", err);
+                record_failure(&mut failures, &raw_item, code, &msg);
                 continue;
             }
         };
-        let bucket = root.join(format!("{}_{}", stamp, index));
-        if let Err(err) = fs::create_dir_all(&bucket) {
-            let msg = err.to_string();
-            crate::log_error("delete", &raw_item, &bucket.to_string_lossy(), &msg);
-            failed += 1;
-            record_failure(&mut failures, &raw_item, io_error_code(&err), &msg);
-            continue;
-        }
-        let to = bucket.join(name);
-        if let Err(err) = move_recursively(&from, &to) {
-            let code = io_error_code(&err);
-            let msg = err.to_string();
-            crate::log_error("delete", &raw_item, &to.to_string_lossy(), &msg);
-            failed += 1;
-            record_failure(&mut failures, &raw_item, code, &msg);
-            continue;
-        }
         crate::log_event(
             "DELETE",
             &raw_item,
@@ -141,11 +133,27 @@ pub fn fs_delete_with_undo(items: Vec<String>) -> Result<DeleteSummary, String>
             &format!("count={}; ms={}", total, started.elapsed().as_millis()),
         );
         ok += 1;
+        journal_pairs.push(JournalPair {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+        });
         trashed.push(TrashItem {
             original: raw_item,
             trashed: to.to_string_lossy().to_string(),
         });
     }
+    if !journal_pairs.is_empty() {
+        crate::undo_journal::record(JournalOp::Delete {
+            pairs: journal_pairs,
+        });
+    }
+    let protect: Vec<String> = trashed
+        .iter()
+        .filter_map(|item| Path::new(&item.trashed).parent()?.file_name())
+        .map(|bucket| bucket.to_string_lossy().to_string())
+        .collect();
+    // Walking the whole undo trash can take a while; the delete does not wait for it.
+    std::thread::spawn(move || crate::fs_ops_undo_trash::enforce_undo_trash_limits(&protect));
     Ok(DeleteSummary {
         ok,
         failed,
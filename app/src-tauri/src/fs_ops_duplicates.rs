@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_transfer_helpers::{
+    cancel_requested, io_error_code, record_failure, reset_cancel_request,
+};
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{
+    DuplicateGroup, DuplicateOptions, DuplicateProgress, DuplicateResult, DuplicateStage,
+    HardlinkReplace, OpSummary, EVENT_DUPLICATES_PROGRESS,
+};
+use crate::utils::{file_identity, matches_any_glob, sha256_file, to_hex};
+
+const PARTIAL_HASH_BYTES: u64 = 64 * 1024;
+const SCAN_PROGRESS_EVERY: u64 = 500;
+/// Hashing progress is sent at most this often, plus once when a stage is done.
+const HASH_PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Candidate {
+    path: PathBuf,
+    size: u64,
+}
+
+fn sha256_prefix(path: &Path, limit: u64) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut reader = File::open(path)?.take(limit);
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+fn scan_dir(
+    dir: &Path,
+    rel: &str,
+    options: &DuplicateOptions,
+    seen: &mut HashSet<(u64, u64)>,
+    out: &mut Vec<Candidate>,
+    on_progress: &mut dyn FnMut(DuplicateProgress),
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if cancel_requested() {
+            return;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let child_rel = if rel.is_empty() {
+            name
+        } else {
+            format!("{}/{}", rel, name)
+        };
+        if matches_any_glob(&options.ignore, &child_rel) {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if meta.file_type().is_symlink() {
+            continue;
+        }
+        if meta.is_dir() {
+            scan_dir(&path, &child_rel, options, seen, out, on_progress);
+            continue;
+        }
+        if meta.len() < options.min_size.max(1) {
+            continue;
+        }
+        // Hardlinks and overlapping roots resolve to the same identity; keep one path.
+        if let Some(identity) = file_identity(&path) {
+            if !seen.insert(identity) {
+                continue;
+            }
+        }
+        out.push(Candidate {
+            path,
+            size: meta.len(),
+        });
+        if (out.len() as u64).is_multiple_of(SCAN_PROGRESS_EVERY) {
+            on_progress(DuplicateProgress {
+                stage: DuplicateStage::Scan,
+                processed: out.len() as u64,
+                total: 0,
+                group: None,
+            });
+        }
+    }
+}
+
+/// Regroups every bucket by `key`, dropping groups that end up with a single file.
+/// `on_group` sees each surviving group as soon as its bucket is done.
+fn refine<K, F, G>(
+    buckets: Vec<Vec<Candidate>>,
+    stage: DuplicateStage,
+    mut key: F,
+    mut on_group: G,
+    on_progress: &mut dyn FnMut(DuplicateProgress),
+) -> (Vec<(K, Vec<Candidate>)>, bool)
+where
+    K: std::hash::Hash + Eq,
+    F: FnMut(&Candidate) -> io::Result<K>,
+    G: FnMut(&K, &[Candidate], &mut dyn FnMut(DuplicateProgress)),
+{
+    let total: u64 = buckets.iter().map(|b| b.len() as u64).sum();
+    let mut processed = 0u64;
+    let mut last_emit = Instant::now();
+    let mut out = Vec::new();
+    for bucket in buckets {
+        let mut groups: HashMap<K, Vec<Candidate>> = HashMap::new();
+        for candidate in bucket {
+            if cancel_requested() {
+                return (out, true);
+            }
+            processed += 1;
+            if last_emit.elapsed() >= HASH_PROGRESS_INTERVAL {
+                on_progress(DuplicateProgress {
+                    stage,
+                    processed,
+                    total,
+                    group: None,
+                });
+                last_emit = Instant::now();
+            }
+            // Unreadable files simply drop out of the candidates.
+            if let Ok(k) = key(&candidate) {
+                groups.entry(k).or_default().push(candidate);
+            }
+        }
+        for (k, group) in groups {
+            if group.len() > 1 {
+                on_group(&k, &group, on_progress);
+                out.push((k, group));
+            }
+        }
+    }
+    on_progress(DuplicateProgress {
+        stage,
+        processed,
+        total,
+        group: None,
+    });
+    (out, false)
+}
+
+fn to_group(hash: &str, group: &[Candidate]) -> DuplicateGroup {
+    let mut paths: Vec<String> = group
+        .iter()
+        .map(|c| c.path.to_string_lossy().to_string())
+        .collect();
+    paths.sort();
+    DuplicateGroup {
+        hash: hash.to_string(),
+        size: group[0].size,
+        paths,
+    }
+}
+
+pub(crate) fn find_duplicates(
+    roots: &[PathBuf],
+    options: &DuplicateOptions,
+    on_progress: &mut dyn FnMut(DuplicateProgress),
+) -> DuplicateResult {
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+    for root in roots {
+        scan_dir(root, "", options, &mut seen, &mut candidates, on_progress);
+    }
+    let scanned_files = candidates.len() as u64;
+    let mut result = DuplicateResult {
+        groups: Vec::new(),
+        scanned_files,
+        wasted_bytes: 0,
+        canceled: cancel_requested(),
+    };
+    if result.canceled {
+        return result;
+    }
+
+    let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    for candidate in candidates {
+        by_size.entry(candidate.size).or_default().push(candidate);
+    }
+    let size_buckets: Vec<Vec<Candidate>> = by_size
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+
+    let (partial, canceled) = refine(
+        size_buckets,
+        DuplicateStage::Partial,
+        |c| sha256_prefix(&c.path, PARTIAL_HASH_BYTES),
+        |_, _, _| {},
+        on_progress,
+    );
+    if canceled {
+        result.canceled = true;
+        return result;
+    }
+    let partial_buckets = partial.into_iter().map(|(_, group)| group).collect();
+    let (full, canceled) = refine(
+        partial_buckets,
+        DuplicateStage::Full,
+        |c| {
+            // Files that fit in the partial read are already fully hashed.
+            if c.size <= PARTIAL_HASH_BYTES {
+                sha256_prefix(&c.path, PARTIAL_HASH_BYTES)
+            } else {
+                sha256_file(&c.path)
+            }
+        },
+        |hash, group, on_progress| {
+            on_progress(DuplicateProgress {
+                stage: DuplicateStage::Full,
+                processed: 0,
+                total: 0,
+                group: Some(to_group(hash, group)),
+            });
+        },
+        on_progress,
+    );
+    let mut groups: Vec<DuplicateGroup> = full
+        .iter()
+        .map(|(hash, group)| to_group(hash, group))
+        .collect();
+    groups.sort_by(|a, b| {
+        (b.size * b.paths.len() as u64)
+            .cmp(&(a.size * a.paths.len() as u64))
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    result.wasted_bytes = groups
+        .iter()
+        .map(|g| g.size * (g.paths.len() as u64 - 1))
+        .sum();
+    result.groups = groups;
+    result.canceled = canceled;
+    result
+}
+
+#[tauri::command]
+pub fn fs_find_duplicates(
+    app: AppHandle,
+    roots: Vec<String>,
+    options: Option<DuplicateOptions>,
+) -> Result<DuplicateResult, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    if roots.is_empty() {
+        return Err(format_error(AppErrorKind::InvalidPath, "no roots"));
+    }
+    let mut resolved = Vec::with_capacity(roots.len());
+    for root in &roots {
+        let path = resolve_legacy_path_for(root, ProviderCapability::Read).map_err(|err| {
+            let msg = format!("code={}; {}", err.code(), err);
+            crate::log_error("duplicates", root, "-", &msg);
+            msg
+        })?;
+        if !path.is_dir() {
+            let msg = format_error(AppErrorKind::NotFound, "folder not found");
+            crate::log_error("duplicates", root, "-", &msg);
+            return Err(msg);
+        }
+        resolved.push(path);
+    }
+    let options = options.unwrap_or_default();
+    let result = find_duplicates(&resolved, &options, &mut |progress| {
+        let _ = app.emit(EVENT_DUPLICATES_PROGRESS, progress);
+    });
+    crate::log_event(
+        "DUPLICATES",
+        &roots.join("|"),
+        "-",
+        &format!(
+            "groups={}; files={}; ms={}",
+            result.groups.len(),
+            result.scanned_files,
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(result)
+}
+
+fn replace_with_hardlink(keep: &Path, target: &Path) -> io::Result<()> {
+    if !fs::metadata(target)?.is_file() || !fs::metadata(keep)?.is_file() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file"));
+    }
+    if file_identity(keep).is_some() && file_identity(keep) == file_identity(target) {
+        return Ok(());
+    }
+    if sha256_file(keep)? != sha256_file(target)? {
+        return Err(io::Error::other("contents differ"));
+    }
+    let name = target
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))?;
+    let temp = target.with_file_name(format!(".{}.rf-link", name.to_string_lossy()));
+    fs::hard_link(keep, &temp)?;
+    // Rename over the duplicate so it is never missing, even if linking fails midway.
+    if let Err(err) = fs::rename(&temp, target) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn fs_replace_with_hardlinks(groups: Vec<HardlinkReplace>) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    let total: usize = groups.iter().map(|g| g.replace.len()).sum();
+    if total == 0 {
+        return Err(format_error(AppErrorKind::InvalidPath, "no items"));
+    }
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    for group in groups {
+        let keep = match resolve_legacy_path_for(&group.keep, ProviderCapability::Read) {
+            Ok(path) => path,
+            Err(err) => {
+                let msg = format!("code={}; {}", err.code(), err);
+                crate::log_error("hardlink", &group.keep, "-", &msg);
+                for item in &group.replace {
+                    failed += 1;
+                    record_failure(&mut failures, item, err.code(), &msg);
+                }
+                continue;
+            }
+        };
+        for item in group.replace {
+            let result = resolve_legacy_path_for(&item, ProviderCapability::Delete)
+                .map_err(|err| (err.code(), err.to_string()))
+                .and_then(|target| {
+                    replace_with_hardlink(&keep, &target)
+                        .map_err(|err| (io_error_code(&err), err.to_string()))
+                });
+            match result {
+                Ok(()) => {
+                    crate::log_event(
+                        "HARDLINK",
+                        &group.keep,
+                        &item,
+                        &format!("count={}; ms={}", total, started.elapsed().as_millis()),
+                    );
+                    ok += 1;
+                }
+                Err((code, message)) => {
+                    crate::log_error("hardlink", &group.keep, &item, &message);
+                    failed += 1;
+                    record_failure(&mut failures, &item, code, &message);
+                }
+            }
+        }
+    }
+    Ok(OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_duplicates, replace_with_hardlink};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{DuplicateOptions, DuplicateStage};
+    use crate::utils::file_identity;
+    use std::fs;
+
+    #[test]
+    fn find_duplicates_groups_identical_files_and_skips_hardlinks() {
+        let root = unique_temp_dir("rf-dupes");
+        let big: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut big_other = big.clone();
+        *big_other.last_mut().expect("non-empty") ^= 1;
+        fs::create_dir_all(root.join("sub")).expect("create sub");
+        fs::write(root.join("a.bin"), &big).expect("write a");
+        fs::write(root.join("sub").join("b.bin"), &big).expect("write b");
+        fs::write(root.join("c.bin"), &big_other).expect("write c");
+        fs::write(root.join("x.txt"), b"same").expect("write x");
+        fs::write(root.join("y.txt"), b"same").expect("write y");
+        fs::hard_link(root.join("x.txt"), root.join("x-link.txt")).expect("hardlink");
+
+        let mut hash_progress = Vec::new();
+        let result = find_duplicates(
+            std::slice::from_ref(&root),
+            &DuplicateOptions::default(),
+            &mut |progress| {
+                if progress.group.is_none() && !matches!(progress.stage, DuplicateStage::Scan) {
+                    hash_progress.push((progress.processed, progress.total));
+                }
+            },
+        );
+        // Throttled: far fewer reports than hashed files, ending with a complete one.
+        assert!(hash_progress.len() < 10);
+        assert_eq!(hash_progress.last(), Some(&(5, 5)));
+        assert!(!result.canceled);
+        assert_eq!(result.groups.len(), 2);
+        assert_eq!(result.groups[0].paths.len(), 2);
+        assert!(result.groups[0].paths[0].ends_with("a.bin"));
+        assert_eq!(result.groups[1].paths.len(), 2);
+        assert_eq!(result.wasted_bytes, 200_000 + 4);
+
+        replace_with_hardlink(&root.join("a.bin"), &root.join("sub").join("b.bin"))
+            .expect("replace with hardlink");
+        assert_eq!(
+            file_identity(&root.join("a.bin")),
+            file_identity(&root.join("sub").join("b.bin"))
+        );
+        assert!(replace_with_hardlink(&root.join("a.bin"), &root.join("c.bin")).is_err());
+        let _ = fs::remove_dir_all(root);
+    }
+}
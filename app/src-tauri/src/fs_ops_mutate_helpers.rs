@@ -1,8 +1,9 @@
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::types::OpFailure;
+use crate::types::{ConflictPolicy, OpFailure};
 
 pub fn validate_name(name: &str) -> Result<&str, String> {
     let trimmed = name.trim();
@@ -32,12 +33,39 @@ pub fn log_and_fail(action: &str, src: &str, dst: &str, err: &str) -> Result<(),
     Err(message)
 }
 
+/// Creates a symlink at `link`. Windows needs to know whether the target is a folder.
+pub fn create_symlink(target: &Path, link: &Path, target_is_dir: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let _ = target_is_dir;
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        if target_is_dir {
+            std::os::windows::fs::symlink_dir(target, link)
+        } else {
+            std::os::windows::fs::symlink_file(target, link)
+        }
+    }
+}
+
 pub fn move_recursively(from: &Path, to: &Path) -> io::Result<()> {
     let rename_result = fs::rename(from, to);
     match rename_result {
         Ok(_) => Ok(()),
         Err(_err) => {
-            if from.is_dir() {
+            let is_link = fs::symlink_metadata(from)
+                .map(|meta| meta.file_type().is_symlink())
+                .unwrap_or(false);
+            if is_link {
+                // Recreate the link itself rather than copying what it points to.
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                create_symlink(&fs::read_link(from)?, to, from.is_dir())?;
+                fs::remove_file(from).or_else(|_| fs::remove_dir(from))?;
+            } else if from.is_dir() {
                 fs::create_dir_all(to)?;
                 for entry in fs::read_dir(from)? {
                     let entry = entry?;
@@ -87,3 +115,144 @@ pub fn undo_trash_root() -> Result<PathBuf, String> {
     })?;
     Ok(base.join("undo_trash"))
 }
+
+pub fn undo_trash_stamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+pub fn move_to_undo_trash(from: &Path, stamp: u128, index: usize) -> io::Result<PathBuf> {
+    let root = undo_trash_root().map_err(io::Error::other)?;
+    move_to_undo_trash_in(&root, from, stamp, index)
+}
+
+/// Creates a bucket that no other item shares. Items trashed in the same millisecond under
+/// the same index, e.g. by separate deletes or restores, get `<stamp>_<index>_1`, `_2`, ...
+fn claim_undo_trash_bucket(root: &Path, stamp: u128, index: usize) -> io::Result<PathBuf> {
+    fs::create_dir_all(root)?;
+    let mut suffix = 0u32;
+    loop {
+        let id = match suffix {
+            0 => format!("{}_{}", stamp, index),
+            _ => format!("{}_{}_{}", stamp, index, suffix),
+        };
+        let bucket = root.join(id);
+        match fs::create_dir(&bucket) {
+            Ok(()) => return Ok(bucket),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => suffix += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn move_to_undo_trash_in(
+    root: &Path,
+    from: &Path,
+    stamp: u128,
+    index: usize,
+) -> io::Result<PathBuf> {
+    let name = from
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))?;
+    let bucket = claim_undo_trash_bucket(root, stamp, index)?;
+    let to = bucket.join(name);
+    if let Err(err) = move_recursively(from, &to) {
+        let _ = fs::remove_dir(&bucket);
+        return Err(err);
+    }
+    crate::fs_ops_undo_trash::write_undo_trash_manifest(&bucket, from, &to);
+    Ok(to)
+}
+
+/// Returns `name (2).ext`, `name (3).ext`, ... next to `path`, whichever is free first.
+pub fn unique_sibling_path(path: &Path) -> PathBuf {
+    let parent = path.parent().map(PathBuf::from).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut n = 2u32;
+    loop {
+        let candidate = parent.join(format!("{stem} ({n}){ext}"));
+        if !candidate.exists() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+pub enum ConflictAction {
+    /// Target is free (possibly a renamed sibling); write there.
+    Proceed(PathBuf),
+    /// Target exists and should be replaced.
+    Replace(PathBuf),
+    Skip,
+}
+
+pub fn plan_conflict(
+    target: &Path,
+    source_modified: Option<SystemTime>,
+    policy: ConflictPolicy,
+) -> io::Result<ConflictAction> {
+    if fs::symlink_metadata(target).is_err() {
+        return Ok(ConflictAction::Proceed(target.to_path_buf()));
+    }
+    match policy {
+        ConflictPolicy::Fail => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "target already exists",
+        )),
+        ConflictPolicy::Skip => Ok(ConflictAction::Skip),
+        ConflictPolicy::Rename => Ok(ConflictAction::Proceed(unique_sibling_path(target))),
+        ConflictPolicy::Overwrite => Ok(ConflictAction::Replace(target.to_path_buf())),
+        ConflictPolicy::Newer => {
+            let existing = fs::metadata(target).and_then(|m| m.modified()).ok();
+            match (source_modified, existing) {
+                (Some(source), Some(existing)) if source > existing => {
+                    Ok(ConflictAction::Replace(target.to_path_buf()))
+                }
+                _ => Ok(ConflictAction::Skip),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::move_to_undo_trash_in;
+    use crate::test_support::unique_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn items_trashed_with_the_same_stamp_get_their_own_buckets() {
+        let root = unique_temp_dir("rf-undo-trash-claim");
+        let trash = root.join("trash");
+        let (first, second) = (root.join("a").join("x.txt"), root.join("b").join("x.txt"));
+        for path in [&first, &second] {
+            fs::create_dir_all(path.parent().expect("parent")).expect("create parent");
+            fs::write(path, path.to_string_lossy().as_bytes()).expect("write item");
+        }
+
+        let first_to = move_to_undo_trash_in(&trash, &first, 42, 0).expect("trash first");
+        let second_to = move_to_undo_trash_in(&trash, &second, 42, 0).expect("trash second");
+        assert_eq!(first_to, trash.join("42_0").join("x.txt"));
+        assert_eq!(second_to, trash.join("42_0_1").join("x.txt"));
+        for (path, to) in [(&first, &first_to), (&second, &second_to)] {
+            assert_eq!(
+                fs::read(to).expect("read trashed"),
+                path.to_string_lossy().as_bytes()
+            );
+            let manifest =
+                fs::read_to_string(to.parent().expect("bucket").join(".rf-undo-manifest.json"))
+                    .expect("manifest");
+            assert!(manifest.contains(&*path.parent().expect("parent").to_string_lossy()));
+        }
+        let _ = fs::remove_dir_all(root);
+    }
+}
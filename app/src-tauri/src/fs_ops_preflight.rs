@@ -1,7 +1,10 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::fs_ops_archive_split::existing_volume;
 use crate::fs_ops_mutate_helpers::{ensure_parent_exists, validate_name};
+use crate::types::AttributeChanges;
+use crate::utils::{parse_mode, parse_time, same_volume};
 
 #[derive(Debug)]
 pub struct PreflightError {
@@ -31,6 +34,11 @@ fn is_same_path(a: &Path, b: &Path) -> bool {
     }
 }
 
+/// Like `exists`, but also true for dangling symlinks.
+fn path_present(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
 fn is_subpath(parent: &Path, child: &Path) -> bool {
     match (canonical_or(parent), canonical_or(child)) {
         (Some(parent_canon), Some(child_canon)) => child_canon.starts_with(&parent_canon),
@@ -38,15 +46,17 @@ fn is_subpath(parent: &Path, child: &Path) -> bool {
     }
 }
 
+pub const CREATE_KINDS: [&str; 4] = ["file", "folder", "symlink", "hardlink"];
+
 pub fn preflight_create(parent: &str, name: &str, kind: &str) -> PreflightResult<PathBuf> {
     let trimmed = validate_name(name).map_err(|err| PreflightError::new("invalid_name", err))?;
-    if kind != "file" && kind != "folder" {
+    if !CREATE_KINDS.contains(&kind) {
         return Err(PreflightError::new("invalid_kind", "invalid create kind"));
     }
     let base = PathBuf::from(parent);
     ensure_parent_exists(&base).map_err(|err| PreflightError::new("parent_not_found", err))?;
     let target = base.join(trimmed);
-    if target.exists() {
+    if path_present(&target) {
         return Err(PreflightError::new(
             "already_exists",
             "target already exists",
@@ -55,11 +65,51 @@ pub fn preflight_create(parent: &str, name: &str, kind: &str) -> PreflightResult
     Ok(target)
 }
 
+/// Checks the target of a `symlink`/`hardlink` create. A relative `link_target` is taken
+/// relative to the new link's folder. Returns the target as it resolves on disk.
+pub fn preflight_create_link(
+    link: &Path,
+    kind: &str,
+    link_target: &str,
+) -> PreflightResult<PathBuf> {
+    if link_target.trim().is_empty() {
+        return Err(PreflightError::new(
+            "link_target_required",
+            "link target is required",
+        ));
+    }
+    let parent = link
+        .parent()
+        .ok_or_else(|| PreflightError::new("invalid_path", "invalid path"))?;
+    let resolved = parent.join(link_target);
+    if !resolved.exists() {
+        return Err(PreflightError::new(
+            "link_target_missing",
+            format!("link target not found: {}", resolved.display()),
+        ));
+    }
+    if kind == "hardlink" {
+        if !resolved.is_file() {
+            return Err(PreflightError::new(
+                "hardlink_target_not_file",
+                "hardlinks can only point to files",
+            ));
+        }
+        if same_volume(&resolved, parent) == Some(false) {
+            return Err(PreflightError::new(
+                "hardlink_cross_device",
+                "hardlink target is on a different volume",
+            ));
+        }
+    }
+    Ok(resolved)
+}
+
 pub fn preflight_rename(path: &str, new_name: &str) -> PreflightResult<(PathBuf, PathBuf)> {
     let trimmed =
         validate_name(new_name).map_err(|err| PreflightError::new("invalid_name", err))?;
     let from = PathBuf::from(path);
-    if !from.exists() {
+    if !path_present(&from) {
         return Err(PreflightError::new("not_found", "source not found"));
     }
     let parent = from
@@ -69,7 +119,7 @@ pub fn preflight_rename(path: &str, new_name: &str) -> PreflightResult<(PathBuf,
     if is_same_path(&from, &to) {
         return Err(PreflightError::new("same_path", "same name"));
     }
-    if to.exists() {
+    if path_present(&to) {
         return Err(PreflightError::new(
             "already_exists",
             "target already exists",
@@ -87,13 +137,80 @@ pub fn preflight_delete(items: &[String]) -> PreflightResult<()> {
             return Err(PreflightError::new("invalid_path", "invalid path"));
         }
         let path = PathBuf::from(item);
-        if !path.exists() {
+        if !path_present(&path) {
             return Err(PreflightError::new("not_found", "source not found"));
         }
     }
     Ok(())
 }
 
+pub const SHRED_MAX_PASSES: u32 = 35;
+
+/// Shredding cannot be undone, so the caller must pass `confirmed` after asking the user.
+pub fn preflight_shred(items: &[String], passes: u32, confirmed: bool) -> PreflightResult<()> {
+    preflight_delete(items)?;
+    if passes == 0 || passes > SHRED_MAX_PASSES {
+        return Err(PreflightError::new(
+            "shred_invalid_passes",
+            format!("passes must be between 1 and {}", SHRED_MAX_PASSES),
+        ));
+    }
+    if !confirmed {
+        return Err(PreflightError::new(
+            "shred_confirm_required",
+            "secure delete requires confirmation",
+        ));
+    }
+    Ok(())
+}
+
+pub fn preflight_set_attributes(
+    items: &[String],
+    changes: &AttributeChanges,
+) -> PreflightResult<()> {
+    preflight_delete(items)?;
+    let has_changes = changes.readonly.is_some()
+        || changes.file_mode.is_some()
+        || changes.dir_mode.is_some()
+        || changes.uid.is_some()
+        || changes.gid.is_some()
+        || changes.mtime.is_some()
+        || changes.atime.is_some();
+    if !has_changes {
+        return Err(PreflightError::new("no_changes", "nothing to change"));
+    }
+    for mode in [&changes.file_mode, &changes.dir_mode]
+        .into_iter()
+        .flatten()
+    {
+        if parse_mode(mode).is_none() {
+            return Err(PreflightError::new(
+                "invalid_mode",
+                format!("invalid mode: {}", mode),
+            ));
+        }
+    }
+    for time in [&changes.mtime, &changes.atime].into_iter().flatten() {
+        if parse_time(time).is_none() {
+            return Err(PreflightError::new(
+                "invalid_time",
+                format!("invalid time: {}", time),
+            ));
+        }
+    }
+    let unix_only = changes.file_mode.is_some()
+        || changes.dir_mode.is_some()
+        || changes.uid.is_some()
+        || changes.gid.is_some();
+    if unix_only && !cfg!(unix) {
+        return Err(PreflightError::new(
+            "attributes_unsupported",
+            "mode and owner changes need a Unix file system",
+        ));
+    }
+    Ok(())
+}
+
 pub fn preflight_transfer(items: &[String], destination: &str) -> PreflightResult<PathBuf> {
     if items.is_empty() {
         return Err(PreflightError::new("no_items", "no items"));
@@ -168,7 +285,54 @@ pub fn preflight_copy_pairs(pairs: &[(String, String)]) -> PreflightResult<()> {
     Ok(())
 }
 
-pub fn preflight_zip_create(items: &[String], destination: &str) -> PreflightResult<()> {
+pub fn preflight_sync(source: &str, target: &str) -> PreflightResult<()> {
+    if source.trim().is_empty() || target.trim().is_empty() {
+        return Err(PreflightError::new("invalid_path", "invalid path"));
+    }
+    let source_path = PathBuf::from(source);
+    let target_path = PathBuf::from(target);
+    if !source_path.exists() {
+        return Err(PreflightError::new("not_found", "source not found"));
+    }
+    if !source_path.is_dir() {
+        return Err(PreflightError::new(
+            "source_not_dir",
+            "source is not a directory",
+        ));
+    }
+    if target_path.exists() && !target_path.is_dir() {
+        return Err(PreflightError::new(
+            "destination_not_dir",
+            "destination is not a directory",
+        ));
+    }
+    if is_same_path(&source_path, &target_path) {
+        return Err(PreflightError::new(
+            "same_path",
+            "source and destination are the same",
+        ));
+    }
+    if is_subpath(&source_path, &target_path) {
+        return Err(PreflightError::new(
+            "destination_inside_source",
+            "destination inside source",
+        ));
+    }
+    if is_subpath(&target_path, &source_path) {
+        return Err(PreflightError::new(
+            "source_inside_destination",
+            "source inside destination",
+        ));
+    }
+    Ok(())
+}
+
+/// `volume_size` is set when the archive will be split into volumes.
+pub fn preflight_zip_create(
+    items: &[String],
+    destination: &str,
+    volume_size: Option<u64>,
+) -> PreflightResult<()> {
     if items.is_empty() {
         return Err(PreflightError::new("no_items", "no items to zip"));
     }
@@ -183,6 +347,36 @@ pub fn preflight_zip_create(items: &[String], destination: &str) -> PreflightRes
             return Err(PreflightError::new("not_found", "source not found"));
         }
     }
+    if volume_size.is_some() {
+        if let Some(volume) = existing_volume(Path::new(destination)) {
+            return Err(PreflightError::new(
+                "already_exists",
+                format!("volume already exists: {}", volume.display()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `removes` is the number of entry names to delete; an update must change something.
+pub fn preflight_zip_update(path: &str, items: &[String], removes: usize) -> PreflightResult<()> {
+    if path.trim().is_empty() {
+        return Err(PreflightError::new("invalid_path", "invalid path"));
+    }
+    if !Path::new(path).is_file() {
+        return Err(PreflightError::new("not_found", "archive not found"));
+    }
+    if items.is_empty() && removes == 0 {
+        return Err(PreflightError::new("no_items", "nothing to update"));
+    }
+    for item in items {
+        if item.trim().is_empty() {
+            return Err(PreflightError::new("invalid_path", "invalid path"));
+        }
+        if !Path::new(item).exists() {
+            return Err(PreflightError::new("not_found", "source not found"));
+        }
+    }
     Ok(())
 }
 
@@ -196,3 +390,104 @@ pub fn preflight_zip_extract(path: &str, destination: &str) -> PreflightResult<(
     }
     Ok(())
 }
+
+/// `outputs` are the part files plus the manifest that a split would write.
+pub fn preflight_split(source: &Path, part_size: u64, outputs: &[PathBuf]) -> PreflightResult<()> {
+    if !source.is_file() {
+        return Err(PreflightError::new(
+            "source_not_file",
+            "source is not a file",
+        ));
+    }
+    if part_size == 0 {
+        return Err(PreflightError::new(
+            "invalid_part_size",
+            "part size must be greater than zero",
+        ));
+    }
+    if let Some(existing) = outputs.iter().find(|path| path_present(path)) {
+        return Err(PreflightError::new(
+            "already_exists",
+            format!("already exists: {}", existing.display()),
+        ));
+    }
+    Ok(())
+}
+
+pub fn preflight_join(parts: &[PathBuf], target: &Path) -> PreflightResult<()> {
+    if parts.is_empty() {
+        return Err(PreflightError::new(
+            "invalid_manifest",
+            "manifest lists no parts",
+        ));
+    }
+    if let Some(missing) = parts.iter().find(|path| !path.is_file()) {
+        return Err(PreflightError::new(
+            "part_missing",
+            format!("part not found: {}", missing.display()),
+        ));
+    }
+    if path_present(target) {
+        return Err(PreflightError::new(
+            "already_exists",
+            format!("already exists: {}", target.display()),
+        ));
+    }
+    Ok(())
+}
+
+pub fn preflight_journal_transfer(from: &Path, to: &Path) -> PreflightResult<()> {
+    if from.as_os_str().is_empty() || to.as_os_str().is_empty() {
+        return Err(PreflightError::new("invalid_path", "invalid path"));
+    }
+    if !path_present(from) {
+        return Err(PreflightError::new(
+            "journal_source_missing",
+            format!("no longer exists: {}", from.display()),
+        ));
+    }
+    if path_present(to) {
+        return Err(PreflightError::new(
+            "journal_target_exists",
+            format!("already exists: {}", to.display()),
+        ));
+    }
+    if from.is_dir() && is_subpath(from, to) {
+        return Err(PreflightError::new(
+            "destination_inside_source",
+            "destination inside source",
+        ));
+    }
+    Ok(())
+}
+
+pub fn preflight_journal_remove(path: &Path) -> PreflightResult<()> {
+    if path.as_os_str().is_empty() {
+        return Err(PreflightError::new("invalid_path", "invalid path"));
+    }
+    if !path_present(path) {
+        return Err(PreflightError::new(
+            "journal_source_missing",
+            format!("no longer exists: {}", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+pub fn preflight_journal_create(path: &Path, kind: &str) -> PreflightResult<()> {
+    if !CREATE_KINDS.contains(&kind) {
+        return Err(PreflightError::new("invalid_kind", "invalid create kind"));
+    }
+    let parent = path
+        .parent()
+        .ok_or_else(|| PreflightError::new("invalid_path", "invalid path"))?;
+    ensure_parent_exists(&parent.to_path_buf())
+        .map_err(|err| PreflightError::new("parent_not_found", err))?;
+    if path_present(path) {
+        return Err(PreflightError::new(
+            "journal_target_exists",
+            format!("already exists: {}", path.display()),
+        ));
+    }
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::fs_ops_mutate_helpers::{io_error_code, log_and_fail};
 use crate::fs_ops_preflight::preflight_rename;
 use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::undo_journal::JournalOp;
 use std::fs;
 use std::time::Instant;
 
@@ -47,5 +48,9 @@ pub fn fs_rename(path: String, new_name: String) -> Result<(), String> {
         &to.to_string_lossy(),
         &format!("ms={}", started.elapsed().as_millis()),
     );
+    crate::undo_journal::record(JournalOp::Rename {
+        from: from.to_string_lossy().to_string(),
+        to: to.to_string_lossy().to_string(),
+    });
     Ok(())
 }
@@ -0,0 +1,322 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use tauri::AppHandle;
+
+use crate::fs_ops_preflight::preflight_shred;
+use crate::fs_ops_transfer_helpers::{
+    cancel_requested, emit_progress, ensure_not_canceled, io_error_code, record_failure,
+    reset_cancel_request,
+};
+use crate::storage_provider::{resolve_legacy_paths_for, ProviderCapability};
+use crate::types::{OpKind, OpStatus, OpSummary};
+
+const SHRED_DEFAULT_PASSES: u32 = 3;
+const SHRED_CHUNK_SIZE: usize = 1024 * 1024;
+const SHRED_RENAME_ROUNDS: usize = 3;
+
+/// Overwriting a file with more than one name would destroy the data under its other
+/// names too, so such files are refused instead.
+#[derive(Debug)]
+struct HardLinked;
+
+impl fmt::Display for HardLinked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("file has other hard links; refusing to overwrite it")
+    }
+}
+
+impl std::error::Error for HardLinked {}
+
+fn shred_error_code(err: &io::Error) -> &'static str {
+    if err.get_ref().is_some_and(|inner| inner.is::<HardLinked>()) {
+        "hardlinked"
+    } else {
+        io_error_code(err)
+    }
+}
+
+#[cfg(unix)]
+fn ensure_single_link(meta: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    if meta.nlink() > 1 {
+        return Err(io::Error::other(HardLinked));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn ensure_single_link(_meta: &fs::Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    getrandom::getrandom(buf).map_err(|err| io::Error::other(err.to_string()))
+}
+
+fn random_name(len: usize) -> io::Result<String> {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    let mut bytes = vec![0u8; len.max(1)];
+    fill_random(&mut bytes)?;
+    Ok(bytes
+        .iter()
+        .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char)
+        .collect())
+}
+
+/// Renames `path` to random names of the same length inside its directory so the
+/// original name does not stay in the directory entries. Returns the final path.
+fn scramble_name(path: &Path) -> io::Result<PathBuf> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))?;
+    let len = path
+        .file_name()
+        .map(|name| name.to_string_lossy().chars().count())
+        .unwrap_or(8);
+    let mut current = path.to_path_buf();
+    for _ in 0..SHRED_RENAME_ROUNDS {
+        let next = parent.join(random_name(len)?);
+        if fs::symlink_metadata(&next).is_ok() {
+            continue;
+        }
+        fs::rename(&current, &next)?;
+        current = next;
+    }
+    Ok(current)
+}
+
+fn overwrite_file(path: &Path, passes: u32) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    // Checked on the opened file so a link made after `shred_path` looked is still seen.
+    let meta = file.metadata()?;
+    ensure_single_link(&meta)?;
+    let len = meta.len();
+    let mut buf = vec![0u8; SHRED_CHUNK_SIZE];
+    for _ in 0..passes {
+        file.seek(SeekFrom::Start(0))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            ensure_not_canceled()?;
+            let chunk = remaining.min(SHRED_CHUNK_SIZE as u64) as usize;
+            fill_random(&mut buf[..chunk])?;
+            file.write_all(&buf[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        file.sync_all()?;
+    }
+    file.set_len(0)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Fails if any file under `path` has other hard links, so a refusal comes before anything
+/// in the tree has been overwritten.
+fn ensure_no_hard_links(path: &Path) -> io::Result<()> {
+    ensure_not_canceled()?;
+    let meta = fs::symlink_metadata(path)?;
+    if meta.file_type().is_symlink() {
+        return Ok(());
+    }
+    if meta.is_dir() {
+        for entry in fs::read_dir(path)? {
+            ensure_no_hard_links(&entry?.path())?;
+        }
+        return Ok(());
+    }
+    ensure_single_link(&meta)
+}
+
+fn shred_path(path: &Path, passes: u32) -> io::Result<()> {
+    ensure_no_hard_links(path)?;
+    shred_tree(path, passes)
+}
+
+fn shred_tree(path: &Path, passes: u32) -> io::Result<()> {
+    ensure_not_canceled()?;
+    let meta = fs::symlink_metadata(path)?;
+    if meta.file_type().is_symlink() {
+        // Only the link itself goes away; its target is left untouched.
+        let scrambled = scramble_name(path)?;
+        return fs::remove_file(scrambled);
+    }
+    if meta.is_dir() {
+        for entry in fs::read_dir(path)? {
+            shred_tree(&entry?.path(), passes)?;
+        }
+        let scrambled = scramble_name(path)?;
+        return fs::remove_dir(scrambled);
+    }
+    // Checked again before touching permissions, which every name of the file shares.
+    ensure_single_link(&meta)?;
+    if meta.permissions().readonly() {
+        let mut perms = meta.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        perms.set_readonly(false);
+        fs::set_permissions(path, perms)?;
+    }
+    overwrite_file(path, passes)?;
+    let scrambled = scramble_name(path)?;
+    fs::remove_file(scrambled)
+}
+
+#[tauri::command]
+pub fn fs_delete_shred(
+    app: AppHandle,
+    items: Vec<String>,
+    passes: Option<u32>,
+    confirmed: Option<bool>,
+) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let passes = passes.unwrap_or(SHRED_DEFAULT_PASSES);
+    let resolved_items = match resolve_legacy_paths_for(&items, ProviderCapability::Delete) {
+        Ok(paths) => paths,
+        Err(err) => {
+            crate::log_error(
+                "shred",
+                "batch",
+                "-",
+                &format!("code={}; {}", err.code(), err),
+            );
+            return Err(format!("code={}; {}", err.code(), err));
+        }
+    };
+    let preflight_items: Vec<String> = resolved_items
+        .iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    if let Err(err) = preflight_shred(&preflight_items, passes, confirmed.unwrap_or(false)) {
+        crate::log_error(
+            "shred",
+            "batch",
+            "-",
+            &format!("code={}; {}", err.code, err.message),
+        );
+        return Err(format!("code={}; {}", err.code, err.message));
+    }
+    let total = resolved_items.len();
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    for (index, (raw_item, path)) in items.into_iter().zip(resolved_items).enumerate() {
+        if cancel_requested() {
+            break;
+        }
+        emit_progress(
+            &app,
+            OpKind::Shred,
+            raw_item.clone(),
+            index,
+            total,
+            OpStatus::Start,
+            String::new(),
+        );
+        if let Err(err) = shred_path(&path, passes) {
+            let code = shred_error_code(&err);
+            let message = err.to_string();
+            crate::log_error("shred", &raw_item, "-", &message);
+            failed += 1;
+            record_failure(&mut failures, &raw_item, code, &message);
+            emit_progress(
+                &app,
+                OpKind::Shred,
+                raw_item.clone(),
+                index,
+                total,
+                OpStatus::Fail,
+                message,
+            );
+            if cancel_requested() {
+                break;
+            }
+            continue;
+        }
+        crate::log_event(
+            "SHRED",
+            &raw_item,
+            "-",
+            &format!(
+                "passes={}; count={}; ms={}",
+                passes,
+                total,
+                started.elapsed().as_millis()
+            ),
+        );
+        ok += 1;
+        emit_progress(
+            &app,
+            OpKind::Shred,
+            raw_item,
+            index,
+            total,
+            OpStatus::Done,
+            String::new(),
+        );
+    }
+    Ok(OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shred_error_code, shred_path};
+    use crate::test_support::unique_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn shred_path_removes_tree_and_leaves_no_names_behind() {
+        let root = unique_temp_dir("rf-shred");
+        let target = root.join("secret");
+        fs::create_dir_all(target.join("inner")).expect("create dirs");
+        fs::write(target.join("a.txt"), vec![b'a'; 4096]).expect("write a");
+        fs::write(target.join("inner").join("b.txt"), b"b").expect("write b");
+
+        shred_path(&target, 2).expect("shred");
+
+        let leftovers: Vec<_> = fs::read_dir(&root).expect("read root").flatten().collect();
+        assert!(leftovers.is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shred_path_refuses_hard_linked_files() {
+        let root = unique_temp_dir("rf-shred-hardlink");
+        let target = root.join("secret.txt");
+        let other = root.join("other-name.txt");
+        fs::write(&target, b"keep me").expect("write target");
+        fs::hard_link(&target, &other).expect("hard link");
+
+        let err = shred_path(&target, 1).expect_err("hard-linked file");
+        assert_eq!(shred_error_code(&err), "hardlinked");
+        assert_eq!(fs::read(&target).expect("read target"), b"keep me");
+        assert_eq!(fs::read(&other).expect("read other"), b"keep me");
+
+        // A hard link deep inside a folder stops the shred before anything is overwritten.
+        let folder = root.join("folder");
+        fs::create_dir_all(folder.join("inner").join("deeper")).expect("create dirs");
+        fs::write(folder.join("a.txt"), b"first").expect("write a");
+        fs::write(folder.join("inner").join("z.txt"), b"last").expect("write z");
+        fs::hard_link(
+            &other,
+            folder.join("inner").join("deeper").join("linked.txt"),
+        )
+        .expect("hard link into folder");
+        let err = shred_path(&folder, 1).expect_err("hard link in folder");
+        assert_eq!(shred_error_code(&err), "hardlinked");
+        assert_eq!(fs::read(folder.join("a.txt")).expect("read a"), b"first");
+        assert_eq!(
+            fs::read(folder.join("inner").join("z.txt")).expect("read z"),
+            b"last"
+        );
+        let _ = fs::remove_dir_all(root);
+    }
+}
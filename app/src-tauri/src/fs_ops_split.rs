@@ -0,0 +1,507 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::fs_ops_preflight::{preflight_join, preflight_split};
+use crate::fs_ops_transfer_helpers::{
+    emit_progress, ensure_not_canceled, io_error_code, record_failure, reset_cancel_request,
+};
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{OpKind, OpStatus, OpSummary};
+use crate::utils::to_hex;
+
+const SPLIT_CHUNK_SIZE: usize = 1024 * 1024;
+const MANIFEST_EXT: &str = "sha256";
+
+/// Checksum mismatches are reported as `InvalidData` so they get their own error code.
+fn job_error_code(err: &io::Error) -> &'static str {
+    if err.kind() == io::ErrorKind::InvalidData {
+        "checksum_mismatch"
+    } else {
+        io_error_code(err)
+    }
+}
+
+fn manifest_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{name}.{MANIFEST_EXT}"))
+}
+
+/// Parts are `<name>.001`, `<name>.002`, ...; the width grows past three digits when needed.
+fn part_paths(dir: &Path, name: &str, count: u64) -> Vec<PathBuf> {
+    let width = count.to_string().len().max(3);
+    (1..=count)
+        .map(|index| dir.join(format!("{name}.{index:0width$}")))
+        .collect()
+}
+
+fn part_count(len: u64, part_size: u64) -> u64 {
+    len.div_ceil(part_size).max(1)
+}
+
+/// Copies up to `limit` bytes from `reader`, feeding every hasher on the way.
+fn copy_hashed(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    limit: u64,
+    hashers: &mut [&mut Sha256],
+    buf: &mut [u8],
+) -> io::Result<u64> {
+    let mut copied = 0u64;
+    while copied < limit {
+        ensure_not_canceled()?;
+        let want = (limit - copied).min(buf.len() as u64) as usize;
+        let read = reader.read(&mut buf[..want])?;
+        if read == 0 {
+            break;
+        }
+        for hasher in hashers.iter_mut() {
+            hasher.update(&buf[..read]);
+        }
+        writer.write_all(&buf[..read])?;
+        copied += read as u64;
+    }
+    Ok(copied)
+}
+
+fn write_parts(
+    source: &Path,
+    parts: &[PathBuf],
+    manifest: &Path,
+    part_size: u64,
+    on_part: &mut dyn FnMut(usize, &Path, OpStatus),
+) -> io::Result<()> {
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut reader = File::open(source)?;
+    let mut whole = Sha256::new();
+    let mut lines = Vec::with_capacity(parts.len() + 1);
+    let mut buf = vec![0u8; SPLIT_CHUNK_SIZE];
+    for (index, part) in parts.iter().enumerate() {
+        on_part(index, part, OpStatus::Start);
+        let mut part_hash = Sha256::new();
+        let mut writer = BufWriter::new(File::create(part)?);
+        copy_hashed(
+            &mut reader,
+            &mut writer,
+            part_size,
+            &mut [&mut part_hash, &mut whole],
+            &mut buf,
+        )?;
+        writer.flush()?;
+        let part_name = part
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        lines.push(format!("{}  {}", to_hex(&part_hash.finalize()), part_name));
+        on_part(index, part, OpStatus::Done);
+    }
+    // sha256sum-compatible; the last line is the original file.
+    lines.push(format!("{}  {}", to_hex(&whole.finalize()), name));
+    fs::write(manifest, lines.join("\n") + "\n")
+}
+
+/// Splits `source` into parts of `part_size` bytes inside `dest_dir`, next to a checksum
+/// manifest. Everything written is removed again if the split fails or is canceled.
+fn split_file(
+    source: &Path,
+    dest_dir: &Path,
+    part_size: u64,
+    on_part: &mut dyn FnMut(usize, &Path, OpStatus),
+) -> Result<Vec<PathBuf>, String> {
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "code=invalid_path; invalid path".to_string())?;
+    if !dest_dir.is_dir() {
+        return Err("code=destination_not_dir; destination is not a folder".to_string());
+    }
+    let len = fs::metadata(source).map(|meta| meta.len()).unwrap_or(0);
+    let parts = part_paths(dest_dir, &name, part_count(len, part_size.max(1)));
+    let manifest = manifest_path(dest_dir, &name);
+    let mut outputs = parts.clone();
+    outputs.push(manifest.clone());
+    preflight_split(source, part_size, &outputs)
+        .map_err(|err| format!("code={}; {}", err.code, err.message))?;
+    if let Err(err) = write_parts(source, &parts, &manifest, part_size, on_part) {
+        for path in &outputs {
+            let _ = fs::remove_file(path);
+        }
+        return Err(format!("code={}; {}", job_error_code(&err), err));
+    }
+    Ok(parts)
+}
+
+struct JoinManifest {
+    name: String,
+    parts: Vec<(PathBuf, String)>,
+    whole_hash: String,
+}
+
+/// A manifest entry must name a file next to the manifest; names that could reach
+/// outside that folder are refused, anything the file system allows otherwise is kept.
+fn entry_name(entry: &str) -> Option<&str> {
+    let mut components = Path::new(entry).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) if name == entry && !entry.contains(['/', '\\']) => {
+            Some(entry)
+        }
+        _ => None,
+    }
+}
+
+/// Reads a manifest written by `split_file`. Entries must be plain names in the manifest's
+/// folder, and the last entry must be the file named by the manifest itself.
+fn read_manifest(manifest: &Path) -> Result<JoinManifest, String> {
+    let invalid = |detail: &str| format!("code=invalid_manifest; {detail}");
+    let text = fs::read_to_string(manifest)
+        .map_err(|err| format!("code={}; {}", io_error_code(&err), err))?;
+    let file_name = manifest
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = file_name
+        .strip_suffix(&format!(".{MANIFEST_EXT}"))
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| invalid("manifest must be named <file>.sha256"))?
+        .to_string();
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let mut entries = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let (hash, entry) = line
+            .split_once("  ")
+            .ok_or_else(|| invalid("malformed line"))?;
+        let hash = hash.trim().to_ascii_lowercase();
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid("malformed checksum"));
+        }
+        let entry =
+            entry_name(entry).ok_or_else(|| invalid(&format!("bad entry name: {entry}")))?;
+        entries.push((entry.to_string(), hash));
+    }
+    let (last_name, whole_hash) = entries.pop().ok_or_else(|| invalid("manifest is empty"))?;
+    if last_name != name {
+        return Err(invalid("last entry must be the joined file"));
+    }
+    Ok(JoinManifest {
+        name,
+        parts: entries
+            .into_iter()
+            .map(|(entry, hash)| (dir.join(entry), hash))
+            .collect(),
+        whole_hash,
+    })
+}
+
+fn write_joined(
+    manifest: &JoinManifest,
+    temp: &Path,
+    on_part: &mut dyn FnMut(usize, &Path, OpStatus),
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(temp)?);
+    let mut whole = Sha256::new();
+    let mut buf = vec![0u8; SPLIT_CHUNK_SIZE];
+    for (index, (part, expected)) in manifest.parts.iter().enumerate() {
+        on_part(index, part, OpStatus::Start);
+        let mut part_hash = Sha256::new();
+        let mut reader = File::open(part)?;
+        copy_hashed(
+            &mut reader,
+            &mut writer,
+            u64::MAX,
+            &mut [&mut part_hash, &mut whole],
+            &mut buf,
+        )?;
+        if &to_hex(&part_hash.finalize()) != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("checksum mismatch: {}", part.display()),
+            ));
+        }
+        on_part(index, part, OpStatus::Done);
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    if to_hex(&whole.finalize()) != manifest.whole_hash {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("checksum mismatch: {}", manifest.name),
+        ));
+    }
+    Ok(())
+}
+
+/// Joins the parts listed in `manifest` into `dest_dir`. The result is written to a hidden
+/// temp file and only renamed into place once every checksum matched.
+fn join_file(
+    manifest_file: &Path,
+    dest_dir: &Path,
+    on_part: &mut dyn FnMut(usize, &Path, OpStatus),
+) -> Result<(PathBuf, usize), String> {
+    let manifest = read_manifest(manifest_file)?;
+    if !dest_dir.is_dir() {
+        return Err("code=destination_not_dir; destination is not a folder".to_string());
+    }
+    let target = dest_dir.join(&manifest.name);
+    let parts: Vec<PathBuf> = manifest
+        .parts
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect();
+    preflight_join(&parts, &target).map_err(|err| format!("code={}; {}", err.code, err.message))?;
+    let temp = dest_dir.join(format!(".{}.rf-join", manifest.name));
+    let result = write_joined(&manifest, &temp, on_part).and_then(|_| {
+        if target.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("already exists: {}", target.display()),
+            ));
+        }
+        fs::rename(&temp, &target)
+    });
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("code={}; {}", job_error_code(&err), err));
+    }
+    Ok((target, parts.len()))
+}
+
+fn resolve_dest_dir(destination: Option<String>, fallback: &Path) -> Result<PathBuf, String> {
+    match destination.filter(|value| !value.trim().is_empty()) {
+        Some(value) => resolve_legacy_path_for(&value, ProviderCapability::Copy)
+            .map_err(|err| format!("code={}; {}", err.code(), err)),
+        None => Ok(fallback.parent().unwrap_or(Path::new("")).to_path_buf()),
+    }
+}
+
+fn summary_for(total: usize, done: usize, item: &str, err: Option<&str>) -> OpSummary {
+    let mut failures = Vec::new();
+    if let Some(message) = err {
+        let code = message
+            .strip_prefix("code=")
+            .and_then(|rest| rest.split(';').next())
+            .unwrap_or("unknown");
+        record_failure(&mut failures, item, code, message);
+    }
+    OpSummary {
+        ok: done as u64,
+        failed: failures.len() as u64,
+        total: total as u64,
+        failures,
+    }
+}
+
+#[tauri::command]
+pub fn fs_split_file(
+    app: AppHandle,
+    path: String,
+    part_size: u64,
+    destination: Option<String>,
+) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let source = match resolve_legacy_path_for(&path, ProviderCapability::Copy) {
+        Ok(source) => source,
+        Err(err) => {
+            let message = format!("code={}; {}", err.code(), err);
+            crate::log_error("split", &path, "-", &message);
+            return Err(message);
+        }
+    };
+    let dest_dir = match resolve_dest_dir(destination, &source) {
+        Ok(dir) => dir,
+        Err(message) => {
+            crate::log_error("split", &path, "-", &message);
+            return Err(message);
+        }
+    };
+    let dest_text = dest_dir.to_string_lossy().to_string();
+    let len = fs::metadata(&source).map(|meta| meta.len()).unwrap_or(0);
+    let total = part_count(len, part_size.max(1)) as usize;
+    let mut done = 0usize;
+    let mut on_part = |index: usize, part: &Path, status: OpStatus| {
+        if matches!(status, OpStatus::Done) {
+            done += 1;
+        }
+        let part_text = part.to_string_lossy().to_string();
+        emit_progress(
+            &app,
+            OpKind::Split,
+            part_text,
+            index,
+            total,
+            status,
+            String::new(),
+        );
+    };
+    match split_file(&source, &dest_dir, part_size, &mut on_part) {
+        Ok(parts) => {
+            crate::log_event(
+                "SPLIT",
+                &path,
+                &dest_text,
+                &format!(
+                    "parts={}; part_size={}; ms={}",
+                    parts.len(),
+                    part_size,
+                    started.elapsed().as_millis()
+                ),
+            );
+            Ok(summary_for(total, parts.len(), &path, None))
+        }
+        Err(message) => {
+            crate::log_error("split", &path, &dest_text, &message);
+            let failed_index = done.min(total.saturating_sub(1));
+            emit_progress(
+                &app,
+                OpKind::Split,
+                path.clone(),
+                failed_index,
+                total,
+                OpStatus::Fail,
+                message.clone(),
+            );
+            // Parts written before the failure were removed again.
+            Ok(summary_for(total, 0, &path, Some(&message)))
+        }
+    }
+}
+
+#[tauri::command]
+pub fn fs_join_file(
+    app: AppHandle,
+    manifest: String,
+    destination: Option<String>,
+) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let manifest_file = match resolve_legacy_path_for(&manifest, ProviderCapability::Copy) {
+        Ok(path) => path,
+        Err(err) => {
+            let message = format!("code={}; {}", err.code(), err);
+            crate::log_error("join", &manifest, "-", &message);
+            return Err(message);
+        }
+    };
+    let dest_dir = match resolve_dest_dir(destination, &manifest_file) {
+        Ok(dir) => dir,
+        Err(message) => {
+            crate::log_error("join", &manifest, "-", &message);
+            return Err(message);
+        }
+    };
+    let dest_text = dest_dir.to_string_lossy().to_string();
+    let total = read_manifest(&manifest_file)
+        .map(|parsed| parsed.parts.len())
+        .unwrap_or(0);
+    let mut done = 0usize;
+    let mut on_part = |index: usize, part: &Path, status: OpStatus| {
+        if matches!(status, OpStatus::Done) {
+            done += 1;
+        }
+        let part_text = part.to_string_lossy().to_string();
+        emit_progress(
+            &app,
+            OpKind::Join,
+            part_text,
+            index,
+            total,
+            status,
+            String::new(),
+        );
+    };
+    match join_file(&manifest_file, &dest_dir, &mut on_part) {
+        Ok((target, parts)) => {
+            crate::log_event(
+                "JOIN",
+                &manifest,
+                &target.to_string_lossy(),
+                &format!("parts={}; ms={}", parts, started.elapsed().as_millis()),
+            );
+            Ok(summary_for(total, parts, &manifest, None))
+        }
+        Err(message) => {
+            crate::log_error("join", &manifest, &dest_text, &message);
+            emit_progress(
+                &app,
+                OpKind::Join,
+                manifest.clone(),
+                done.min(total.saturating_sub(1)),
+                total.max(1),
+                OpStatus::Fail,
+                message.clone(),
+            );
+            Ok(summary_for(total, 0, &manifest, Some(&message)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{join_file, split_file};
+    use crate::test_support::unique_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn split_and_join_round_trip_with_verification() {
+        let root = unique_temp_dir("rf-split");
+        let (parts_dir, out_dir) = (root.join("parts"), root.join("out"));
+        fs::create_dir_all(&parts_dir).expect("create parts dir");
+        fs::create_dir_all(&out_dir).expect("create out dir");
+        let source = root.join("big.bin");
+        let data: Vec<u8> = (0..2500u32).map(|n| (n % 251) as u8).collect();
+        fs::write(&source, &data).expect("write source");
+
+        let mut events = Vec::new();
+        let parts = split_file(&source, &parts_dir, 1000, &mut |index, _, _| {
+            events.push(index)
+        })
+        .expect("split");
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2], parts_dir.join("big.bin.003"));
+        assert_eq!(fs::metadata(&parts[2]).expect("part").len(), 500);
+        assert_eq!(events, vec![0, 0, 1, 1, 2, 2]);
+        let manifest = parts_dir.join("big.bin.sha256");
+        let err = split_file(&source, &parts_dir, 1000, &mut |_, _, _| {}).expect_err("exists");
+        assert!(err.starts_with("code=already_exists;"));
+
+        let (joined, count) = join_file(&manifest, &out_dir, &mut |_, _, _| {}).expect("join");
+        assert_eq!(count, 3);
+        assert_eq!(fs::read(&joined).expect("read joined"), data);
+
+        fs::remove_file(&joined).expect("remove joined");
+        fs::write(&parts[1], vec![0u8; 1000]).expect("corrupt part");
+        let err = join_file(&manifest, &out_dir, &mut |_, _, _| {}).expect_err("corrupt");
+        assert!(err.starts_with("code=checksum_mismatch;"));
+        assert!(fs::read_dir(&out_dir).expect("read out").next().is_none());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn manifest_keeps_plain_names_and_refuses_escapes() {
+        let root = unique_temp_dir("rf-split-names");
+        let (parts_dir, out_dir) = (root.join("parts"), root.join("out"));
+        fs::create_dir_all(&parts_dir).expect("create parts dir");
+        fs::create_dir_all(&out_dir).expect("create out dir");
+        let source = root.join("backup 12:00.tar");
+        fs::write(&source, vec![7u8; 1500]).expect("write source");
+
+        split_file(&source, &parts_dir, 1000, &mut |_, _, _| {}).expect("split");
+        let manifest = parts_dir.join("backup 12:00.tar.sha256");
+        let (joined, count) = join_file(&manifest, &out_dir, &mut |_, _, _| {}).expect("join");
+        assert_eq!((joined, count), (out_dir.join("backup 12:00.tar"), 2));
+
+        let text = fs::read_to_string(&manifest).expect("read manifest");
+        let escaping = text.replacen("backup 12:00.tar.001", "../backup 12:00.tar.001", 1);
+        fs::write(&manifest, escaping).expect("rewrite manifest");
+        let err = join_file(&manifest, &out_dir, &mut |_, _, _| {}).expect_err("escape");
+        assert!(err.starts_with("code=invalid_manifest;"), "{err}");
+        let _ = fs::remove_dir_all(root);
+    }
+}
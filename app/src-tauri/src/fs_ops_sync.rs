@@ -0,0 +1,838 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::fs_ops_preflight::preflight_sync;
+use crate::fs_ops_transfer_helpers::{
+    cancel_requested, copy_recursively, emit_progress, io_error_code, record_failure,
+    remove_recursively, reset_cancel_request,
+};
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{
+    OpKind, OpStatus, OpSummary, SyncAction, SyncActionKind, SyncCompare, SyncDirection, SyncMode,
+    SyncOptions, SyncPlan,
+};
+use crate::utils::{mtimes_match, sha256_file, to_hex, MTIME_TOLERANCE};
+
+struct NodeInfo {
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+fn scan_into(dir: &Path, prefix: &str, out: &mut BTreeMap<String, NodeInfo>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let meta = fs::symlink_metadata(entry.path())?;
+        // Links are neither followed nor synced.
+        if meta.file_type().is_symlink() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        out.insert(
+            rel.clone(),
+            NodeInfo {
+                is_dir: meta.is_dir(),
+                size: if meta.is_dir() { 0 } else { meta.len() },
+                modified: meta.modified().ok(),
+            },
+        );
+        if meta.is_dir() {
+            scan_into(&entry.path(), &rel, out)?;
+        }
+    }
+    Ok(())
+}
+
+/// What a two-way sync left identical on both sides, by relative path, so the next plan
+/// can tell which side changed since.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct SyncSnapshot {
+    entries: BTreeMap<String, SnapshotNode>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SnapshotNode {
+    is_dir: bool,
+    size: u64,
+    /// Milliseconds since the epoch, per side.
+    source_mtime: Option<u64>,
+    target_mtime: Option<u64>,
+}
+
+fn to_millis(time: Option<SystemTime>) -> Option<u64> {
+    let millis = time?.duration_since(UNIX_EPOCH).ok()?.as_millis();
+    u64::try_from(millis).ok()
+}
+
+fn from_millis(millis: Option<u64>) -> Option<SystemTime> {
+    UNIX_EPOCH.checked_add(Duration::from_millis(millis?))
+}
+
+impl SyncSnapshot {
+    /// True when `node` on the given side is new or differs from what was last synced.
+    fn changed(&self, rel: &str, node: &NodeInfo, in_source: bool) -> bool {
+        let Some(last) = self.entries.get(rel) else {
+            return true;
+        };
+        let mtime = if in_source {
+            last.source_mtime
+        } else {
+            last.target_mtime
+        };
+        node.is_dir != last.is_dir
+            || (!node.is_dir
+                && (node.size != last.size || !mtimes_match(node.modified, from_millis(mtime))))
+    }
+
+    /// True when `rel` or anything below it changed on the given side.
+    fn subtree_changed(
+        &self,
+        tree: &BTreeMap<String, NodeInfo>,
+        rel: &str,
+        in_source: bool,
+    ) -> bool {
+        let prefix = format!("{}/", rel);
+        tree.range(rel.to_string()..)
+            .take_while(|(key, _)| key.as_str() == rel || key.starts_with(&prefix))
+            .any(|(key, node)| self.changed(key, node, in_source))
+    }
+}
+
+fn is_under(rel: &str, root: &str) -> bool {
+    rel == root
+        || rel
+            .strip_prefix(root)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Snapshots live in the config directory, one file per source/target pair.
+fn snapshot_path(source: &Path, target: &Path) -> PathBuf {
+    use sha2::{Digest, Sha256};
+    let key = format!("{}\n{}", source.to_string_lossy(), target.to_string_lossy());
+    let name = to_hex(&Sha256::digest(key.as_bytes()));
+    crate::config::config_path()
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("sync_snapshots")
+        .join(format!("{}.json", name))
+}
+
+fn load_snapshot(path: &Path) -> Option<SyncSnapshot> {
+    let text = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn save_snapshot(path: &Path, snapshot: &SyncSnapshot) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let text = serde_json::to_string(snapshot).map_err(io::Error::other)?;
+    // Write next to the snapshot and swap in, so a crash never leaves a half-written file.
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, text)?;
+    fs::rename(&tmp_path, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp_path);
+    })
+}
+
+/// Records what is identical on both sides after a sync. Paths in `pending`, whose
+/// actions or conflicts were not carried out, keep their entries from `last`.
+fn record_snapshot(
+    source: &Path,
+    target: &Path,
+    compare: SyncCompare,
+    pending: &[&str],
+    last: Option<&SyncSnapshot>,
+) -> io::Result<SyncSnapshot> {
+    let left = scan_tree(source)?;
+    let right = scan_tree(target)?;
+    let is_pending = |rel: &str| pending.iter().any(|root| is_under(rel, root));
+    let mut entries = BTreeMap::new();
+    for (rel, l) in &left {
+        let Some(r) = right.get(rel) else {
+            continue;
+        };
+        // Hash plans already compared same-sized files by content.
+        let same = l.is_dir == r.is_dir
+            && (l.is_dir
+                || (l.size == r.size
+                    && (compare == SyncCompare::Hash || mtimes_match(l.modified, r.modified))));
+        if same && !is_pending(rel) {
+            entries.insert(
+                rel.clone(),
+                SnapshotNode {
+                    is_dir: l.is_dir,
+                    size: l.size,
+                    source_mtime: to_millis(l.modified),
+                    target_mtime: to_millis(r.modified),
+                },
+            );
+        }
+    }
+    if let Some(last) = last {
+        for (rel, node) in &last.entries {
+            if is_pending(rel) {
+                entries.insert(rel.clone(), node.clone());
+            }
+        }
+    }
+    Ok(SyncSnapshot { entries })
+}
+
+fn scan_tree(root: &Path) -> io::Result<BTreeMap<String, NodeInfo>> {
+    let mut out = BTreeMap::new();
+    if root.is_dir() {
+        scan_into(root, "", &mut out)?;
+    }
+    Ok(out)
+}
+
+fn rel_to_path(root: &Path, rel: &str) -> PathBuf {
+    rel.split('/')
+        .fold(root.to_path_buf(), |acc, part| acc.join(part))
+}
+
+/// Rejects plan entries that could point outside the sync roots.
+fn is_safe_rel_path(rel: &str) -> bool {
+    !rel.is_empty()
+        && Path::new(rel)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Orders two modification times; `None` means they are equal within the tolerance.
+fn newer_side(left: Option<SystemTime>, right: Option<SystemTime>) -> Option<SyncDirection> {
+    let (left, right) = (left?, right?);
+    match left.duration_since(right) {
+        Ok(diff) if diff > MTIME_TOLERANCE => Some(SyncDirection::ToTarget),
+        Ok(_) => None,
+        Err(err) if err.duration() > MTIME_TOLERANCE => Some(SyncDirection::ToSource),
+        Err(_) => None,
+    }
+}
+
+fn files_match(
+    left: &NodeInfo,
+    right: &NodeInfo,
+    left_path: &Path,
+    right_path: &Path,
+    compare: SyncCompare,
+) -> io::Result<bool> {
+    if left.size != right.size {
+        return Ok(false);
+    }
+    match compare {
+        SyncCompare::Meta => Ok(mtimes_match(left.modified, right.modified)),
+        SyncCompare::Hash => Ok(sha256_file(left_path)? == sha256_file(right_path)?),
+    }
+}
+
+fn subtree_size(tree: &BTreeMap<String, NodeInfo>, rel: &str) -> u64 {
+    let prefix = format!("{}/", rel);
+    tree.range(prefix.clone()..)
+        .take_while(|(key, _)| key.starts_with(&prefix))
+        .map(|(_, node)| node.size)
+        .sum()
+}
+
+fn action(
+    kind: SyncActionKind,
+    direction: SyncDirection,
+    rel: &str,
+    node: &NodeInfo,
+    size: u64,
+    reason: &str,
+) -> SyncAction {
+    SyncAction {
+        kind,
+        direction,
+        rel_path: rel.to_string(),
+        is_dir: node.is_dir,
+        size,
+        reason: reason.to_string(),
+    }
+}
+
+/// Plans a sync. Two-way plans use `snapshot`, the state after the last sync of this pair,
+/// to propagate deletions and to flag paths changed on both sides; without one the newer
+/// side wins.
+pub(crate) fn build_sync_plan(
+    source: &Path,
+    target: &Path,
+    options: &SyncOptions,
+    snapshot: Option<&SyncSnapshot>,
+) -> io::Result<SyncPlan> {
+    let snapshot = snapshot.filter(|_| options.mode == SyncMode::TwoWay);
+    let left = scan_tree(source)?;
+    let right = scan_tree(target)?;
+    let mut keys: Vec<&String> = left.keys().chain(right.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut actions = Vec::new();
+    let mut conflicts = Vec::new();
+    // Directories whose whole subtree is already handled by one action.
+    let mut covered: HashSet<String> = HashSet::new();
+    for rel in keys {
+        let under_covered = rel
+            .match_indices('/')
+            .any(|(idx, _)| covered.contains(&rel[..idx]));
+        if under_covered {
+            continue;
+        }
+        let size_of = |tree: &BTreeMap<String, NodeInfo>, node: &NodeInfo| {
+            if node.is_dir {
+                subtree_size(tree, rel)
+            } else {
+                node.size
+            }
+        };
+        // A path synced last time and now gone on one side was deleted there.
+        let synced_before = snapshot.filter(|snapshot| snapshot.entries.contains_key(rel.as_str()));
+        match (left.get(rel), right.get(rel)) {
+            (Some(node), None) | (None, Some(node)) if synced_before.is_some() => {
+                let in_source = left.contains_key(rel);
+                let (tree, kept, gone) = if in_source {
+                    (&left, "source", "target")
+                } else {
+                    (&right, "target", "source")
+                };
+                let size = size_of(tree, node);
+                if synced_before.is_some_and(|s| s.subtree_changed(tree, rel, in_source)) {
+                    conflicts.push(action(
+                        SyncActionKind::Conflict,
+                        SyncDirection::ToTarget,
+                        rel,
+                        node,
+                        size,
+                        &format!("changed in {}, deleted in {}", kept, gone),
+                    ));
+                } else {
+                    // Delete on the side that still has it.
+                    let direction = if in_source {
+                        SyncDirection::ToSource
+                    } else {
+                        SyncDirection::ToTarget
+                    };
+                    let reason = format!("deleted in {}", gone);
+                    actions.push(action(
+                        SyncActionKind::Delete,
+                        direction,
+                        rel,
+                        node,
+                        size,
+                        &reason,
+                    ));
+                }
+                if node.is_dir {
+                    covered.insert(rel.clone());
+                }
+            }
+            (Some(l), None) => {
+                actions.push(action(
+                    SyncActionKind::Copy,
+                    SyncDirection::ToTarget,
+                    rel,
+                    l,
+                    size_of(&left, l),
+                    "missing in target",
+                ));
+                if l.is_dir {
+                    covered.insert(rel.clone());
+                }
+            }
+            (None, Some(r)) => {
+                let planned = match options.mode {
+                    SyncMode::Mirror => Some((
+                        SyncActionKind::Delete,
+                        SyncDirection::ToTarget,
+                        "not in source",
+                    )),
+                    SyncMode::TwoWay => Some((
+                        SyncActionKind::Copy,
+                        SyncDirection::ToSource,
+                        "missing in source",
+                    )),
+                    SyncMode::Update => None,
+                };
+                if let Some((kind, direction, reason)) = planned {
+                    actions.push(action(kind, direction, rel, r, size_of(&right, r), reason));
+                }
+                if r.is_dir {
+                    covered.insert(rel.clone());
+                }
+            }
+            (Some(l), Some(r)) if l.is_dir && r.is_dir => {}
+            (Some(l), Some(r)) if l.is_dir != r.is_dir => {
+                if options.mode == SyncMode::Mirror {
+                    actions.push(action(
+                        SyncActionKind::Update,
+                        SyncDirection::ToTarget,
+                        rel,
+                        l,
+                        size_of(&left, l),
+                        "type differs",
+                    ));
+                } else {
+                    conflicts.push(action(
+                        SyncActionKind::Conflict,
+                        SyncDirection::ToTarget,
+                        rel,
+                        l,
+                        size_of(&left, l),
+                        "file and folder with the same name",
+                    ));
+                }
+                covered.insert(rel.clone());
+            }
+            (Some(l), Some(r)) => {
+                let same = files_match(
+                    l,
+                    r,
+                    &rel_to_path(source, rel),
+                    &rel_to_path(target, rel),
+                    options.compare,
+                )?;
+                if same {
+                    continue;
+                }
+                let changed = snapshot.map(|snapshot| {
+                    (
+                        snapshot.changed(rel, l, true),
+                        snapshot.changed(rel, r, false),
+                    )
+                });
+                let newer = match changed {
+                    // Changed on both sides since the last sync: nobody can pick a winner.
+                    Some((true, true)) => None,
+                    Some((true, false)) => Some(SyncDirection::ToTarget),
+                    Some((false, true)) => Some(SyncDirection::ToSource),
+                    Some((false, false)) | None => newer_side(l.modified, r.modified),
+                };
+                match (options.mode, newer) {
+                    (SyncMode::Mirror, _) | (SyncMode::Update, Some(SyncDirection::ToTarget)) => {
+                        actions.push(action(
+                            SyncActionKind::Update,
+                            SyncDirection::ToTarget,
+                            rel,
+                            l,
+                            l.size,
+                            "differs",
+                        ));
+                    }
+                    (SyncMode::Update, _) => {}
+                    (SyncMode::TwoWay, Some(SyncDirection::ToTarget)) => {
+                        actions.push(action(
+                            SyncActionKind::Update,
+                            SyncDirection::ToTarget,
+                            rel,
+                            l,
+                            l.size,
+                            "newer in source",
+                        ));
+                    }
+                    (SyncMode::TwoWay, Some(SyncDirection::ToSource)) => {
+                        actions.push(action(
+                            SyncActionKind::Update,
+                            SyncDirection::ToSource,
+                            rel,
+                            r,
+                            r.size,
+                            "newer in target",
+                        ));
+                    }
+                    (SyncMode::TwoWay, None) => {
+                        conflicts.push(action(
+                            SyncActionKind::Conflict,
+                            SyncDirection::ToTarget,
+                            rel,
+                            l,
+                            l.size,
+                            "both sides changed",
+                        ));
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    let bytes = actions
+        .iter()
+        .filter(|a| a.kind != SyncActionKind::Delete)
+        .map(|a| a.size)
+        .sum();
+    Ok(SyncPlan {
+        source: source.to_string_lossy().to_string(),
+        target: target.to_string_lossy().to_string(),
+        options: options.clone(),
+        actions,
+        conflicts,
+        bytes,
+    })
+}
+
+/// Copies modification times from `from` onto the copied tree at `to`, so the next
+/// size/mtime comparison sees the files as identical.
+fn copy_mtimes(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(from)?;
+    if meta.is_dir() {
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_mtimes(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    if let Ok(modified) = meta.modified() {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(to)?
+            .set_modified(modified)?;
+    }
+    Ok(())
+}
+
+fn apply_action(source: &Path, target: &Path, action: &SyncAction) -> io::Result<()> {
+    let (from_root, to_root) = match action.direction {
+        SyncDirection::ToTarget => (source, target),
+        SyncDirection::ToSource => (target, source),
+    };
+    let from = rel_to_path(from_root, &action.rel_path);
+    let to = rel_to_path(to_root, &action.rel_path);
+    match action.kind {
+        SyncActionKind::Delete => remove_recursively(&to),
+        SyncActionKind::Copy | SyncActionKind::Update => {
+            if !from.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "source changed since the plan was made",
+                ));
+            }
+            if to.exists() && (to.is_dir() || from.is_dir()) {
+                remove_recursively(&to)?;
+            }
+            copy_recursively(&from, &to)?;
+            copy_mtimes(&from, &to)
+        }
+        SyncActionKind::Conflict => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "conflicts are not executed",
+        )),
+    }
+}
+
+fn resolve_sync_roots(source: &str, target: &str) -> Result<(PathBuf, PathBuf), String> {
+    let resolve = |path: &str, capability| {
+        resolve_legacy_path_for(path, capability).map_err(|err| {
+            let msg = format!("code={}; {}", err.code(), err);
+            crate::log_error("sync", source, target, &msg);
+            msg
+        })
+    };
+    let source_path = resolve(source, ProviderCapability::Copy)?;
+    let target_path = resolve(target, ProviderCapability::Create)?;
+    if let Err(err) = preflight_sync(
+        &source_path.to_string_lossy(),
+        &target_path.to_string_lossy(),
+    ) {
+        let msg = format!("code={}; {}", err.code, err.message);
+        crate::log_error("sync", source, target, &msg);
+        return Err(msg);
+    }
+    Ok((source_path, target_path))
+}
+
+#[tauri::command]
+pub fn fs_sync_plan(
+    source: String,
+    target: String,
+    options: Option<SyncOptions>,
+) -> Result<SyncPlan, String> {
+    let options = options.unwrap_or_default();
+    let (source_path, target_path) = resolve_sync_roots(&source, &target)?;
+    let snapshot = load_snapshot(&snapshot_path(&source_path, &target_path));
+    build_sync_plan(&source_path, &target_path, &options, snapshot.as_ref()).map_err(|err| {
+        let msg = format!("code={}; {}", io_error_code(&err), err);
+        crate::log_error("sync", &source, &target, &msg);
+        msg
+    })
+}
+
+/// Executes a plan from `fs_sync_plan`. Deletions run first to free space on the target.
+/// A two-way sync then records the new snapshot for the pair.
+#[tauri::command]
+pub fn fs_sync(app: AppHandle, plan: SyncPlan) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    reset_cancel_request();
+    let (source, target) = resolve_sync_roots(&plan.source, &plan.target)?;
+    fs::create_dir_all(&target).map_err(|e| format!("code={}; {}", io_error_code(&e), e))?;
+    let mut ordered: Vec<&SyncAction> = plan
+        .actions
+        .iter()
+        .filter(|a| a.kind == SyncActionKind::Delete)
+        .collect();
+    ordered.extend(
+        plan.actions
+            .iter()
+            .filter(|a| a.kind != SyncActionKind::Delete),
+    );
+    let total = ordered.len();
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    let mut done: HashSet<&str> = HashSet::new();
+    for (index, action) in ordered.into_iter().enumerate() {
+        if cancel_requested() {
+            break;
+        }
+        emit_progress(
+            &app,
+            OpKind::Sync,
+            action.rel_path.clone(),
+            index,
+            total,
+            OpStatus::Start,
+            String::new(),
+        );
+        let result = if is_safe_rel_path(&action.rel_path) {
+            apply_action(&source, &target, action)
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid path"))
+        };
+        if let Err(err) = result {
+            let code = io_error_code(&err);
+            let message = err.to_string();
+            crate::log_error("sync", &action.rel_path, &plan.target, &message);
+            failed += 1;
+            record_failure(&mut failures, &action.rel_path, code, &message);
+            emit_progress(
+                &app,
+                OpKind::Sync,
+                action.rel_path.clone(),
+                index,
+                total,
+                OpStatus::Fail,
+                message,
+            );
+            if cancel_requested() {
+                break;
+            }
+            continue;
+        }
+        ok += 1;
+        done.insert(&action.rel_path);
+        emit_progress(
+            &app,
+            OpKind::Sync,
+            action.rel_path.clone(),
+            index,
+            total,
+            OpStatus::Done,
+            String::new(),
+        );
+    }
+    if plan.options.mode == SyncMode::TwoWay {
+        let pending: Vec<&str> = plan
+            .actions
+            .iter()
+            .chain(&plan.conflicts)
+            .map(|a| a.rel_path.as_str())
+            .filter(|rel| !done.contains(rel))
+            .collect();
+        let path = snapshot_path(&source, &target);
+        let last = load_snapshot(&path);
+        let result = record_snapshot(
+            &source,
+            &target,
+            plan.options.compare,
+            &pending,
+            last.as_ref(),
+        )
+        .and_then(|snapshot| save_snapshot(&path, &snapshot));
+        if let Err(err) = result {
+            crate::log_error(
+                "sync_snapshot",
+                &plan.source,
+                &plan.target,
+                &err.to_string(),
+            );
+        }
+    }
+    crate::log_event(
+        "SYNC",
+        &plan.source,
+        &plan.target,
+        &format!(
+            "count={}; failed={}; conflicts={}; ms={}",
+            ok,
+            failed,
+            plan.conflicts.len(),
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_action, build_sync_plan, record_snapshot};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{SyncActionKind, SyncCompare, SyncDirection, SyncMode, SyncOptions};
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    fn set_mtime(path: &PathBuf, secs_ago: u64) {
+        let time = SystemTime::now() - Duration::from_secs(secs_ago);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .expect("open")
+            .set_modified(time)
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn mirror_plan_copies_updates_and_deletes_then_converges() {
+        let root = unique_temp_dir("rf-sync-mirror");
+        let (src, dst) = (root.join("src"), root.join("dst"));
+        fs::create_dir_all(src.join("sub")).expect("create src");
+        fs::create_dir_all(dst.join("old")).expect("create dst");
+        fs::write(src.join("sub").join("new.txt"), b"new").expect("write new");
+        fs::write(src.join("same.txt"), b"same").expect("write same");
+        fs::write(dst.join("same.txt"), b"diff").expect("write changed");
+        fs::write(dst.join("old").join("gone.txt"), b"gone").expect("write gone");
+        fs::write(dst.join("old-b.txt"), b"b").expect("write sibling");
+        set_mtime(&dst.join("same.txt"), 3600);
+
+        let options = SyncOptions {
+            mode: SyncMode::Mirror,
+            ..SyncOptions::default()
+        };
+        let plan = build_sync_plan(&src, &dst, &options, None).expect("plan");
+        let kinds: Vec<(SyncActionKind, &str)> = plan
+            .actions
+            .iter()
+            .map(|a| (a.kind, a.rel_path.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (SyncActionKind::Delete, "old"),
+                (SyncActionKind::Delete, "old-b.txt"),
+                (SyncActionKind::Update, "same.txt"),
+                (SyncActionKind::Copy, "sub"),
+            ]
+        );
+        for action in &plan.actions {
+            apply_action(&src, &dst, action).expect("apply");
+        }
+        let again = build_sync_plan(&src, &dst, &options, None).expect("plan again");
+        assert!(again.actions.is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn two_way_plan_reports_conflicts_and_update_mode_never_deletes() {
+        let root = unique_temp_dir("rf-sync-two-way");
+        let (src, dst) = (root.join("src"), root.join("dst"));
+        fs::create_dir_all(&src).expect("create src");
+        fs::create_dir_all(&dst).expect("create dst");
+        fs::write(src.join("both.txt"), b"left").expect("write left");
+        fs::write(dst.join("both.txt"), b"right!").expect("write right");
+        fs::write(dst.join("only.txt"), b"only").expect("write only");
+
+        let two_way = SyncOptions {
+            mode: SyncMode::TwoWay,
+            ..SyncOptions::default()
+        };
+        let plan = build_sync_plan(&src, &dst, &two_way, None).expect("plan");
+        assert_eq!(plan.conflicts.len(), 1);
+        assert_eq!(plan.conflicts[0].rel_path, "both.txt");
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].direction, SyncDirection::ToSource);
+
+        let update = SyncOptions {
+            mode: SyncMode::Update,
+            ..SyncOptions::default()
+        };
+        let plan = build_sync_plan(&src, &dst, &update, None).expect("plan");
+        assert!(plan.actions.is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn two_way_snapshot_propagates_deletes_and_flags_changes_on_both_sides() {
+        let root = unique_temp_dir("rf-sync-snapshot");
+        let (src, dst) = (root.join("src"), root.join("dst"));
+        for dir in [&src, &dst] {
+            fs::create_dir_all(dir.join("folder")).expect("create dir");
+            for name in ["both.txt", "gone.txt", "edited.txt", "folder/in.txt"] {
+                fs::write(dir.join(name), b"v1").expect("write");
+                set_mtime(&dir.join(name), 3600);
+            }
+        }
+        let snapshot = record_snapshot(&src, &dst, SyncCompare::Meta, &[], None).expect("snapshot");
+
+        fs::write(src.join("both.txt"), b"source v2").expect("edit source");
+        fs::write(dst.join("both.txt"), b"target v2").expect("edit target");
+        set_mtime(&dst.join("both.txt"), 600);
+        fs::remove_file(dst.join("gone.txt")).expect("delete in target");
+        fs::remove_file(src.join("edited.txt")).expect("delete in source");
+        fs::write(dst.join("edited.txt"), b"target v2").expect("edit deleted");
+        fs::remove_dir_all(src.join("folder")).expect("delete folder");
+
+        let two_way = SyncOptions {
+            mode: SyncMode::TwoWay,
+            ..SyncOptions::default()
+        };
+        let plan = build_sync_plan(&src, &dst, &two_way, Some(&snapshot)).expect("plan");
+        let actions: Vec<(SyncActionKind, SyncDirection, &str)> = plan
+            .actions
+            .iter()
+            .map(|a| (a.kind, a.direction, a.rel_path.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (SyncActionKind::Delete, SyncDirection::ToTarget, "folder"),
+                (SyncActionKind::Delete, SyncDirection::ToSource, "gone.txt"),
+            ]
+        );
+        let conflicts: Vec<(&str, &str)> = plan
+            .conflicts
+            .iter()
+            .map(|a| (a.rel_path.as_str(), a.reason.as_str()))
+            .collect();
+        assert_eq!(
+            conflicts,
+            vec![
+                ("both.txt", "both sides changed"),
+                ("edited.txt", "changed in target, deleted in source"),
+            ]
+        );
+
+        for action in &plan.actions {
+            apply_action(&src, &dst, action).expect("apply");
+        }
+        assert!(!src.join("gone.txt").exists());
+        assert!(!dst.join("folder").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+}
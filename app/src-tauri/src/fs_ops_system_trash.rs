@@ -0,0 +1,340 @@
+use std::ffi::OsString;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_mutate_helpers::record_failure;
+use crate::types::{EntryType, OpSummary, SortKey, SortOrder, SystemTrashEntry};
+use crate::utils::system_time_to_rfc3339;
+
+#[cfg(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+))]
+mod platform {
+    pub use trash::os_limited::{list, metadata, purge_all, restore_all};
+}
+
+#[cfg(not(any(
+    target_os = "windows",
+    all(
+        unix,
+        not(target_os = "macos"),
+        not(target_os = "ios"),
+        not(target_os = "android")
+    )
+)))]
+mod platform {
+    use trash::{Error, TrashItem, TrashItemMetadata};
+
+    fn unsupported() -> Error {
+        Error::Unknown {
+            description: "system trash browsing is not supported on this platform".to_string(),
+        }
+    }
+
+    pub fn list() -> Result<Vec<TrashItem>, Error> {
+        Err(unsupported())
+    }
+
+    pub fn metadata(_item: &TrashItem) -> Result<TrashItemMetadata, Error> {
+        Err(unsupported())
+    }
+
+    pub fn purge_all<I>(_items: I) -> Result<(), Error>
+    where
+        I: IntoIterator,
+    {
+        Err(unsupported())
+    }
+
+    pub fn restore_all<I>(_items: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = trash::TrashItem>,
+    {
+        Err(unsupported())
+    }
+}
+
+fn trash_error_code(err: &trash::Error) -> &'static str {
+    match err {
+        trash::Error::RestoreCollision { .. } | trash::Error::RestoreTwins { .. } => "conflict",
+        trash::Error::CouldNotAccess { .. } => "permission_denied",
+        _ => "io_error",
+    }
+}
+
+fn trash_error_message(err: &trash::Error) -> String {
+    match err {
+        trash::Error::RestoreCollision { path, .. } => {
+            format!("target already exists: {}", path.display())
+        }
+        trash::Error::RestoreTwins { path, .. } => {
+            format!("multiple items share the original path: {}", path.display())
+        }
+        _ => err.to_string(),
+    }
+}
+
+fn format_trash_error(err: &trash::Error) -> String {
+    format!(
+        "code={}; {}",
+        trash_error_code(err),
+        trash_error_message(err)
+    )
+}
+
+fn entry_from_trash_item(item: &trash::TrashItem) -> SystemTrashEntry {
+    let size = platform::metadata(item).ok().map(|meta| meta.size);
+    entry_from_trash_parts(item, size)
+}
+
+/// Builds the entry from an item and its size, `None` when the metadata was unreadable.
+fn entry_from_trash_parts(
+    item: &trash::TrashItem,
+    size: Option<trash::TrashItemSize>,
+) -> SystemTrashEntry {
+    let (entry_type, size) = match size {
+        Some(trash::TrashItemSize::Entries(_)) => (EntryType::Dir, 0),
+        Some(trash::TrashItemSize::Bytes(bytes)) => (EntryType::File, bytes),
+        None => (EntryType::File, 0),
+    };
+    let ext = match entry_type {
+        EntryType::Dir => String::new(),
+        EntryType::File => std::path::Path::new(&item.name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| format!(".{}", e))
+            .unwrap_or_default(),
+    };
+    let deleted = u64::try_from(item.time_deleted)
+        .ok()
+        .and_then(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)));
+    SystemTrashEntry {
+        id: item.id.to_string_lossy().to_string(),
+        name: item.name.clone(),
+        path: item.original_path().to_string_lossy().to_string(),
+        original_parent: item.original_parent.to_string_lossy().to_string(),
+        entry_type,
+        size,
+        deleted_at: system_time_to_rfc3339(deleted),
+        ext,
+    }
+}
+
+fn sort_trash_entries(entries: &mut [SystemTrashEntry], sort_key: &str, sort_order: &str) {
+    let key = SortKey::parse(sort_key);
+    let order = SortOrder::parse(sort_order);
+    entries.sort_by(|a, b| {
+        let cmp = match key {
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Type => a
+                .ext
+                .to_lowercase()
+                .cmp(&b.ext.to_lowercase())
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())),
+            // Deletion time takes the place of the modified column.
+            SortKey::Modified | SortKey::Unknown => a.deleted_at.cmp(&b.deleted_at),
+        };
+        match order {
+            SortOrder::Desc => cmp.reverse(),
+            SortOrder::Asc | SortOrder::Unknown => cmp,
+        }
+    });
+}
+
+/// Picks the trash items matching `ids`, recording unknown ids as failures.
+fn select_items(
+    ids: &[String],
+    failures: &mut Vec<crate::types::OpFailure>,
+) -> Result<Vec<trash::TrashItem>, String> {
+    let all = platform::list().map_err(|err| format_trash_error(&err))?;
+    let mut selected = Vec::with_capacity(ids.len());
+    for id in ids {
+        let wanted = OsString::from(id);
+        match all.iter().find(|item| item.id == wanted) {
+            Some(item) => selected.push(item.clone()),
+            None => record_failure(failures, id, "not_found", "item is no longer in the trash"),
+        }
+    }
+    Ok(selected)
+}
+
+#[tauri::command]
+pub fn system_trash_list(
+    sort_key: Option<String>,
+    sort_order: Option<String>,
+) -> Result<Vec<SystemTrashEntry>, String> {
+    let items = platform::list().map_err(|err| {
+        let msg = format_trash_error(&err);
+        crate::log_error("system_trash_list", "-", "-", &msg);
+        msg
+    })?;
+    let mut entries: Vec<SystemTrashEntry> = items.iter().map(entry_from_trash_item).collect();
+    sort_trash_entries(
+        &mut entries,
+        sort_key.as_deref().unwrap_or("modified"),
+        sort_order.as_deref().unwrap_or("desc"),
+    );
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn system_trash_restore(ids: Vec<String>) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    if ids.is_empty() {
+        return Err(format_error(AppErrorKind::InvalidPath, "no items"));
+    }
+    let total = ids.len();
+    let mut failures = Vec::new();
+    let items = select_items(&ids, &mut failures)?;
+    let mut ok = 0u64;
+    // One item at a time so a collision only fails that item.
+    for item in items {
+        let original = item.original_path().to_string_lossy().to_string();
+        match platform::restore_all([item]) {
+            Ok(()) => {
+                crate::log_event(
+                    "TRASH_RESTORE",
+                    &original,
+                    "-",
+                    &format!("count={}; ms={}", total, started.elapsed().as_millis()),
+                );
+                ok += 1;
+            }
+            Err(err) => {
+                let msg = format_trash_error(&err);
+                crate::log_error("system_trash_restore", &original, "-", &msg);
+                record_failure(
+                    &mut failures,
+                    &original,
+                    trash_error_code(&err),
+                    &trash_error_message(&err),
+                );
+            }
+        }
+    }
+    Ok(OpSummary {
+        ok,
+        failed: failures.len() as u64,
+        total: total as u64,
+        failures,
+    })
+}
+
+/// Permanently deletes the given trash items, or empties the trash when `ids` is `None`.
+#[tauri::command]
+pub fn system_trash_purge(ids: Option<Vec<String>>) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    let mut failures = Vec::new();
+    let items = match &ids {
+        Some(ids) => select_items(ids, &mut failures)?,
+        None => platform::list().map_err(|err| format_trash_error(&err))?,
+    };
+    let total = ids.as_ref().map(|ids| ids.len()).unwrap_or(items.len());
+    let mut ok = 0u64;
+    for item in items {
+        let original = item.original_path().to_string_lossy().to_string();
+        match platform::purge_all([&item]) {
+            Ok(()) => ok += 1,
+            Err(err) => {
+                let msg = format_trash_error(&err);
+                crate::log_error("system_trash_purge", &original, "-", &msg);
+                record_failure(
+                    &mut failures,
+                    &original,
+                    trash_error_code(&err),
+                    &trash_error_message(&err),
+                );
+            }
+        }
+    }
+    crate::log_event(
+        "TRASH_PURGE",
+        "-",
+        "-",
+        &format!(
+            "count={}; all={}; ms={}",
+            ok,
+            ids.is_none(),
+            started.elapsed().as_millis()
+        ),
+    );
+    Ok(OpSummary {
+        ok,
+        failed: failures.len() as u64,
+        total: total as u64,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entry_from_trash_parts, sort_trash_entries};
+    use crate::types::EntryType;
+    use std::path::PathBuf;
+    use trash::{TrashItem, TrashItemSize};
+
+    fn item(name: &str, time_deleted: i64) -> TrashItem {
+        TrashItem {
+            id: format!("id-{}", name).into(),
+            name: name.to_string(),
+            original_parent: PathBuf::from("/home/user/docs"),
+            time_deleted,
+        }
+    }
+
+    #[test]
+    fn trash_items_map_to_entries() {
+        let file = entry_from_trash_parts(&item("Report.PDF", 60), Some(TrashItemSize::Bytes(42)));
+        assert_eq!(file.id, "id-Report.PDF");
+        assert_eq!(
+            file.path,
+            PathBuf::from("/home/user/docs")
+                .join("Report.PDF")
+                .to_string_lossy()
+        );
+        assert_eq!(file.original_parent, "/home/user/docs");
+        assert!(matches!(file.entry_type, EntryType::File));
+        assert_eq!((file.size, file.ext.as_str()), (42, ".PDF"));
+        assert!(!file.deleted_at.is_empty());
+
+        let dir = entry_from_trash_parts(&item("photos.d", 60), Some(TrashItemSize::Entries(3)));
+        assert!(matches!(dir.entry_type, EntryType::Dir));
+        assert_eq!((dir.size, dir.ext.as_str()), (0, ""));
+
+        // Unreadable metadata and a pre-epoch time still give an entry.
+        let unknown = entry_from_trash_parts(&item("notes", -1), None);
+        assert!(matches!(unknown.entry_type, EntryType::File));
+        assert_eq!(unknown.size, 0);
+        assert_eq!(unknown.deleted_at, "");
+    }
+
+    #[test]
+    fn trash_entries_sort_by_key_and_order() {
+        let mut entries = vec![
+            entry_from_trash_parts(&item("b.txt", 300), Some(TrashItemSize::Bytes(1))),
+            entry_from_trash_parts(&item("A.md", 100), Some(TrashItemSize::Bytes(3))),
+            entry_from_trash_parts(&item("c.md", 200), Some(TrashItemSize::Bytes(2))),
+        ];
+        let names = |entries: &[crate::types::SystemTrashEntry]| -> Vec<String> {
+            entries.iter().map(|e| e.name.clone()).collect()
+        };
+        sort_trash_entries(&mut entries, "name", "asc");
+        assert_eq!(names(&entries), ["A.md", "b.txt", "c.md"]);
+        sort_trash_entries(&mut entries, "size", "desc");
+        assert_eq!(names(&entries), ["A.md", "c.md", "b.txt"]);
+        sort_trash_entries(&mut entries, "type", "asc");
+        assert_eq!(names(&entries), ["A.md", "c.md", "b.txt"]);
+        sort_trash_entries(&mut entries, "modified", "desc");
+        assert_eq!(names(&entries), ["b.txt", "c.md", "A.md"]);
+        // Unknown keys sort by name, unknown orders ascend.
+        sort_trash_entries(&mut entries, "bogus", "bogus");
+        assert_eq!(names(&entries), ["A.md", "b.txt", "c.md"]);
+    }
+}
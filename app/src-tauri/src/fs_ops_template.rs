@@ -0,0 +1,293 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::config::templates_dir;
+use crate::fs_ops_mutate_helpers::{io_error_code, validate_name};
+use crate::fs_ops_preflight::preflight_create;
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{TemplateEntry, TemplateList};
+
+/// Values substituted for `{date}`, `{time}`, `{name}` and `{user}`.
+struct Placeholders {
+    date: String,
+    time: String,
+    name: String,
+    user: String,
+}
+
+impl Placeholders {
+    fn new(name: &str) -> Self {
+        let now = chrono::Local::now();
+        let user = std::env::var("USERNAME")
+            .or_else(|_| std::env::var("USER"))
+            .unwrap_or_default();
+        Placeholders {
+            date: now.format("%Y-%m-%d").to_string(),
+            time: now.format("%H%M").to_string(),
+            name: name.to_string(),
+            user,
+        }
+    }
+
+    fn apply(&self, text: &str) -> String {
+        text.replace("{date}", &self.date)
+            .replace("{time}", &self.time)
+            .replace("{name}", &self.name)
+            .replace("{user}", &self.user)
+    }
+}
+
+fn file_stem(name: &str) -> &str {
+    Path::new(name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(name)
+}
+
+/// Text files get placeholders substituted; anything that is not UTF-8 or holds NUL
+/// bytes is treated as binary and copied as-is.
+fn write_instance(src: &Path, file: &mut fs::File, values: &Placeholders) -> io::Result<()> {
+    let bytes = fs::read(src)?;
+    match String::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => file.write_all(values.apply(&text).as_bytes()),
+        Ok(text) => file.write_all(text.as_bytes()),
+        Err(err) => file.write_all(err.as_bytes()),
+    }
+}
+
+/// Creates `dst` fresh, so an item already there is never truncated.
+fn create_file(dst: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)
+}
+
+fn fill_dir(src: &Path, dst: &Path, values: &Placeholders) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let name = values.apply(&entry.file_name().to_string_lossy());
+        let child_dst = dst.join(name);
+        if entry.file_type()?.is_dir() {
+            fs::create_dir(&child_dst)?;
+            fill_dir(&entry.path(), &child_dst, values)?;
+        } else {
+            write_instance(&entry.path(), &mut create_file(&child_dst)?, values)?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates `dst` from the template at `src`. Only a `dst` this call created is removed
+/// again when filling it fails.
+fn instantiate(src: &Path, dst: &Path, values: &Placeholders) -> io::Result<()> {
+    if src.is_dir() {
+        fs::create_dir(dst)?;
+        fill_dir(src, dst, values).inspect_err(|_| {
+            let _ = fs::remove_dir_all(dst);
+        })
+    } else {
+        let mut file = create_file(dst)?;
+        let result = write_instance(src, &mut file, values);
+        if result.is_err() {
+            // Close the handle first so the file can be removed on Windows.
+            drop(file);
+            let _ = fs::remove_file(dst);
+        }
+        result
+    }
+}
+
+fn list_templates_in(dir: &Path) -> io::Result<Vec<TemplateEntry>> {
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        templates.push(TemplateEntry {
+            name,
+            is_dir: entry.file_type()?.is_dir(),
+        });
+    }
+    templates.sort_by_key(|entry| entry.name.to_lowercase());
+    Ok(templates)
+}
+
+/// Resolves the template and the output path. Without `name` the output is named after
+/// the template, with placeholders filled in.
+fn plan_from_template(
+    templates_root: &Path,
+    parent: &str,
+    template: &str,
+    name: Option<&str>,
+) -> Result<(PathBuf, PathBuf, Placeholders), String> {
+    let template = validate_name(template).map_err(|err| format!("code=invalid_name; {err}"))?;
+    let source = templates_root.join(template);
+    if !source.exists() {
+        return Err(format!(
+            "code=template_not_found; template not found: {template}"
+        ));
+    }
+    let kind = if source.is_dir() { "folder" } else { "file" };
+    let (values, out_name) = match name.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => (Placeholders::new(file_stem(name)), name.to_string()),
+        None => {
+            // The template's own stem may hold placeholders too, e.g. `report {date}.md`.
+            let mut values = Placeholders::new("");
+            values.name = values.apply(file_stem(template));
+            let out_name = values.apply(template);
+            (values, out_name)
+        }
+    };
+    let target = preflight_create(parent, &out_name, kind)
+        .map_err(|err| format!("code={}; {}", err.code, err.message))?;
+    Ok((source, target, values))
+}
+
+fn create_from_template_in(
+    templates_root: &Path,
+    parent: &str,
+    template: &str,
+    name: Option<&str>,
+) -> Result<PathBuf, String> {
+    let (source, target, values) = plan_from_template(templates_root, parent, template, name)?;
+    instantiate(&source, &target, &values)
+        .map_err(|err| format!("code={}; {}", io_error_code(&err), err))?;
+    Ok(target)
+}
+
+#[tauri::command]
+pub fn fs_list_templates() -> Result<TemplateList, String> {
+    let dir = templates_dir();
+    if let Err(err) = fs::create_dir_all(&dir) {
+        return Err(format!("code={}; {}", io_error_code(&err), err));
+    }
+    let templates =
+        list_templates_in(&dir).map_err(|err| format!("code={}; {}", io_error_code(&err), err))?;
+    Ok(TemplateList {
+        dir: dir.to_string_lossy().to_string(),
+        templates,
+    })
+}
+
+#[tauri::command]
+pub fn fs_create_from_template(
+    parent: String,
+    template: String,
+    name: Option<String>,
+) -> Result<String, String> {
+    let started = Instant::now();
+    let resolved_parent = match resolve_legacy_path_for(&parent, ProviderCapability::Create) {
+        Ok(path) => path,
+        Err(err) => {
+            let message = format!("code={}; {}", err.code(), err);
+            crate::log_error("create_template", &parent, &template, &message);
+            return Err(message);
+        }
+    };
+    let resolved_parent_text = resolved_parent.to_string_lossy().to_string();
+    match create_from_template_in(
+        &templates_dir(),
+        &resolved_parent_text,
+        &template,
+        name.as_deref(),
+    ) {
+        Ok(path) => {
+            let path_text = path.to_string_lossy().to_string();
+            crate::log_event(
+                "CREATE_TEMPLATE",
+                &template,
+                &path_text,
+                &format!("ms={}", started.elapsed().as_millis()),
+            );
+            Ok(path_text)
+        }
+        Err(message) => {
+            crate::log_error("create_template", &parent, &template, &message);
+            Err(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create_from_template_in, instantiate, list_templates_in, Placeholders};
+    use crate::test_support::unique_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn create_from_template_substitutes_names_and_text() {
+        let root = unique_temp_dir("rf-template");
+        let templates = root.join("templates");
+        let skeleton = templates.join("meeting");
+        fs::create_dir_all(skeleton.join("assets")).expect("create skeleton");
+        fs::write(skeleton.join("{name}-{date}.md"), "# {name}\nby {user}\n").expect("write");
+        fs::write(skeleton.join("assets").join("logo.bin"), [0u8, 1, 2]).expect("write bin");
+        fs::write(templates.join("report {date}.txt"), "{name}").expect("write file");
+        let out = root.join("out");
+        fs::create_dir_all(&out).expect("create out");
+        let out_text = out.to_string_lossy().to_string();
+
+        let listed = list_templates_in(&templates).expect("list");
+        assert_eq!(listed.len(), 2);
+        assert!(listed[0].is_dir);
+
+        let folder = create_from_template_in(&templates, &out_text, "meeting", Some("weekly"))
+            .expect("create folder");
+        assert_eq!(folder, out.join("weekly"));
+        let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+        let note = fs::read_to_string(folder.join(format!("weekly-{date}.md"))).expect("note");
+        assert!(note.starts_with("# weekly\nby "));
+        assert!(!note.contains("{user}"));
+        assert_eq!(
+            fs::read(folder.join("assets").join("logo.bin")).expect("bin"),
+            vec![0u8, 1, 2]
+        );
+
+        let file = create_from_template_in(&templates, &out_text, "report {date}.txt", None)
+            .expect("create file");
+        assert_eq!(file, out.join(format!("report {date}.txt")));
+        assert_eq!(
+            fs::read_to_string(&file).expect("read"),
+            format!("report {date}")
+        );
+
+        let err = create_from_template_in(&templates, &out_text, "meeting", Some("weekly"))
+            .expect_err("existing target");
+        assert!(err.starts_with("code=already_exists;"));
+        let err = create_from_template_in(&templates, &out_text, "missing", None)
+            .expect_err("missing template");
+        assert!(err.starts_with("code=template_not_found;"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn instantiate_leaves_items_it_did_not_create() {
+        let root = unique_temp_dir("rf-template-race");
+        let templates = root.join("templates");
+        fs::create_dir_all(templates.join("folder")).expect("create template");
+        fs::write(templates.join("folder").join("a.txt"), "{name}").expect("write");
+        fs::write(templates.join("note.txt"), "{name}").expect("write");
+        // Items that appeared after the preflight check.
+        let taken_dir = root.join("taken");
+        fs::create_dir(&taken_dir).expect("create taken");
+        fs::write(taken_dir.join("keep.txt"), b"keep").expect("write keep");
+        let taken_file = root.join("taken.txt");
+        fs::write(&taken_file, b"original").expect("write taken");
+        let values = Placeholders::new("x");
+
+        let err =
+            instantiate(&templates.join("folder"), &taken_dir, &values).expect_err("folder exists");
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(taken_dir.join("keep.txt")).expect("keep"), b"keep");
+        let err = instantiate(&templates.join("note.txt"), &taken_file, &values)
+            .expect_err("file exists");
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&taken_file).expect("taken"), b"original");
+        let _ = fs::remove_dir_all(root);
+    }
+}
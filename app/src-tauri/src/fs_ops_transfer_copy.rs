@@ -12,6 +12,7 @@ use crate::fs_ops_transfer_helpers::{
 };
 use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
 use crate::types::{OpKind, OpStatus, OpSummary};
+use crate::undo_journal::{copy_created_pairs, JournalOp};
 
 #[derive(serde::Deserialize)]
 pub struct CopyPair {
@@ -87,6 +88,7 @@ fn fs_copy_all_local(
     let mut ok = 0u64;
     let mut failed = 0u64;
     let mut failures = Vec::new();
+    let mut journal_pairs = Vec::new();
     for (index, (raw_item, from)) in resolved_items.into_iter().enumerate() {
         if cancel_requested() {
             break;
@@ -154,6 +156,7 @@ fn fs_copy_all_local(
                 continue;
             }
         }
+        let created = copy_created_pairs(&from, &to);
         if let Err(err) = copy_recursively(&from, &to) {
             let code = io_error_code(&err);
             let message = err.to_string();
@@ -181,6 +184,7 @@ fn fs_copy_all_local(
             &format!("count={}; ms={}", total, started.elapsed().as_millis()),
         );
         ok += 1;
+        journal_pairs.extend(created);
         emit_progress(
             &app,
             OpKind::Copy,
@@ -191,6 +195,11 @@ fn fs_copy_all_local(
             String::new(),
         );
     }
+    if !journal_pairs.is_empty() {
+        crate::undo_journal::record(JournalOp::Copy {
+            pairs: journal_pairs,
+        });
+    }
     Ok(OpSummary {
         ok,
         failed,
@@ -258,6 +267,7 @@ pub fn fs_copy_pairs(app: AppHandle, pairs: Vec<CopyPair>) -> Result<OpSummary,
     let mut ok = 0u64;
     let mut failed = 0u64;
     let mut failures = Vec::new();
+    let mut journal_pairs = Vec::new();
     for (index, (from_raw, to_raw, from, to)) in resolved_pairs.into_iter().enumerate() {
         if cancel_requested() {
             break;
@@ -321,6 +331,7 @@ pub fn fs_copy_pairs(app: AppHandle, pairs: Vec<CopyPair>) -> Result<OpSummary,
                 continue;
             }
         }
+        let created = copy_created_pairs(&from, &to);
         if let Err(err) = copy_recursively(&from, &to) {
             let code = io_error_code(&err);
             let message = err.to_string();
@@ -348,6 +359,7 @@ pub fn fs_copy_pairs(app: AppHandle, pairs: Vec<CopyPair>) -> Result<OpSummary,
             &format!("count={}; ms={}", total, started.elapsed().as_millis()),
         );
         ok += 1;
+        journal_pairs.extend(created);
         emit_progress(
             &app,
             OpKind::Copy,
@@ -358,6 +370,11 @@ pub fn fs_copy_pairs(app: AppHandle, pairs: Vec<CopyPair>) -> Result<OpSummary,
             String::new(),
         );
     }
+    if !journal_pairs.is_empty() {
+        crate::undo_journal::record(JournalOp::Copy {
+            pairs: journal_pairs,
+        });
+    }
     Ok(OpSummary {
         ok,
         failed,
@@ -25,7 +25,8 @@ fn cancel_error() -> io::Error {
     io::Error::new(io::ErrorKind::Interrupted, "canceled")
 }
 
-fn ensure_not_canceled() -> io::Result<()> {
+/// Fails with `Interrupted` once `op_cancel` was requested.
+pub fn ensure_not_canceled() -> io::Result<()> {
     if cancel_requested() {
         Err(cancel_error())
     } else {
@@ -11,6 +11,7 @@ use crate::fs_ops_transfer_helpers::{
 };
 use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
 use crate::types::{OpKind, OpStatus, OpSummary};
+use crate::undo_journal::{JournalOp, JournalPair};
 
 #[tauri::command]
 pub fn fs_move(
@@ -70,6 +71,7 @@ pub fn fs_move(
     let mut ok = 0u64;
     let mut failed = 0u64;
     let mut failures = Vec::new();
+    let mut journal_pairs = Vec::new();
     for (index, (raw_item, from)) in resolved_items.into_iter().enumerate() {
         if cancel_requested() {
             break;
@@ -173,6 +175,10 @@ pub fn fs_move(
             &format!("count={}; ms={}", total, started.elapsed().as_millis()),
         );
         ok += 1;
+        journal_pairs.push(JournalPair {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+        });
         emit_progress(
             &app,
             OpKind::Move,
@@ -183,6 +189,11 @@ pub fn fs_move(
             String::new(),
         );
     }
+    if !journal_pairs.is_empty() {
+        crate::undo_journal::record(JournalOp::Move {
+            pairs: journal_pairs,
+        });
+    }
     Ok(OpSummary {
         ok,
         failed,
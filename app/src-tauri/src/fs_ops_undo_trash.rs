@@ -0,0 +1,529 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_mutate_helpers::{
+    io_error_code, move_recursively, move_to_undo_trash, plan_conflict, record_failure,
+    undo_trash_root, undo_trash_stamp, ConflictAction,
+};
+use crate::storage_provider::{resolve_legacy_path_for, ProviderCapability};
+use crate::types::{ConflictPolicy, OpSummary, UndoTrashEntry};
+
+const UNDO_TRASH_MANIFEST_NAME: &str = ".rf-undo-manifest.json";
+
+#[derive(Serialize, Deserialize)]
+struct UndoTrashManifest {
+    original: String,
+    name: String,
+    deleted_at: String,
+    size: u64,
+    is_dir: bool,
+}
+
+struct UndoTrashBucket {
+    entry: UndoTrashEntry,
+    path: PathBuf,
+    deleted: SystemTime,
+}
+
+fn path_size(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| path_size(&entry.path()))
+        .sum()
+}
+
+/// Writes the bucket manifest. A missing manifest only degrades listing, so errors are logged.
+pub fn write_undo_trash_manifest(bucket: &Path, original: &Path, trashed: &Path) {
+    let Some(name) = trashed.file_name() else {
+        return;
+    };
+    if name == UNDO_TRASH_MANIFEST_NAME {
+        return;
+    }
+    let manifest = UndoTrashManifest {
+        original: original.to_string_lossy().to_string(),
+        name: name.to_string_lossy().to_string(),
+        deleted_at: Local::now().to_rfc3339(),
+        size: path_size(trashed),
+        is_dir: trashed.is_dir(),
+    };
+    let result = serde_json::to_string(&manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|text| {
+            fs::write(bucket.join(UNDO_TRASH_MANIFEST_NAME), text).map_err(|e| e.to_string())
+        });
+    if let Err(err) = result {
+        crate::log_error(
+            "undo_trash_manifest",
+            &original.to_string_lossy(),
+            &bucket.to_string_lossy(),
+            &err,
+        );
+    }
+}
+
+/// True when nothing but the manifest is left in `bucket`.
+fn bucket_is_empty(bucket: &Path) -> bool {
+    fs::read_dir(bucket)
+        .map(|entries| {
+            entries
+                .flatten()
+                .all(|entry| entry.file_name() == UNDO_TRASH_MANIFEST_NAME)
+        })
+        .unwrap_or(false)
+}
+
+fn sync_buckets_after_move(root: &Path, from: &Path, to: &Path) {
+    if let Some(bucket) = to.parent().filter(|bucket| bucket.parent() == Some(root)) {
+        write_undo_trash_manifest(bucket, from, to);
+    }
+    let emptied = from
+        .parent()
+        .filter(|bucket| bucket.parent() == Some(root) && bucket_is_empty(bucket));
+    if let Some(bucket) = emptied {
+        let _ = fs::remove_dir_all(bucket);
+    }
+}
+
+/// Called after the journal moved `from` to `to`: an item moved back out of the undo trash
+/// takes its emptied bucket with it, and one moved in gets a manifest.
+pub fn sync_undo_trash_after_move(from: &Path, to: &Path) {
+    if let Ok(root) = undo_trash_root() {
+        sync_buckets_after_move(&root, from, to);
+    }
+}
+
+fn bucket_stamp_time(id: &str) -> Option<SystemTime> {
+    let stamp = id.split('_').next()?.parse::<u64>().ok()?;
+    UNIX_EPOCH.checked_add(Duration::from_millis(stamp))
+}
+
+fn read_bucket(path: &Path) -> Option<UndoTrashBucket> {
+    let id = path.file_name()?.to_string_lossy().to_string();
+    let manifest = fs::read_to_string(path.join(UNDO_TRASH_MANIFEST_NAME))
+        .ok()
+        .and_then(|text| serde_json::from_str::<UndoTrashManifest>(&text).ok());
+    let stamp_time = bucket_stamp_time(&id);
+    match manifest {
+        Some(manifest) => {
+            let trashed = path.join(&manifest.name);
+            // The item was moved back out, e.g. by undo; the bucket is only a leftover.
+            fs::symlink_metadata(&trashed).ok()?;
+            let deleted = DateTime::parse_from_rfc3339(&manifest.deleted_at)
+                .map(SystemTime::from)
+                .ok()
+                .or(stamp_time)
+                .unwrap_or(UNIX_EPOCH);
+            Some(UndoTrashBucket {
+                entry: UndoTrashEntry {
+                    id,
+                    name: manifest.name,
+                    original: manifest.original,
+                    trashed: trashed.to_string_lossy().to_string(),
+                    deleted_at: manifest.deleted_at,
+                    size: manifest.size,
+                    is_dir: manifest.is_dir,
+                    has_manifest: true,
+                },
+                path: path.to_path_buf(),
+                deleted,
+            })
+        }
+        None => {
+            // Buckets written before manifests existed hold a single item and nothing else.
+            let item = fs::read_dir(path).ok()?.flatten().next()?.path();
+            let deleted = stamp_time.unwrap_or(UNIX_EPOCH);
+            let deleted_at: DateTime<Local> = deleted.into();
+            Some(UndoTrashBucket {
+                entry: UndoTrashEntry {
+                    id,
+                    name: item
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    original: String::new(),
+                    trashed: item.to_string_lossy().to_string(),
+                    deleted_at: deleted_at.to_rfc3339(),
+                    size: path_size(&item),
+                    is_dir: item.is_dir(),
+                    has_manifest: false,
+                },
+                path: path.to_path_buf(),
+                deleted,
+            })
+        }
+    }
+}
+
+fn list_buckets_in(root: &Path) -> Vec<UndoTrashBucket> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut buckets: Vec<UndoTrashBucket> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| read_bucket(&entry.path()))
+        .collect();
+    buckets.sort_by_key(|bucket| std::cmp::Reverse(bucket.deleted));
+    buckets
+}
+
+fn bucket_path_for_id(root: &Path, id: &str) -> Result<PathBuf, String> {
+    let trimmed = id.trim();
+    let plain =
+        !trimmed.is_empty() && trimmed != "." && trimmed != ".." && !trimmed.contains(['/', '\\']);
+    if !plain {
+        return Err(format_error(AppErrorKind::InvalidPath, "invalid bucket id"));
+    }
+    let path = root.join(trimmed);
+    if !path.is_dir() {
+        return Err(format_error(AppErrorKind::NotFound, "bucket not found"));
+    }
+    Ok(path)
+}
+
+/// Removes leftover buckets whose item is gone, buckets older than `max_age` and then the
+/// oldest ones until the total fits `max_size`. Buckets listed in `protect` are never
+/// removed. Returns the removed buckets.
+fn purge_buckets_over_limits(
+    root: &Path,
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+    protect: &[String],
+) -> Vec<PathBuf> {
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    if let Ok(entries) = fs::read_dir(root) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let protected = protect.iter().any(|id| entry.file_name() == id.as_str());
+            // Only buckets whose manifest outlived the item; a fresh bucket has none yet.
+            let stale =
+                path.join(UNDO_TRASH_MANIFEST_NAME).is_file() && read_bucket(&path).is_none();
+            if !protected && stale && fs::remove_dir_all(&path).is_ok() {
+                removed.push(path);
+            }
+        }
+    }
+    let mut buckets = list_buckets_in(root);
+    // Oldest first, so the quota pass drops the oldest buckets.
+    buckets.reverse();
+    let mut total: u64 = buckets.iter().map(|b| b.entry.size).sum();
+    for bucket in buckets {
+        if protect.contains(&bucket.entry.id) {
+            continue;
+        }
+        let expired = match max_age {
+            Some(max_age) => now
+                .duration_since(bucket.deleted)
+                .map(|age| age > max_age)
+                .unwrap_or(false),
+            None => false,
+        };
+        let over_quota = max_size.map(|limit| total > limit).unwrap_or(false);
+        if !expired && !over_quota {
+            continue;
+        }
+        match fs::remove_dir_all(&bucket.path) {
+            Ok(()) => {
+                total = total.saturating_sub(bucket.entry.size);
+                removed.push(bucket.path);
+            }
+            Err(err) => crate::log_error(
+                "undo_trash_purge",
+                &bucket.path.to_string_lossy(),
+                "-",
+                &err.to_string(),
+            ),
+        }
+    }
+    removed
+}
+
+/// Applies the age and size limits from `AppConfig` to the undo trash.
+pub fn enforce_undo_trash_limits(protect: &[String]) {
+    let config = crate::config::load_config_fast();
+    let Ok(root) = undo_trash_root() else {
+        return;
+    };
+    let max_age = (config.undo_trash_max_age_days > 0)
+        .then(|| Duration::from_secs(config.undo_trash_max_age_days * 24 * 60 * 60));
+    let max_size = (config.undo_trash_max_size_mb > 0)
+        .then(|| config.undo_trash_max_size_mb.saturating_mul(1024 * 1024));
+    let removed = purge_buckets_over_limits(&root, max_age, max_size, protect);
+    if !removed.is_empty() {
+        crate::undo_journal::forget_trash_buckets(&removed);
+        crate::log_event(
+            "UNDO_TRASH_PURGE",
+            &root.to_string_lossy(),
+            "-",
+            &format!("count={}; auto=true", removed.len()),
+        );
+    }
+}
+
+fn restore_bucket(bucket: &UndoTrashBucket, policy: ConflictPolicy) -> Result<bool, String> {
+    if bucket.entry.original.trim().is_empty() {
+        return Err(format_error(
+            AppErrorKind::NotFound,
+            "original location unknown (no manifest)",
+        ));
+    }
+    let target = resolve_legacy_path_for(&bucket.entry.original, ProviderCapability::Create)
+        .map_err(|err| format!("code={}; {}", err.code(), err))?;
+    let trashed = PathBuf::from(&bucket.entry.trashed);
+    if !trashed.exists() {
+        return Err(format_error(AppErrorKind::NotFound, "trashed item missing"));
+    }
+    let source_modified = fs::metadata(&trashed).and_then(|m| m.modified()).ok();
+    let action = plan_conflict(&target, source_modified, policy)
+        .map_err(|e| format!("code={}; {}", io_error_code(&e), e))?;
+    let to = match action {
+        ConflictAction::Skip => return Ok(false),
+        ConflictAction::Proceed(to) => to,
+        ConflictAction::Replace(to) => {
+            // Keep the replaced item recoverable instead of deleting it.
+            move_to_undo_trash(&to, undo_trash_stamp(), 0)
+                .map_err(|e| format!("code={}; {}", io_error_code(&e), e))?;
+            to
+        }
+    };
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("code={}; {}", io_error_code(&e), e))?;
+    }
+    move_recursively(&trashed, &to).map_err(|e| format!("code={}; {}", io_error_code(&e), e))?;
+    // Anything else that ended up in the bucket stays recoverable.
+    if bucket_is_empty(&bucket.path) {
+        let _ = fs::remove_dir_all(&bucket.path);
+    }
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn undo_trash_list() -> Result<Vec<UndoTrashEntry>, String> {
+    let root = undo_trash_root()?;
+    Ok(list_buckets_in(&root)
+        .into_iter()
+        .map(|bucket| bucket.entry)
+        .collect())
+}
+
+#[tauri::command]
+pub fn undo_trash_restore(ids: Vec<String>, conflict: Option<String>) -> Result<OpSummary, String> {
+    let started = Instant::now();
+    if ids.is_empty() {
+        return Err(format_error(AppErrorKind::InvalidPath, "no items"));
+    }
+    let policy = ConflictPolicy::parse(conflict.as_deref().unwrap_or("").trim());
+    let root = undo_trash_root()?;
+    let total = ids.len();
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    let mut restored = Vec::new();
+    for id in ids {
+        let bucket = match bucket_path_for_id(&root, &id).and_then(|path| {
+            read_bucket(&path)
+                .ok_or_else(|| format_error(AppErrorKind::NotFound, "bucket is empty"))
+        }) {
+            Ok(bucket) => bucket,
+            Err(err) => {
+                crate::log_error("undo_trash_restore", &id, "-", &err);
+                failed += 1;
+                record_failure(&mut failures, &id, "not_found", &err);
+                continue;
+            }
+        };
+        match restore_bucket(&bucket, policy) {
+            Ok(true) => {
+                crate::log_event(
+                    "UNDO_TRASH_RESTORE",
+                    &bucket.entry.trashed,
+                    &bucket.entry.original,
+                    &format!("count={}; ms={}", total, started.elapsed().as_millis()),
+                );
+                ok += 1;
+                restored.push(bucket.path);
+            }
+            Ok(false) => {
+                failed += 1;
+                record_failure(
+                    &mut failures,
+                    &bucket.entry.original,
+                    "skipped",
+                    "target already exists",
+                );
+            }
+            Err(err) => {
+                crate::log_error(
+                    "undo_trash_restore",
+                    &bucket.entry.trashed,
+                    &bucket.entry.original,
+                    &err,
+                );
+                let code = err
+                    .strip_prefix("code=")
+                    .and_then(|rest| rest.split(';').next())
+                    .unwrap_or("unknown")
+                    .to_string();
+                failed += 1;
+                record_failure(&mut failures, &bucket.entry.original, &code, &err);
+            }
+        }
+    }
+    crate::undo_journal::forget_trash_buckets(&restored);
+    Ok(OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    })
+}
+
+/// Permanently removes the given buckets, or all of them when `ids` is `None`.
+#[tauri::command]
+pub fn undo_trash_purge(ids: Option<Vec<String>>) -> Result<OpSummary, String> {
+    let root = undo_trash_root()?;
+    let ids = match ids {
+        Some(ids) => ids,
+        None => list_buckets_in(&root)
+            .into_iter()
+            .map(|bucket| bucket.entry.id)
+            .collect(),
+    };
+    let total = ids.len();
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    let mut purged = Vec::new();
+    for id in ids {
+        let result = bucket_path_for_id(&root, &id).and_then(|path| {
+            fs::remove_dir_all(&path).map_err(|e| format!("code={}; {}", io_error_code(&e), e))?;
+            Ok(path)
+        });
+        match result {
+            Ok(path) => purged.push(path),
+            Err(err) => {
+                crate::log_error("undo_trash_purge", &id, "-", &err);
+                failed += 1;
+                record_failure(&mut failures, &id, "io_error", &err);
+                continue;
+            }
+        }
+        ok += 1;
+    }
+    crate::undo_journal::forget_trash_buckets(&purged);
+    crate::log_event(
+        "UNDO_TRASH_PURGE",
+        &root.to_string_lossy(),
+        "-",
+        &format!("count={}; failed={}", ok, failed),
+    );
+    Ok(OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        list_buckets_in, purge_buckets_over_limits, sync_buckets_after_move,
+        UNDO_TRASH_MANIFEST_NAME,
+    };
+    use crate::test_support::unique_temp_dir;
+    use std::fs;
+    use std::path::Path;
+    use std::time::Duration;
+
+    fn write_bucket(root: &Path, id: &str, deleted_at: &str, size: usize) {
+        let bucket = root.join(id);
+        fs::create_dir_all(&bucket).expect("create bucket");
+        fs::write(bucket.join("a.txt"), vec![b'x'; size]).expect("write item");
+        let manifest = format!(
+            r#"{{"original":"/tmp/a.txt","name":"a.txt","deleted_at":"{deleted_at}","size":{size},"is_dir":false}}"#
+        );
+        fs::write(bucket.join(UNDO_TRASH_MANIFEST_NAME), manifest).expect("write manifest");
+    }
+
+    #[test]
+    fn list_reads_manifest_and_legacy_buckets() {
+        let root = unique_temp_dir("rf-undo-trash-list");
+        write_bucket(&root, "2000_0", "2026-01-02T00:00:00+00:00", 4);
+        let legacy = root.join("1000_0");
+        fs::create_dir_all(&legacy).expect("create legacy bucket");
+        fs::write(legacy.join("old.txt"), b"old").expect("write legacy item");
+
+        let buckets = list_buckets_in(&root);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].entry.id, "2000_0");
+        assert!(buckets[0].entry.has_manifest);
+        assert_eq!(buckets[0].entry.original, "/tmp/a.txt");
+        assert_eq!(buckets[1].entry.name, "old.txt");
+        assert!(!buckets[1].entry.has_manifest);
+        assert_eq!(buckets[1].entry.size, 3);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn purge_drops_expired_then_oldest_over_quota() {
+        let root = unique_temp_dir("rf-undo-trash-purge");
+        write_bucket(&root, "1_0", "2001-01-01T00:00:00+00:00", 10);
+        write_bucket(&root, "2_0", "2099-01-01T00:00:00+00:00", 10);
+        write_bucket(&root, "3_0", "2099-01-02T00:00:00+00:00", 10);
+        write_bucket(&root, "4_0", "2099-01-03T00:00:00+00:00", 10);
+
+        let removed = purge_buckets_over_limits(
+            &root,
+            Some(Duration::from_secs(24 * 60 * 60)),
+            Some(25),
+            &["2_0".to_string()],
+        );
+        assert_eq!(removed, [root.join("1_0"), root.join("3_0")]);
+        assert!(!root.join("1_0").exists());
+        assert!(root.join("2_0").exists());
+        assert!(!root.join("3_0").exists());
+        assert!(root.join("4_0").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn items_moved_out_leave_no_bucket_behind() {
+        let root = unique_temp_dir("rf-undo-trash-moved-out");
+        write_bucket(&root, "1_0", "2099-01-01T00:00:00+00:00", 4);
+        write_bucket(&root, "2_0", "2099-01-02T00:00:00+00:00", 4);
+        write_bucket(&root, "3_0", "2099-01-03T00:00:00+00:00", 4);
+        let restored = root.join("restored.txt");
+        fs::rename(root.join("1_0").join("a.txt"), &restored).expect("move out");
+        sync_buckets_after_move(&root, &root.join("1_0").join("a.txt"), &restored);
+        assert!(!root.join("1_0").exists());
+
+        // A bucket emptied behind our back is hidden from the list and purged.
+        fs::remove_file(root.join("2_0").join("a.txt")).expect("remove item");
+        let ids: Vec<String> = list_buckets_in(&root)
+            .into_iter()
+            .map(|bucket| bucket.entry.id)
+            .collect();
+        assert_eq!(ids, ["3_0"]);
+        let removed = purge_buckets_over_limits(&root, None, None, &[]);
+        assert_eq!(removed, [root.join("2_0")]);
+        assert!(root.join("3_0").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -115,13 +115,20 @@ fn cleanup_viewer_image_cache_dir(dir: &Path) {
     }
 }
 
-fn entry_from_resource_ref(resource_ref: ResourceRef) -> AppResult<Entry> {
+pub(crate) fn entry_from_resource_ref(resource_ref: ResourceRef) -> AppResult<Entry> {
     let registry = provider_registry();
     let provider = registry.provider_for_ref(&resource_ref)?;
     let capabilities = provider_capabilities(provider);
 
     let path = provider.resolve_path(&resource_ref)?;
-    let metadata = provider.metadata(&resource_ref)?;
+    let metadata = match provider.metadata(&resource_ref) {
+        Ok(metadata) => metadata,
+        // A dangling link is still an entry; it is shown as the link itself.
+        Err(err) => match fs::symlink_metadata(&path) {
+            Ok(link) if link.file_type().is_symlink() => link,
+            _ => return Err(err),
+        },
+    };
     let entry_type = if metadata.is_dir() {
         EntryType::Dir
     } else {
@@ -201,7 +208,7 @@ fn dir_stats(path: &Path) -> (u64, u64, u64) {
     (total_size, file_count, dir_count)
 }
 
-fn sort_entries(entries: &mut [Entry], sort_key: &str, sort_order: &str) {
+pub(crate) fn sort_entries(entries: &mut [Entry], sort_key: &str, sort_order: &str) {
     let key = SortKey::parse(sort_key);
     let order = SortOrder::parse(sort_order);
     entries.sort_by(|a, b| {
@@ -758,22 +765,11 @@ mod tests {
         fs_read_image_data_url_impl, fs_read_image_normalized_temp_path_impl, fs_read_text_impl,
         fs_read_text_viewport_lines_impl, fs_text_viewport_info_impl, infer_image_mime,
     };
+    use crate::test_support::unique_temp_dir;
     use crate::types::{PropertyKind, ResourceRef, StorageProvider};
     use image::{DynamicImage, ImageBuffer, ImageFormat, Rgb};
     use std::fs;
     use std::path::{Path, PathBuf};
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    fn unique_temp_dir(prefix: &str) -> PathBuf {
-        let mut dir = std::env::temp_dir();
-        let stamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("system time")
-            .as_nanos();
-        dir.push(format!("{prefix}-{}-{stamp}", std::process::id()));
-        fs::create_dir_all(&dir).expect("create temp dir");
-        dir
-    }
 
     fn write_temp_file(dir: &Path, name: &str, bytes: &[u8]) -> PathBuf {
         let path = dir.join(name);
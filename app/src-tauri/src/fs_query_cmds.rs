@@ -1,3 +1,5 @@
+use tauri::{AppHandle, Emitter};
+
 use crate::fs_query::{
     fs_dir_stats_impl, fs_get_capabilities_by_ref_impl, fs_get_capabilities_impl,
     fs_get_properties_by_ref_impl, fs_get_properties_impl, fs_is_probably_text_by_ref_impl,
@@ -8,7 +10,11 @@ use crate::fs_query::{
     fs_read_text_viewport_lines_impl, fs_text_viewport_info_by_ref_impl,
     fs_text_viewport_info_impl, TextViewportChunk, TextViewportInfo,
 };
-use crate::types::{DirStats, Entry, Properties, ProviderCapabilities, ResourceRef};
+use crate::fs_query_listing::{fs_list_dir_page_impl, fs_list_dir_stream_impl};
+use crate::types::{
+    DirListingPage, DirStats, Entry, Properties, ProviderCapabilities, ResourceRef,
+    EVENT_DIR_LISTING,
+};
 
 #[tauri::command]
 pub fn fs_list_dir(
@@ -32,6 +38,42 @@ pub fn fs_list_dir_by_ref(
         .map_err(|err| format!("code={}; {}", err.code(), err))
 }
 
+/// Streams the listing as `dir_listing` batch events and returns its first sorted page.
+/// Async so walking a large folder does not block the main thread.
+#[tauri::command(async)]
+pub fn fs_list_dir_stream(
+    app: AppHandle,
+    resource_ref: ResourceRef,
+    show_hidden: bool,
+    sort_key: String,
+    sort_order: String,
+    limit: Option<usize>,
+) -> Result<DirListingPage, String> {
+    fs_list_dir_stream_impl(
+        resource_ref,
+        show_hidden,
+        sort_key,
+        sort_order,
+        limit,
+        &mut |batch| {
+            let _ = app.emit(EVENT_DIR_LISTING, batch);
+        },
+    )
+    .map_err(|err| format!("code={}; {}", err.code(), err))
+}
+
+#[tauri::command]
+pub fn fs_list_dir_page(
+    listing_id: u64,
+    offset: usize,
+    limit: Option<usize>,
+    sort_key: String,
+    sort_order: String,
+) -> Result<DirListingPage, String> {
+    fs_list_dir_page_impl(listing_id, offset, limit, sort_key, sort_order)
+        .map_err(|err| format!("code={}; {}", err.code(), err))
+}
+
 #[tauri::command]
 pub fn fs_read_text(path: String, max_bytes: usize) -> Result<String, String> {
     fs_read_text_impl(path, max_bytes).map_err(|err| format!("code={}; {}", err.code(), err))
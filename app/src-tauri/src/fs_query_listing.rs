@@ -0,0 +1,208 @@
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::error::{AppError, AppErrorKind, AppResult};
+use crate::fs_query::{entry_from_resource_ref, sort_entries};
+use crate::storage_provider::provider_registry;
+use crate::types::{
+    DirListingBatch, DirListingError, DirListingPage, Entry, ResourceRef, SortKey, SortOrder,
+};
+
+const LISTING_BATCH_SIZE: usize = 1000;
+const LISTING_CACHE_MAX_ENTRIES: usize = 4;
+
+/// A finished listing kept for paging, sorted by the key and order it was last paged with.
+struct ListingSnapshot {
+    id: u64,
+    entries: Vec<Entry>,
+    errors: Vec<DirListingError>,
+    sort_key: &'static str,
+    sort_order: &'static str,
+}
+
+impl ListingSnapshot {
+    fn sort(&mut self, sort_key: &str, sort_order: &str) {
+        let key = SortKey::parse(sort_key).as_str();
+        let order = SortOrder::parse(sort_order).as_str();
+        if (key, order) != (self.sort_key, self.sort_order) {
+            sort_entries(&mut self.entries, key, order);
+            self.sort_key = key;
+            self.sort_order = order;
+        }
+    }
+
+    fn page(&self, offset: usize, limit: Option<usize>) -> DirListingPage {
+        let total = self.entries.len();
+        let start = offset.min(total);
+        let end = limit.map_or(total, |limit| start.saturating_add(limit).min(total));
+        DirListingPage {
+            listing_id: self.id,
+            offset: start,
+            total,
+            entries: self.entries[start..end].to_vec(),
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+static NEXT_LISTING_ID: AtomicU64 = AtomicU64::new(1);
+static LISTING_CACHE: Lazy<Mutex<Vec<ListingSnapshot>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+fn listing_error(path: String, err: &AppError) -> DirListingError {
+    DirListingError {
+        path,
+        code: err.code().to_string(),
+        error: err.to_string(),
+    }
+}
+
+fn lock_cache() -> AppResult<std::sync::MutexGuard<'static, Vec<ListingSnapshot>>> {
+    LISTING_CACHE
+        .lock()
+        .map_err(|_| AppError::msg("listing cache lock failed"))
+}
+
+/// Lists `dir_ref` without stopping at children that cannot be read. Entries reach
+/// `on_batch` unsorted while the folder is walked, the last batch marked `done`. The sorted
+/// result is kept for `fs_list_dir_page_impl` and its first `limit` entries are returned.
+pub(crate) fn fs_list_dir_stream_impl(
+    dir_ref: ResourceRef,
+    show_hidden: bool,
+    sort_key: String,
+    sort_order: String,
+    limit: Option<usize>,
+    on_batch: &mut dyn FnMut(DirListingBatch),
+) -> AppResult<DirListingPage> {
+    let registry = provider_registry();
+    let provider = registry.provider_for_ref(&dir_ref)?;
+    let dir_path = provider.display_path(&dir_ref);
+    let listing_id = NEXT_LISTING_ID.fetch_add(1, Ordering::Relaxed);
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut errors = Vec::new();
+    let (mut sent, mut sent_errors) = (0, 0);
+    provider.visit_dir_refs(&dir_ref, &mut |item| {
+        match item {
+            Ok(resource_ref) => {
+                let path = provider.display_path(&resource_ref);
+                match entry_from_resource_ref(resource_ref) {
+                    Ok(entry) if !show_hidden && entry.hidden => {}
+                    Ok(entry) => entries.push(entry),
+                    Err(err) => errors.push(listing_error(path, &err)),
+                }
+            }
+            Err(err) => errors.push(listing_error(dir_path.clone(), &err)),
+        }
+        if entries.len() - sent >= LISTING_BATCH_SIZE {
+            on_batch(DirListingBatch {
+                listing_id,
+                entries: entries[sent..].to_vec(),
+                errors: errors[sent_errors..].to_vec(),
+                loaded: entries.len(),
+                done: false,
+            });
+            (sent, sent_errors) = (entries.len(), errors.len());
+        }
+    })?;
+    on_batch(DirListingBatch {
+        listing_id,
+        entries: entries[sent..].to_vec(),
+        errors: errors[sent_errors..].to_vec(),
+        loaded: entries.len(),
+        done: true,
+    });
+
+    let mut snapshot = ListingSnapshot {
+        id: listing_id,
+        entries,
+        errors,
+        sort_key: "",
+        sort_order: "",
+    };
+    snapshot.sort(&sort_key, &sort_order);
+    let page = snapshot.page(0, limit);
+    let mut cache = lock_cache()?;
+    if cache.len() >= LISTING_CACHE_MAX_ENTRIES {
+        cache.remove(0);
+    }
+    cache.push(snapshot);
+    Ok(page)
+}
+
+/// Returns `limit` entries from `offset` of a listing made by `fs_list_dir_stream_impl`,
+/// sorting the kept snapshot again only when the key or order changed.
+pub(crate) fn fs_list_dir_page_impl(
+    listing_id: u64,
+    offset: usize,
+    limit: Option<usize>,
+    sort_key: String,
+    sort_order: String,
+) -> AppResult<DirListingPage> {
+    let mut cache = lock_cache()?;
+    let snapshot = cache
+        .iter_mut()
+        .find(|snapshot| snapshot.id == listing_id)
+        .ok_or_else(|| AppError::with_kind(AppErrorKind::NotFound, "listing expired"))?;
+    snapshot.sort(&sort_key, &sort_order);
+    Ok(snapshot.page(offset, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fs_list_dir_page_impl, fs_list_dir_stream_impl};
+    use crate::test_support::unique_temp_dir;
+    use crate::types::{ResourceRef, StorageProvider};
+    use std::fs;
+
+    #[test]
+    fn streamed_listing_keeps_dangling_links_and_pages() {
+        let dir = unique_temp_dir("rf-fs-query-stream");
+        fs::write(dir.join("b.txt"), b"bb").expect("write b");
+        fs::write(dir.join("c.txt"), b"ccc").expect("write c");
+        fs::write(dir.join("a.txt"), b"a").expect("write a");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(dir.join("gone"), dir.join("dangling")).expect("symlink");
+
+        let mut batches = Vec::new();
+        let page = fs_list_dir_stream_impl(
+            ResourceRef {
+                provider: StorageProvider::Local,
+                resource_id: dir.to_string_lossy().to_string(),
+            },
+            true,
+            "name".to_string(),
+            "asc".to_string(),
+            Some(2),
+            &mut |batch| batches.push(batch),
+        )
+        .expect("stream listing");
+        // The dangling link is listed as the link itself, not reported as an error.
+        let listed = if cfg!(unix) { 4 } else { 3 };
+        let last = batches.last().expect("final batch");
+        assert!(last.done);
+        assert_eq!(last.loaded, listed);
+        assert_eq!(page.total, listed);
+        assert!(page.errors.is_empty());
+        let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+
+        let page = fs_list_dir_page_impl(
+            page.listing_id,
+            1,
+            Some(5),
+            "size".to_string(),
+            "desc".to_string(),
+        )
+        .expect("page");
+        let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!((page.offset, page.total), (1, listed));
+        assert!(names.ends_with(&["b.txt", "a.txt"]), "{names:?}");
+
+        let err = fs_list_dir_page_impl(0, 0, None, "name".to_string(), "asc".to_string())
+            .err()
+            .expect("unknown listing");
+        assert_eq!(err.code(), "not_found");
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -10,22 +10,45 @@ mod dragdrop_cmds;
 mod error;
 mod external_apps;
 mod external_apps_cmds;
+mod fs_compare;
+mod fs_compare_cmds;
 mod fs_ops_archive;
+mod fs_ops_archive_7z;
+mod fs_ops_archive_cmds;
+mod fs_ops_archive_limits;
+mod fs_ops_archive_names;
+mod fs_ops_archive_rar;
+mod fs_ops_archive_split;
+mod fs_ops_archive_tar;
+mod fs_ops_archive_update;
+mod fs_ops_attributes;
 mod fs_ops_create;
 mod fs_ops_delete;
+mod fs_ops_duplicates;
 mod fs_ops_mutate;
 mod fs_ops_mutate_helpers;
 mod fs_ops_preflight;
 mod fs_ops_rename;
+mod fs_ops_shred;
+mod fs_ops_split;
+mod fs_ops_sync;
+mod fs_ops_system_trash;
+mod fs_ops_template;
 mod fs_ops_transfer;
 mod fs_ops_transfer_copy;
 mod fs_ops_transfer_helpers;
 mod fs_ops_transfer_move;
+mod fs_ops_undo_trash;
 mod fs_query;
 mod fs_query_cmds;
+mod fs_query_listing;
 mod log;
 mod storage_provider;
 mod system_cmds;
+#[cfg(test)]
+mod test_support;
+mod undo_journal;
+mod undo_journal_cmds;
 mod utils;
 mod viewer_cmds;
 mod watch;
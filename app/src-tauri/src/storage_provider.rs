@@ -38,6 +38,13 @@ pub trait StorageProviderBackend: Send + Sync {
     fn display_path(&self, resource_ref: &ResourceRef) -> String;
     fn metadata(&self, resource_ref: &ResourceRef) -> AppResult<fs::Metadata>;
     fn list_dir_refs(&self, dir_ref: &ResourceRef) -> AppResult<Vec<ResourceRef>>;
+    /// Like `list_dir_refs`, but hands each child to `visit` as it is read and reports
+    /// unreadable children there instead of failing the whole listing.
+    fn visit_dir_refs(
+        &self,
+        dir_ref: &ResourceRef,
+        visit: &mut dyn FnMut(AppResult<ResourceRef>),
+    ) -> AppResult<()>;
 }
 
 #[derive(Default)]
@@ -104,6 +111,25 @@ impl StorageProviderBackend for LocalStorageProvider {
         }
         Ok(refs)
     }
+
+    fn visit_dir_refs(
+        &self,
+        dir_ref: &ResourceRef,
+        visit: &mut dyn FnMut(AppResult<ResourceRef>),
+    ) -> AppResult<()> {
+        let dir_path = self.resolve_path(dir_ref)?;
+        for item in fs::read_dir(&dir_path)? {
+            let resource_ref = item
+                .map_err(AppError::from)
+                .and_then(|entry| normalize_local_resource_id(&entry.path()))
+                .map(|resource_id| ResourceRef {
+                    provider: StorageProvider::Local,
+                    resource_id,
+                });
+            visit(resource_ref);
+        }
+        Ok(())
+    }
 }
 
 pub struct ProviderRegistry {
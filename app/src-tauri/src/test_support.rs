@@ -0,0 +1,15 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Creates a fresh folder under the system temp dir; tests remove it themselves.
+pub(crate) fn unique_temp_dir(prefix: &str) -> PathBuf {
+    let mut dir = std::env::temp_dir();
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time")
+        .as_nanos();
+    dir.push(format!("{prefix}-{}-{stamp}", std::process::id()));
+    fs::create_dir_all(&dir).expect("create temp dir");
+    dir
+}
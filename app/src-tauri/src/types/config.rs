@@ -7,6 +7,30 @@ fn default_true() -> bool {
     true
 }
 
+fn default_undo_trash_max_age_days() -> u64 {
+    30
+}
+
+fn default_undo_trash_max_size_mb() -> u64 {
+    2048
+}
+
+fn default_archive_max_total_size_mb() -> u64 {
+    32768
+}
+
+fn default_archive_max_entries() -> u64 {
+    100_000
+}
+
+fn default_archive_max_ratio() -> u64 {
+    1000
+}
+
+fn default_archive_max_path_depth() -> u64 {
+    64
+}
+
 #[derive(Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct AppConfig {
@@ -90,6 +114,43 @@ pub struct AppConfig {
         alias = "terminal_profile_wsl"
     )]
     pub external_terminal_profile_wsl: String,
+    #[serde(
+        rename = "undo_trash_max_age_days",
+        default = "default_undo_trash_max_age_days"
+    )]
+    pub undo_trash_max_age_days: u64,
+    #[serde(
+        rename = "undo_trash_max_size_mb",
+        default = "default_undo_trash_max_size_mb"
+    )]
+    pub undo_trash_max_size_mb: u64,
+    #[serde(
+        rename = "archive_max_total_size_mb",
+        default = "default_archive_max_total_size_mb"
+    )]
+    pub archive_max_total_size_mb: u64,
+    #[serde(
+        rename = "archive_max_entries",
+        default = "default_archive_max_entries"
+    )]
+    pub archive_max_entries: u64,
+    #[serde(rename = "archive_max_ratio", default = "default_archive_max_ratio")]
+    pub archive_max_ratio: u64,
+    #[serde(
+        rename = "archive_max_path_depth",
+        default = "default_archive_max_path_depth"
+    )]
+    pub archive_max_path_depth: u64,
+    #[serde(rename = "archive_zip_method")]
+    pub archive_zip_method: ZipMethod,
+    #[serde(rename = "archive_zip_level")]
+    pub archive_zip_level: i64,
+    #[serde(rename = "archive_zip_exclude")]
+    pub archive_zip_exclude: Vec<String>,
+    #[serde(rename = "archive_zip_follow_symlinks", default = "default_true")]
+    pub archive_zip_follow_symlinks: bool,
+    #[serde(rename = "archive_zip_cp932_names")]
+    pub archive_zip_cp932_names: bool,
     #[serde(rename = "log_path", alias = "log_file")]
     pub log_path: String,
     #[serde(rename = "log_enabled", alias = "logging_enabled")]
@@ -229,6 +290,31 @@ impl Default for KeymapProfile {
     }
 }
 
+/// Compression method for new zip entries. Folders and symlinks are always stored.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ZipMethod {
+    #[default]
+    Deflate,
+    Store,
+    Zstd,
+    Bzip2,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ZipMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ZipMethod::Deflate => "deflate",
+            ZipMethod::Store => "store",
+            ZipMethod::Zstd => "zstd",
+            ZipMethod::Bzip2 => "bzip2",
+            ZipMethod::Unknown => "deflate",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum JumpItemType {
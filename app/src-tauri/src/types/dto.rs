@@ -1,4 +1,7 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use super::config::{AppConfig, ZipMethod};
+use super::files::{Entry, EntryType};
 
 /// Failure detail for copy/move operations.
 #[derive(Serialize)]
@@ -32,6 +35,56 @@ pub struct DeleteSummary {
     pub trashed: Vec<TrashItem>,
 }
 
+/// Item held in the app's undo trash (`undo_trash/<stamp>_<index>`).
+#[derive(Serialize)]
+pub struct UndoTrashEntry {
+    pub id: String,
+    pub name: String,
+    pub original: String,
+    pub trashed: String,
+    pub deleted_at: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub has_manifest: bool,
+}
+
+/// Item in the OS trash, shaped like a directory listing row.
+#[derive(Serialize)]
+pub struct SystemTrashEntry {
+    pub id: String,
+    pub name: String,
+    /// Original location of the item.
+    pub path: String,
+    pub original_parent: String,
+    #[serde(rename = "type")]
+    pub entry_type: EntryType,
+    pub size: u64,
+    pub deleted_at: String,
+    pub ext: String,
+}
+
+/// What to do when an operation's target already exists.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Fail,
+    Overwrite,
+    Skip,
+    Rename,
+    Newer,
+}
+
+impl ConflictPolicy {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "overwrite" => ConflictPolicy::Overwrite,
+            "skip" => ConflictPolicy::Skip,
+            "rename" => ConflictPolicy::Rename,
+            "newer" => ConflictPolicy::Newer,
+            _ => ConflictPolicy::Fail,
+        }
+    }
+}
+
 #[derive(Serialize, Copy, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum OpStatus {
@@ -45,9 +98,13 @@ pub enum OpStatus {
 pub enum OpKind {
     Copy,
     Move,
+    Shred,
+    Sync,
+    Split,
+    Join,
 }
 
-/// Progress event emitted during copy/move.
+/// Progress event emitted during copy/move/shred/sync/split/join.
 #[derive(Serialize, Clone)]
 pub struct OpProgress {
     pub op: OpKind,
@@ -58,6 +115,413 @@ pub struct OpProgress {
     pub error: String,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    /// Target becomes an exact copy of source, including deletions.
+    #[default]
+    Mirror,
+    /// Copy new files and files that are newer in source; never delete.
+    Update,
+    /// Propagate changes both ways; undecidable differences become conflicts.
+    TwoWay,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncCompare {
+    /// Size and modification time (2 second tolerance for FAT drives).
+    #[default]
+    Meta,
+    /// Size and SHA-256 of the contents.
+    Hash,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SyncOptions {
+    pub mode: SyncMode,
+    pub compare: SyncCompare,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncActionKind {
+    Copy,
+    Update,
+    Delete,
+    Conflict,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDirection {
+    ToTarget,
+    ToSource,
+}
+
+/// One planned sync step. `rel_path` uses `/` separators and is relative to both roots.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SyncAction {
+    pub kind: SyncActionKind,
+    pub direction: SyncDirection,
+    pub rel_path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub reason: String,
+}
+
+/// Reviewable sync plan; conflicts are reported but never executed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SyncPlan {
+    pub source: String,
+    pub target: String,
+    pub options: SyncOptions,
+    pub actions: Vec<SyncAction>,
+    pub conflicts: Vec<SyncAction>,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct CompareOptions {
+    /// Compare file contents byte by byte instead of size and modification time.
+    pub content: bool,
+    /// Glob patterns; entries matching any of them are left out on both sides.
+    pub ignore: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareStatus {
+    LeftOnly,
+    RightOnly,
+    Identical,
+    Different,
+}
+
+#[derive(Serialize)]
+pub struct CompareSide {
+    pub is_dir: bool,
+    /// Links are not followed; two links match when they point to the same target.
+    pub is_link: bool,
+    pub size: u64,
+    pub modified: String,
+}
+
+/// Node of a folder comparison. Folder status is `different` if anything below differs.
+#[derive(Serialize)]
+pub struct CompareNode {
+    pub name: String,
+    pub rel_path: String,
+    pub status: CompareStatus,
+    pub left: Option<CompareSide>,
+    pub right: Option<CompareSide>,
+    pub children: Vec<CompareNode>,
+    /// Set when the folder or file could not be read; the node then counts as different.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+pub struct CompareCounts {
+    pub left_only: u64,
+    pub right_only: u64,
+    pub identical: u64,
+    pub different: u64,
+    /// Folders and files that could not be read.
+    pub errors: u64,
+}
+
+#[derive(Serialize)]
+pub struct CompareResult {
+    pub left: String,
+    pub right: String,
+    pub children: Vec<CompareNode>,
+    /// Counts of files (not folders) per status.
+    pub counts: CompareCounts,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct DuplicateOptions {
+    /// Files smaller than this are ignored. Empty files are never reported.
+    pub min_size: u64,
+    pub ignore: Vec<String>,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        DuplicateOptions {
+            min_size: 1,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+/// Byte-identical files. `paths` are sorted; hardlinks to one file appear only once.
+#[derive(Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateResult {
+    pub groups: Vec<DuplicateGroup>,
+    pub scanned_files: u64,
+    /// Bytes that removing all but one file of each group would free.
+    pub wasted_bytes: u64,
+    pub canceled: bool,
+}
+
+#[derive(Copy, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateStage {
+    Scan,
+    Partial,
+    Full,
+}
+
+/// Progress of `fs_find_duplicates`; confirmed groups are streamed in `group`.
+#[derive(Clone, Serialize)]
+pub struct DuplicateProgress {
+    pub stage: DuplicateStage,
+    pub processed: u64,
+    pub total: u64,
+    pub group: Option<DuplicateGroup>,
+}
+
+/// One file kept as is, with identical files to be replaced by hardlinks to it.
+#[derive(Deserialize)]
+pub struct HardlinkReplace {
+    pub keep: String,
+    pub replace: Vec<String>,
+}
+
+/// Requested attribute changes; `None` fields are left as they are.
+/// Modes are octal strings such as `"755"`; times are RFC 3339.
+#[derive(Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct AttributeChanges {
+    pub readonly: Option<bool>,
+    pub file_mode: Option<String>,
+    pub dir_mode: Option<String>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub mtime: Option<String>,
+    pub atime: Option<String>,
+    /// Apply to everything below selected folders as well.
+    pub recursive: bool,
+}
+
+#[derive(Serialize)]
+pub struct TemplateEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Templates found in the templates folder next to `config.toml`.
+#[derive(Serialize)]
+pub struct TemplateList {
+    pub dir: String,
+    pub templates: Vec<TemplateEntry>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+    /// Extract only.
+    SevenZip,
+    /// Extract only.
+    Rar,
+}
+
+#[derive(Serialize, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveOp {
+    Create,
+    Extract,
+    Update,
+    Test,
+}
+
+/// Byte-level progress of an archive job; `path` is the entry being processed.
+/// Totals are 0 when they are not known up front, as for compressed tar streams.
+#[derive(Serialize, Clone)]
+pub struct ArchiveProgress {
+    pub op: ArchiveOp,
+    pub path: String,
+    pub entries_done: u64,
+    pub entries_total: u64,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// Entries and uncompressed bytes processed by an archive job. `skipped` lists entries the
+/// extraction conflict policy left untouched.
+#[derive(Serialize, Default, Debug)]
+pub struct ArchiveSummary {
+    pub count: u64,
+    pub bytes: u64,
+    pub skipped: Vec<String>,
+}
+
+/// How zip entry names without the UTF-8 flag are decoded. Flagged names are always UTF-8.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZipNameEncoding {
+    /// Detect one encoding for the whole archive from its names.
+    #[default]
+    Auto,
+    Utf8,
+    /// Windows Shift_JIS, written by Japanese Windows and older Mac tools.
+    Cp932,
+    /// The encoding the zip format specifies; also used by English Windows.
+    Cp437,
+}
+
+/// Extraction settings; the defaults extract every entry and overwrite existing files.
+#[derive(Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct ArchiveExtractOptions {
+    /// Entry names, folders or glob patterns to extract.
+    pub entries: Vec<String>,
+    /// Leading folders dropped from every entry name.
+    pub strip_components: u32,
+    /// Same values as `ConflictPolicy::parse`; `None` means overwrite.
+    pub conflict: Option<String>,
+    /// Extract into a hidden sibling folder and move the result in only once it succeeded.
+    pub staged: bool,
+    /// Zip only: decoding of entry names without the UTF-8 flag.
+    pub name_encoding: ZipNameEncoding,
+}
+
+/// Zip creation settings. Commands called without options use the `archive_zip_*` config.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct ZipCreateOptions {
+    pub method: ZipMethod,
+    /// `None` uses the method's default level.
+    pub level: Option<i64>,
+    /// Glob patterns; files and folders matching any of them are left out. Items passed in
+    /// directly are always added.
+    pub exclude: Vec<String>,
+    /// Store the targets of symlinks instead of the links themselves. On by default, as
+    /// extraction does not recreate links.
+    pub follow_symlinks: bool,
+    /// Write entry names in CP932 without the UTF-8 flag, for unzip tools that predate it.
+    /// Names CP932 cannot represent stay UTF-8.
+    pub cp932_names: bool,
+    /// Split the archive into `.z01`, `.z02`, ... volumes of at most this many bytes, the
+    /// last one taking the archive's own name. `zip_create` only. The split is cut from the
+    /// finished archive, so the destination needs room for it twice.
+    pub volume_size: Option<u64>,
+}
+
+impl Default for ZipCreateOptions {
+    fn default() -> Self {
+        ZipCreateOptions {
+            method: ZipMethod::default(),
+            level: None,
+            exclude: Vec::new(),
+            follow_symlinks: true,
+            cp932_names: false,
+            volume_size: None,
+        }
+    }
+}
+
+impl ZipCreateOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        ZipCreateOptions {
+            method: config.archive_zip_method,
+            level: (config.archive_zip_level != 0).then_some(config.archive_zip_level),
+            exclude: config.archive_zip_exclude.clone(),
+            follow_symlinks: config.archive_zip_follow_symlinks,
+            cp932_names: config.archive_zip_cp932_names,
+            volume_size: None,
+        }
+    }
+}
+
+/// Changes `zip_update` makes besides adding its items.
+#[derive(Clone, Deserialize, Default)]
+#[serde(default)]
+pub struct ZipUpdateOptions {
+    /// Folder inside the archive the items are added to; empty for the top level.
+    pub folder: String,
+    /// Entry names to delete. A folder name deletes everything below it.
+    pub remove: Vec<String>,
+    /// Settings for the added entries; `None` uses the `archive_zip_*` config.
+    pub create: Option<ZipCreateOptions>,
+}
+
+/// One entry of an archive listing, as recorded in the archive headers. `modified` is
+/// empty and `compressed_size`/`crc` are `None` when the format does not store them.
+#[derive(Serialize, Clone, Debug)]
+pub struct ArchiveListEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub compressed_size: Option<u64>,
+    pub method: String,
+    pub modified: String,
+    pub crc: Option<u32>,
+    pub encrypted: bool,
+}
+
+/// Sums over an archive listing. For tarballs `compressed_size` is the archive file size.
+#[derive(Serialize, Default, Debug)]
+pub struct ArchiveListTotals {
+    pub files: u64,
+    pub dirs: u64,
+    pub size: u64,
+    pub compressed_size: u64,
+    pub encrypted: u64,
+}
+
+#[derive(Serialize)]
+pub struct ArchiveListing {
+    pub format: ArchiveFormat,
+    pub entries: Vec<ArchiveListEntry>,
+    pub totals: ArchiveListTotals,
+}
+
+/// Result of reading one entry back; `error` is empty when the entry is intact. An empty
+/// `name` stands for damage outside any entry, such as a broken compression trailer.
+#[derive(Serialize, Clone, Debug)]
+pub struct ArchiveTestEntry {
+    pub name: String,
+    pub ok: bool,
+    pub error: String,
+}
+
+impl ArchiveTestEntry {
+    pub fn new(name: impl Into<String>, result: Result<(), String>) -> Self {
+        let error = result.err().unwrap_or_default();
+        ArchiveTestEntry {
+            name: name.into(),
+            ok: error.is_empty(),
+            error,
+        }
+    }
+}
+
+/// Outcome of `archive_test`; `count` and `bytes` cover the entries read back.
+#[derive(Serialize, Debug)]
+pub struct ArchiveTestReport {
+    pub entries: Vec<ArchiveTestEntry>,
+    pub count: u64,
+    pub failed: u64,
+    pub bytes: u64,
+}
+
 /// Directory statistics result.
 #[derive(Serialize)]
 pub struct DirStats {
@@ -66,3 +530,32 @@ pub struct DirStats {
     pub dirs: u64,
     pub timed_out: bool,
 }
+
+/// A folder child that could not be read while listing; the rest of the listing goes on.
+#[derive(Clone, Serialize)]
+pub struct DirListingError {
+    pub path: String,
+    pub code: String,
+    pub error: String,
+}
+
+/// Entries of a streamed listing in the order they were read, sent as the folder is walked.
+#[derive(Clone, Serialize)]
+pub struct DirListingBatch {
+    pub listing_id: u64,
+    pub entries: Vec<Entry>,
+    pub errors: Vec<DirListingError>,
+    /// Entries read so far, this batch included.
+    pub loaded: usize,
+    pub done: bool,
+}
+
+/// A sorted slice of a listing snapshot. `errors` always holds every failed child.
+#[derive(Serialize)]
+pub struct DirListingPage {
+    pub listing_id: u64,
+    pub offset: usize,
+    pub total: usize,
+    pub entries: Vec<Entry>,
+    pub errors: Vec<DirListingError>,
+}
@@ -1,2 +1,5 @@
 pub const EVENT_OP_PROGRESS: &str = "op_progress";
 pub const EVENT_FS_CHANGED: &str = "fs_changed";
+pub const EVENT_DUPLICATES_PROGRESS: &str = "duplicates_progress";
+pub const EVENT_ARCHIVE_PROGRESS: &str = "archive_progress";
+pub const EVENT_DIR_LISTING: &str = "dir_listing";
@@ -5,9 +5,23 @@ mod files;
 
 pub use config::{
     AppConfig, FileIconMode, HistoryFile, JumpItem, JumpListFile, KeymapProfile, Language, Theme,
+    ZipMethod,
+};
+pub use dto::{
+    ArchiveExtractOptions, ArchiveFormat, ArchiveListEntry, ArchiveListTotals, ArchiveListing,
+    ArchiveOp, ArchiveProgress, ArchiveSummary, ArchiveTestEntry, ArchiveTestReport,
+    AttributeChanges, CompareCounts, CompareNode, CompareOptions, CompareResult, CompareSide,
+    CompareStatus, ConflictPolicy, DirListingBatch, DirListingError, DirListingPage, DirStats,
+    DuplicateGroup, DuplicateOptions, DuplicateProgress, DuplicateResult, DuplicateStage,
+    HardlinkReplace, OpFailure, OpKind, OpProgress, OpStatus, OpSummary, SyncAction,
+    SyncActionKind, SyncCompare, SyncDirection, SyncMode, SyncOptions, SyncPlan, SystemTrashEntry,
+    TemplateEntry, TemplateList, UndoTrashEntry, ZipCreateOptions, ZipNameEncoding,
+    ZipUpdateOptions,
+};
+pub use events::{
+    EVENT_ARCHIVE_PROGRESS, EVENT_DIR_LISTING, EVENT_DUPLICATES_PROGRESS, EVENT_FS_CHANGED,
+    EVENT_OP_PROGRESS,
 };
-pub use dto::{DirStats, OpFailure, OpKind, OpProgress, OpStatus, OpSummary};
-pub use events::{EVENT_FS_CHANGED, EVENT_OP_PROGRESS};
 pub use files::{
     Entry, EntryType, Properties, PropertyKind, ProviderCapabilities, ResourceRef, SortKey,
     SortOrder, StorageProvider,
@@ -0,0 +1,781 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{format_error, AppErrorKind};
+use crate::fs_ops_mutate_helpers::{
+    create_symlink, io_error_code, move_recursively, move_to_undo_trash, record_failure,
+    undo_trash_stamp,
+};
+use crate::fs_ops_preflight::{
+    preflight_journal_create, preflight_journal_remove, preflight_journal_transfer, PreflightError,
+    CREATE_KINDS,
+};
+use crate::fs_ops_transfer_helpers::copy_recursively;
+use crate::types::OpSummary;
+use crate::utils::to_hex;
+
+const UNDO_JOURNAL_VERSION: u32 = 1;
+const UNDO_JOURNAL_MAX_ENTRIES: usize = 100;
+static UNDO_JOURNAL_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct JournalPair {
+    pub from: String,
+    pub to: String,
+}
+
+/// One file operation, recorded with enough data to invert it.
+/// `Delete` pairs point from the original path to its undo-trash location.
+/// `Copy` pairs list only the paths the copy created, not folders it merged into.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum JournalOp {
+    Copy {
+        pairs: Vec<JournalPair>,
+    },
+    Move {
+        pairs: Vec<JournalPair>,
+    },
+    Rename {
+        from: String,
+        to: String,
+    },
+    Create {
+        path: String,
+        create_kind: String,
+        /// Link target as given, for `symlink` and `hardlink` creates.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target: Option<String>,
+    },
+    Delete {
+        pairs: Vec<JournalPair>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct JournalRecord {
+    pub id: u64,
+    pub recorded_at: String,
+    #[serde(flatten)]
+    pub op: JournalOp,
+}
+
+/// Undo/redo stacks, newest record first.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct UndoJournal {
+    pub undo: Vec<JournalRecord>,
+    pub redo: Vec<JournalRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalFile {
+    version: u32,
+    checksum: String,
+    undo: Vec<JournalRecord>,
+    redo: Vec<JournalRecord>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum JournalDirection {
+    Undo,
+    Redo,
+}
+
+impl JournalDirection {
+    fn as_str(self) -> &'static str {
+        match self {
+            JournalDirection::Undo => "undo",
+            JournalDirection::Redo => "redo",
+        }
+    }
+}
+
+enum JournalStep {
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Trash {
+        path: PathBuf,
+    },
+    Create {
+        path: PathBuf,
+        kind: String,
+        target: Option<String>,
+    },
+}
+
+impl JournalStep {
+    fn label(&self) -> String {
+        match self {
+            JournalStep::Move { from, .. } | JournalStep::Copy { from, .. } => {
+                from.to_string_lossy().to_string()
+            }
+            JournalStep::Trash { path } | JournalStep::Create { path, .. } => {
+                path.to_string_lossy().to_string()
+            }
+        }
+    }
+
+    fn preflight(&self) -> Result<(), PreflightError> {
+        match self {
+            JournalStep::Move { from, to } | JournalStep::Copy { from, to } => {
+                preflight_journal_transfer(from, to)
+            }
+            JournalStep::Trash { path } => preflight_journal_remove(path),
+            JournalStep::Create { path, kind, .. } => preflight_journal_create(path, kind),
+        }
+    }
+
+    fn execute(&self, stamp: u128, index: usize) -> std::io::Result<()> {
+        match self {
+            JournalStep::Move { from, to } => {
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                move_recursively(from, to)?;
+                crate::fs_ops_undo_trash::sync_undo_trash_after_move(from, to);
+                Ok(())
+            }
+            JournalStep::Copy { from, to } => copy_recursively(from, to),
+            JournalStep::Trash { path } => move_to_undo_trash(path, stamp, index).map(|_| ()),
+            JournalStep::Create { path, kind, target } => {
+                let link_target = || {
+                    let parent = path.parent().unwrap_or(Path::new(""));
+                    let text = target.as_deref().unwrap_or_default();
+                    (PathBuf::from(text), parent.join(text))
+                };
+                match kind.as_str() {
+                    "folder" => fs::create_dir(path),
+                    "symlink" => {
+                        let (text, resolved) = link_target();
+                        create_symlink(&text, path, resolved.is_dir())
+                    }
+                    "hardlink" => fs::hard_link(link_target().1, path),
+                    _ => fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(path)
+                        .map(|_| ()),
+                }
+            }
+        }
+    }
+}
+
+impl JournalOp {
+    fn pair_count(&self) -> usize {
+        match self {
+            JournalOp::Copy { pairs } | JournalOp::Move { pairs } | JournalOp::Delete { pairs } => {
+                pairs.len()
+            }
+            JournalOp::Rename { .. } | JournalOp::Create { .. } => 1,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        let valid_path = |value: &str| !value.trim().is_empty() && Path::new(value).is_absolute();
+        match self {
+            JournalOp::Copy { pairs } | JournalOp::Move { pairs } | JournalOp::Delete { pairs } => {
+                !pairs.is_empty()
+                    && pairs
+                        .iter()
+                        .all(|pair| valid_path(&pair.from) && valid_path(&pair.to))
+            }
+            JournalOp::Rename { from, to } => valid_path(from) && valid_path(to),
+            JournalOp::Create {
+                path,
+                create_kind,
+                target,
+            } => {
+                let is_link = create_kind == "symlink" || create_kind == "hardlink";
+                valid_path(path)
+                    && CREATE_KINDS.contains(&create_kind.as_str())
+                    && is_link == target.is_some()
+            }
+        }
+    }
+
+    fn steps(&self, direction: JournalDirection) -> Vec<JournalStep> {
+        let undo = direction == JournalDirection::Undo;
+        let transfer = |pair: &JournalPair| {
+            if undo {
+                JournalStep::Move {
+                    from: PathBuf::from(&pair.to),
+                    to: PathBuf::from(&pair.from),
+                }
+            } else {
+                JournalStep::Move {
+                    from: PathBuf::from(&pair.from),
+                    to: PathBuf::from(&pair.to),
+                }
+            }
+        };
+        match self {
+            JournalOp::Copy { pairs } => pairs
+                .iter()
+                .map(|pair| {
+                    if undo {
+                        JournalStep::Trash {
+                            path: PathBuf::from(&pair.to),
+                        }
+                    } else {
+                        JournalStep::Copy {
+                            from: PathBuf::from(&pair.from),
+                            to: PathBuf::from(&pair.to),
+                        }
+                    }
+                })
+                .collect(),
+            JournalOp::Move { pairs } | JournalOp::Delete { pairs } => {
+                pairs.iter().map(transfer).collect()
+            }
+            JournalOp::Rename { from, to } => vec![transfer(&JournalPair {
+                from: from.clone(),
+                to: to.clone(),
+            })],
+            JournalOp::Create {
+                path,
+                create_kind,
+                target,
+            } => {
+                if undo {
+                    vec![JournalStep::Trash {
+                        path: PathBuf::from(path),
+                    }]
+                } else {
+                    vec![JournalStep::Create {
+                        path: PathBuf::from(path),
+                        kind: create_kind.clone(),
+                        target: target.clone(),
+                    }]
+                }
+            }
+        }
+    }
+
+    /// Keeps the pairs whose flag matches `keep`; `None` when nothing is left.
+    fn select(&self, flags: &[bool], keep: bool) -> Option<JournalOp> {
+        let pick = |pairs: &[JournalPair]| -> Vec<JournalPair> {
+            pairs
+                .iter()
+                .zip(flags.iter())
+                .filter(|(_, flag)| **flag == keep)
+                .map(|(pair, _)| pair.clone())
+                .collect()
+        };
+        let op = match self {
+            JournalOp::Copy { pairs } => JournalOp::Copy { pairs: pick(pairs) },
+            JournalOp::Move { pairs } => JournalOp::Move { pairs: pick(pairs) },
+            JournalOp::Delete { pairs } => JournalOp::Delete { pairs: pick(pairs) },
+            JournalOp::Rename { .. } | JournalOp::Create { .. } => {
+                return if flags.first().copied() == Some(keep) {
+                    Some(self.clone())
+                } else {
+                    None
+                };
+            }
+        };
+        if op.pair_count() == 0 {
+            None
+        } else {
+            Some(op)
+        }
+    }
+}
+
+fn journal_checksum(undo: &[JournalRecord], redo: &[JournalRecord]) -> Result<String, String> {
+    let payload = serde_json::to_vec(&(undo, redo))
+        .map_err(|e| format_error(AppErrorKind::Unknown, format!("serialize failed: {e}")))?;
+    Ok(to_hex(&Sha256::digest(&payload)))
+}
+
+fn journal_path() -> PathBuf {
+    crate::config::config_path()
+        .parent()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("undo_journal.json")
+}
+
+fn trim_journal(journal: &mut UndoJournal) {
+    journal.undo.truncate(UNDO_JOURNAL_MAX_ENTRIES);
+    journal.redo.truncate(UNDO_JOURNAL_MAX_ENTRIES);
+}
+
+fn next_record_id(journal: &UndoJournal) -> u64 {
+    journal
+        .undo
+        .iter()
+        .chain(journal.redo.iter())
+        .map(|record| record.id)
+        .max()
+        .unwrap_or(0)
+        + 1
+}
+
+/// Reads a journal file and verifies its checksum. A missing file is an empty journal.
+pub fn load_journal_from(path: &Path) -> Result<UndoJournal, String> {
+    if !path.exists() {
+        return Ok(UndoJournal::default());
+    }
+    let raw = fs::read_to_string(path)
+        .map_err(|e| format_error(AppErrorKind::Io, format!("read undo journal failed: {e}")))?;
+    let file: JournalFile = serde_json::from_str(&raw).map_err(|e| {
+        format_error(
+            AppErrorKind::Conflict,
+            format!("undo journal is corrupted: {e}"),
+        )
+    })?;
+    if file.version != UNDO_JOURNAL_VERSION {
+        return Err(format_error(
+            AppErrorKind::Conflict,
+            format!("unsupported undo journal version: {}", file.version),
+        ));
+    }
+    if journal_checksum(&file.undo, &file.redo)? != file.checksum {
+        return Err(format_error(
+            AppErrorKind::Conflict,
+            "undo journal checksum mismatch",
+        ));
+    }
+    let mut journal = UndoJournal {
+        undo: file.undo,
+        redo: file.redo,
+    };
+    journal.undo.retain(|record| record.op.is_valid());
+    journal.redo.retain(|record| record.op.is_valid());
+    trim_journal(&mut journal);
+    Ok(journal)
+}
+
+pub fn save_journal_to(path: &Path, journal: &UndoJournal) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| {
+            format_error(
+                AppErrorKind::Io,
+                format!("create undo journal directory failed: {e}"),
+            )
+        })?;
+    }
+    let file = JournalFile {
+        version: UNDO_JOURNAL_VERSION,
+        checksum: journal_checksum(&journal.undo, &journal.redo)?,
+        undo: journal.undo.clone(),
+        redo: journal.redo.clone(),
+    };
+    let serialized = serde_json::to_string(&file)
+        .map_err(|e| format_error(AppErrorKind::Unknown, format!("serialize failed: {e}")))?;
+    // Write next to the journal and swap in, so a crash never leaves a half-written file.
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serialized)
+        .map_err(|e| format_error(AppErrorKind::Io, format!("write undo journal failed: {e}")))?;
+    fs::rename(&tmp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp_path);
+        format_error(AppErrorKind::Io, format!("write undo journal failed: {e}"))
+    })?;
+    Ok(())
+}
+
+/// Loads the journal, setting a damaged file aside and starting over instead of failing.
+fn load_journal_or_recover(path: &Path) -> UndoJournal {
+    match load_journal_from(path) {
+        Ok(journal) => journal,
+        Err(err) => {
+            crate::log_error("undo_journal", &path.to_string_lossy(), "-", &err);
+            let _ = fs::rename(path, path.with_extension("json.corrupt"));
+            UndoJournal::default()
+        }
+    }
+}
+
+/// Appends a completed operation to the undo stack and clears the redo stack.
+/// Journal failures are logged; they never fail the operation itself.
+pub fn record(op: JournalOp) {
+    if !op.is_valid() {
+        return;
+    }
+    let Ok(_guard) = UNDO_JOURNAL_LOCK.lock() else {
+        return;
+    };
+    let path = journal_path();
+    let mut journal = load_journal_or_recover(&path);
+    let record = JournalRecord {
+        id: next_record_id(&journal),
+        recorded_at: Local::now().to_rfc3339(),
+        op,
+    };
+    journal.undo.insert(0, record);
+    journal.redo.clear();
+    trim_journal(&mut journal);
+    if let Err(err) = save_journal_to(&path, &journal) {
+        crate::log_error("undo_journal", &path.to_string_lossy(), "-", &err);
+    }
+}
+
+pub fn load_journal(limit: usize) -> Result<UndoJournal, String> {
+    let _guard = UNDO_JOURNAL_LOCK
+        .lock()
+        .map_err(|_| format_error(AppErrorKind::Unknown, "undo journal lock failed"))?;
+    let mut journal = load_journal_or_recover(&journal_path());
+    journal.undo.truncate(limit);
+    journal.redo.truncate(limit);
+    Ok(journal)
+}
+
+pub fn clear_journal() -> Result<(), String> {
+    let _guard = UNDO_JOURNAL_LOCK
+        .lock()
+        .map_err(|_| format_error(AppErrorKind::Unknown, "undo journal lock failed"))?;
+    save_journal_to(&journal_path(), &UndoJournal::default())
+}
+
+fn forget_trash_buckets_in(journal: &mut UndoJournal, buckets: &[PathBuf]) -> bool {
+    let mut changed = false;
+    journal.undo.retain_mut(|record| {
+        let JournalOp::Delete { pairs } = &mut record.op else {
+            return true;
+        };
+        let len = pairs.len();
+        pairs.retain(|pair| {
+            let trashed = Path::new(&pair.to);
+            !buckets.iter().any(|bucket| trashed.starts_with(bucket))
+        });
+        changed |= pairs.len() != len;
+        !pairs.is_empty()
+    });
+    changed
+}
+
+/// Drops undo steps that would bring items back from `buckets`, after the undo trash
+/// restored or purged them on its own.
+pub fn forget_trash_buckets(buckets: &[PathBuf]) {
+    if buckets.is_empty() {
+        return;
+    }
+    let Ok(_guard) = UNDO_JOURNAL_LOCK.lock() else {
+        return;
+    };
+    let path = journal_path();
+    let mut journal = load_journal_or_recover(&path);
+    if !forget_trash_buckets_in(&mut journal, buckets) {
+        return;
+    }
+    if let Err(err) = save_journal_to(&path, &journal) {
+        crate::log_error("undo_journal", &path.to_string_lossy(), "-", &err);
+    }
+}
+
+/// Pairs for the paths that copying `from` to `to` will create, taken before the copy runs.
+/// A folder copied into an existing folder is walked, so undo only removes what the copy
+/// added; files it overwrites are not listed.
+pub fn copy_created_pairs(from: &Path, to: &Path) -> Vec<JournalPair> {
+    let mut pairs = Vec::new();
+    collect_created_pairs(from, to, &mut pairs);
+    pairs
+}
+
+fn collect_created_pairs(from: &Path, to: &Path, pairs: &mut Vec<JournalPair>) {
+    if fs::symlink_metadata(to).is_err() {
+        pairs.push(JournalPair {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+        });
+        return;
+    }
+    if !from.is_dir() || !to.is_dir() {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(from) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        collect_created_pairs(&entry.path(), &to.join(entry.file_name()), pairs);
+    }
+}
+
+/// Runs the inverse (undo) or the original (redo) of `op`.
+///
+/// Every step is preflighted before anything is touched. Returns the summary together
+/// with the pairs that succeeded and the pairs that still need to be applied.
+pub fn execute_op(
+    op: &JournalOp,
+    direction: JournalDirection,
+) -> Result<(OpSummary, Option<JournalOp>, Option<JournalOp>), String> {
+    let steps = op.steps(direction);
+    for step in &steps {
+        if let Err(err) = step.preflight() {
+            return Err(format!("code={}; {}", err.code, err.message));
+        }
+    }
+    let stamp = undo_trash_stamp();
+    let total = steps.len();
+    let mut ok = 0u64;
+    let mut failed = 0u64;
+    let mut failures = Vec::new();
+    let mut flags = Vec::with_capacity(total);
+    for (index, step) in steps.iter().enumerate() {
+        let label = step.label();
+        match step.execute(stamp, index) {
+            Ok(()) => {
+                ok += 1;
+                flags.push(true);
+            }
+            Err(err) => {
+                let message = err.to_string();
+                crate::log_error(direction.as_str(), &label, "-", &message);
+                failed += 1;
+                record_failure(&mut failures, &label, io_error_code(&err), &message);
+                flags.push(false);
+            }
+        }
+    }
+    let summary = OpSummary {
+        ok,
+        failed,
+        total: total as u64,
+        failures,
+    };
+    Ok((summary, op.select(&flags, true), op.select(&flags, false)))
+}
+
+/// Pops the newest record of one stack, applies it and moves it onto the other stack.
+/// Pairs that fail stay on the original stack so they can be retried.
+pub fn apply_next(direction: JournalDirection) -> Result<OpSummary, String> {
+    let _guard = UNDO_JOURNAL_LOCK
+        .lock()
+        .map_err(|_| format_error(AppErrorKind::Unknown, "undo journal lock failed"))?;
+    let path = journal_path();
+    let mut journal = load_journal_or_recover(&path);
+    let (source, target) = match direction {
+        JournalDirection::Undo => (&mut journal.undo, &mut journal.redo),
+        JournalDirection::Redo => (&mut journal.redo, &mut journal.undo),
+    };
+    if source.is_empty() {
+        return Err(format_error(
+            AppErrorKind::NotFound,
+            format!("nothing to {}", direction.as_str()),
+        ));
+    }
+    let record = source.remove(0);
+    let (summary, done, remaining) = match execute_op(&record.op, direction) {
+        Ok(result) => result,
+        Err(err) => {
+            crate::log_error(direction.as_str(), &record.id.to_string(), "-", &err);
+            return Err(err);
+        }
+    };
+    if let Some(op) = remaining {
+        source.insert(
+            0,
+            JournalRecord {
+                op,
+                ..record.clone()
+            },
+        );
+    }
+    if let Some(op) = done {
+        target.insert(0, JournalRecord { op, ..record });
+    }
+    trim_journal(&mut journal);
+    save_journal_to(&path, &journal)?;
+    crate::log_event(
+        &direction.as_str().to_ascii_uppercase(),
+        &summary.total.to_string(),
+        "-",
+        &format!("ok={}; failed={}", summary.ok, summary.failed),
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        copy_created_pairs, execute_op, forget_trash_buckets_in, load_journal_from,
+        save_journal_to, JournalDirection, JournalOp, JournalPair, JournalRecord, UndoJournal,
+    };
+    use crate::test_support::unique_temp_dir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn sample_journal(dir: &std::path::Path) -> UndoJournal {
+        UndoJournal {
+            undo: vec![JournalRecord {
+                id: 1,
+                recorded_at: "2026-01-01T00:00:00+00:00".to_string(),
+                op: JournalOp::Move {
+                    pairs: vec![JournalPair {
+                        from: dir.join("a.txt").to_string_lossy().to_string(),
+                        to: dir.join("sub").join("a.txt").to_string_lossy().to_string(),
+                    }],
+                },
+            }],
+            redo: vec![],
+        }
+    }
+
+    #[test]
+    fn journal_round_trips_through_disk() {
+        let dir = unique_temp_dir("rf-undo-journal-roundtrip");
+        let path = dir.join("undo_journal.json");
+        let journal = sample_journal(&dir);
+        save_journal_to(&path, &journal).expect("save journal");
+        let loaded = load_journal_from(&path).expect("load journal");
+        assert_eq!(loaded.undo, journal.undo);
+        assert!(loaded.redo.is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn journal_rejects_tampered_file() {
+        let dir = unique_temp_dir("rf-undo-journal-tamper");
+        let path = dir.join("undo_journal.json");
+        save_journal_to(&path, &sample_journal(&dir)).expect("save journal");
+        let raw = fs::read_to_string(&path).expect("read journal");
+        fs::write(&path, raw.replace("a.txt", "b.txt")).expect("tamper journal");
+        let err = load_journal_from(&path).expect_err("must reject tampered journal");
+        assert!(err.starts_with("code=conflict; "));
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn rename_undo_and_redo_invert_each_other() {
+        let dir = unique_temp_dir("rf-undo-journal-rename");
+        let from = dir.join("before.txt");
+        let to = dir.join("after.txt");
+        fs::write(&to, b"data").expect("write renamed file");
+        let op = JournalOp::Rename {
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+        };
+
+        let (summary, done, remaining) =
+            execute_op(&op, JournalDirection::Undo).expect("undo rename");
+        assert_eq!(summary.ok, 1);
+        assert_eq!(done, Some(op.clone()));
+        assert!(remaining.is_none());
+        assert!(from.exists());
+        assert!(!to.exists());
+
+        execute_op(&op, JournalDirection::Redo).expect("redo rename");
+        assert!(!from.exists());
+        assert!(to.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn redo_create_recreates_relative_symlink() {
+        let dir = unique_temp_dir("rf-undo-journal-symlink");
+        fs::write(dir.join("target.txt"), b"data").expect("write target");
+        let link = dir.join("link.txt");
+        let op = JournalOp::Create {
+            path: link.to_string_lossy().to_string(),
+            create_kind: "symlink".to_string(),
+            target: Some("target.txt".to_string()),
+        };
+
+        execute_op(&op, JournalDirection::Redo).expect("redo create");
+        assert_eq!(
+            fs::read_link(&link).expect("read link"),
+            PathBuf::from("target.txt")
+        );
+        assert_eq!(fs::read(&link).expect("read through link"), b"data");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn undo_preflight_fails_without_touching_anything() {
+        let dir = unique_temp_dir("rf-undo-journal-preflight");
+        let moved = dir.join("sub").join("a.txt");
+        let blocker = dir.join("a.txt");
+        fs::create_dir_all(moved.parent().expect("parent")).expect("create sub");
+        fs::write(&moved, b"moved").expect("write moved");
+        fs::write(&blocker, b"blocker").expect("write blocker");
+        let err = match execute_op(&sample_journal(&dir).undo[0].op, JournalDirection::Undo) {
+            Ok(_) => panic!("target exists"),
+            Err(err) => err,
+        };
+        assert!(err.starts_with("code=journal_target_exists; "));
+        assert!(moved.exists());
+        assert_eq!(fs::read(&blocker).expect("read blocker"), b"blocker");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn copy_into_existing_folder_records_only_created_paths() {
+        let dir = unique_temp_dir("rf-undo-journal-copy-merge");
+        let from = dir.join("src").join("docs");
+        let to = dir.join("dest").join("docs");
+        fs::create_dir_all(from.join("new")).expect("create src");
+        fs::create_dir_all(&to).expect("create dest");
+        fs::write(from.join("old.txt"), b"copied").expect("write src old");
+        fs::write(from.join("new").join("a.txt"), b"a").expect("write src new");
+        fs::write(from.join("b.txt"), b"b").expect("write src b");
+        fs::write(to.join("old.txt"), b"before").expect("write dest old");
+        fs::write(to.join("keep.txt"), b"keep").expect("write dest keep");
+
+        let mut pairs = copy_created_pairs(&from, &to);
+        pairs.sort_by(|a, b| a.to.cmp(&b.to));
+        let created: Vec<PathBuf> = pairs.iter().map(|pair| PathBuf::from(&pair.to)).collect();
+        assert_eq!(created, [to.join("b.txt"), to.join("new")]);
+        assert_eq!(
+            copy_created_pairs(&from, &dir.join("fresh")),
+            [JournalPair {
+                from: from.to_string_lossy().to_string(),
+                to: dir.join("fresh").to_string_lossy().to_string(),
+            }]
+        );
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn forgetting_trash_buckets_drops_their_delete_pairs() {
+        let pair = |name: &str, bucket: &str| JournalPair {
+            from: format!("/data/{name}"),
+            to: format!("/trash/{bucket}/{name}"),
+        };
+        let record = |id: u64, op: JournalOp| JournalRecord {
+            id,
+            recorded_at: "2026-01-01T00:00:00+00:00".to_string(),
+            op,
+        };
+        let mut journal = UndoJournal {
+            undo: vec![
+                record(
+                    2,
+                    JournalOp::Delete {
+                        pairs: vec![pair("a.txt", "1_0"), pair("b.txt", "1_1")],
+                    },
+                ),
+                record(
+                    1,
+                    JournalOp::Delete {
+                        pairs: vec![pair("c.txt", "0_0")],
+                    },
+                ),
+            ],
+            redo: vec![],
+        };
+        let buckets = [PathBuf::from("/trash/1_0"), PathBuf::from("/trash/0_0")];
+        assert!(forget_trash_buckets_in(&mut journal, &buckets));
+        assert_eq!(journal.undo.len(), 1);
+        assert_eq!(
+            journal.undo[0].op,
+            JournalOp::Delete {
+                pairs: vec![pair("b.txt", "1_1")]
+            }
+        );
+        assert!(!forget_trash_buckets_in(&mut journal, &buckets));
+    }
+}
@@ -0,0 +1,22 @@
+use crate::types::OpSummary;
+use crate::undo_journal::{apply_next, clear_journal, load_journal, JournalDirection, UndoJournal};
+
+#[tauri::command]
+pub fn undo_journal_get(limit: Option<usize>) -> Result<UndoJournal, String> {
+    load_journal(limit.unwrap_or(50).clamp(1, 500))
+}
+
+#[tauri::command]
+pub fn undo_journal_clear() -> Result<(), String> {
+    clear_journal()
+}
+
+#[tauri::command]
+pub fn undo_apply() -> Result<OpSummary, String> {
+    apply_next(JournalDirection::Undo)
+}
+
+#[tauri::command]
+pub fn redo_apply() -> Result<OpSummary, String> {
+    apply_next(JournalDirection::Redo)
+}
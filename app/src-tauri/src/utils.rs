@@ -1,4 +1,5 @@
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 
 pub fn system_time_to_rfc3339(value: Option<std::time::SystemTime>) -> String {
     value
@@ -27,3 +28,189 @@ pub fn is_hidden(path: &Path) -> bool {
         .map(|n| n.starts_with('.'))
         .unwrap_or(false)
 }
+
+/// Volume and file id of `path`, used to recognize hardlinks to the same file.
+#[cfg(unix)]
+pub fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Volume and file id of `path`, used to recognize hardlinks to the same file.
+#[cfg(target_os = "windows")]
+pub fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+    };
+    let file = std::fs::File::open(path).ok()?;
+    let mut info = BY_HANDLE_FILE_INFORMATION::default();
+    unsafe { GetFileInformationByHandle(HANDLE(file.as_raw_handle()), &mut info) }.ok()?;
+    let index = ((info.nFileIndexHigh as u64) << 32) | info.nFileIndexLow as u64;
+    Some((info.dwVolumeSerialNumber as u64, index))
+}
+
+/// Whether two existing paths live on the same volume; `None` when it cannot be told.
+#[cfg(unix)]
+pub fn same_volume(a: &Path, b: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Some(std::fs::metadata(a).ok()?.dev() == std::fs::metadata(b).ok()?.dev())
+}
+
+/// Whether two existing paths live on the same volume; `None` when it cannot be told.
+#[cfg(target_os = "windows")]
+pub fn same_volume(a: &Path, b: &Path) -> Option<bool> {
+    use std::path::Component;
+    let prefix = |path: &Path| -> Option<String> {
+        match std::fs::canonicalize(path).ok()?.components().next()? {
+            Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().to_lowercase()),
+            _ => None,
+        }
+    };
+    Some(prefix(a)? == prefix(b)?)
+}
+
+/// Bytes available to the current user on the volume holding `path`.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    (stat.f_bavail as u64).checked_mul(stat.f_frsize as u64)
+}
+
+/// Bytes available to the current user on the volume holding `path`.
+#[cfg(target_os = "windows")]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut free = 0u64;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR(wide.as_ptr()),
+            Some(&mut free as *mut u64),
+            None,
+            None,
+        )
+    }
+    .ok()?;
+    Some(free)
+}
+
+/// Lowercase hex of `bytes`, as used for checksums.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hex SHA-256 of a file's contents, read in chunks.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Parses an octal permission string such as `"755"` or `"0644"`.
+pub fn parse_mode(value: &str) -> Option<u32> {
+    let trimmed = value.trim().trim_start_matches("0o");
+    if trimmed.is_empty() || trimmed.len() > 4 {
+        return None;
+    }
+    u32::from_str_radix(trimmed, 8).ok()
+}
+
+pub fn parse_time(value: &str) -> Option<SystemTime> {
+    chrono::DateTime::parse_from_rfc3339(value.trim())
+        .ok()
+        .map(SystemTime::from)
+}
+
+/// FAT and exFAT store modification times with 2 second resolution.
+pub const MTIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Modification times that differ by no more than the FAT tolerance count as equal. A
+/// missing time matches anything.
+pub fn mtimes_match(left: Option<SystemTime>, right: Option<SystemTime>) -> bool {
+    let (Some(left), Some(right)) = (left, right) else {
+        return true;
+    };
+    let diff = left
+        .duration_since(right)
+        .unwrap_or_else(|err| err.duration());
+    diff <= MTIME_TOLERANCE
+}
+
+/// Wildcard match used for ignore/include patterns. `*` and `?` stay within one path
+/// segment, `**` crosses `/`. Matching is ASCII case-insensitive.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(p: &[char], t: &[char]) -> bool {
+        match p.first() {
+            None => t.is_empty(),
+            Some('*') if p.get(1) == Some(&'*') => match p.get(2) {
+                // `**/` matches zero or more whole segments.
+                Some('/') => {
+                    matches(&p[3..], t)
+                        || (1..=t.len()).any(|i| t[i - 1] == '/' && matches(&p[3..], &t[i..]))
+                }
+                _ => (0..=t.len()).any(|i| matches(&p[2..], &t[i..])),
+            },
+            Some('*') => {
+                for i in 0..=t.len() {
+                    if matches(&p[1..], &t[i..]) {
+                        return true;
+                    }
+                    if t.get(i) == Some(&'/') {
+                        break;
+                    }
+                }
+                false
+            }
+            Some('?') => matches!(t.first(), Some(c) if *c != '/') && matches(&p[1..], &t[1..]),
+            Some(c) => {
+                matches!(t.first(), Some(d) if c.eq_ignore_ascii_case(d))
+                    && matches(&p[1..], &t[1..])
+            }
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+/// True when `rel_path` (with `/` separators) matches any pattern. Patterns without a
+/// `/` are tested against every path segment, so `node_modules` or `*.tmp` work anywhere.
+pub fn matches_any_glob(patterns: &[String], rel_path: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim().trim_start_matches("./");
+        if pattern.is_empty() {
+            return false;
+        }
+        if pattern.contains('/') {
+            glob_match(pattern.trim_start_matches('/'), rel_path)
+        } else {
+            rel_path
+                .split('/')
+                .any(|segment| glob_match(pattern, segment))
+        }
+    })
+}
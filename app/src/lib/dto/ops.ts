@@ -1,4 +1,4 @@
-export type OpKind = "copy" | "move";
+export type OpKind = "copy" | "move" | "shred" | "sync" | "split" | "join";
 export type OpStatus = "start" | "fail" | "done";
 
 export interface OpFailure {